@@ -1,9 +1,168 @@
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+
+/// Pins bonded out in a package
+#[derive(Clone, Copy)]
+enum Package {
+    /// TSSOP20 and QFN20, every pin
+    Pin20,
+    /// SOP16
+    Sop16,
+    /// SOP8, several pads share each pin
+    Sop8,
+}
+
+impl Package {
+    fn pins(self) -> &'static [&'static str] {
+        match self {
+            Package::Pin20 => &[
+                "pa1", "pa2", "pc0", "pc1", "pc2", "pc3", "pc4", "pc5", "pc6", "pc7", "pd0", "pd1",
+                "pd2", "pd3", "pd4", "pd5", "pd6", "pd7",
+            ],
+            Package::Sop16 => &[
+                "pa1", "pa2", "pc0", "pc1", "pc2", "pc3", "pc4", "pc6", "pc7", "pd1", "pd4", "pd5",
+                "pd6", "pd7",
+            ],
+            Package::Sop8 => &[
+                "pa1", "pa2", "pc1", "pc2", "pc4", "pd1", "pd4", "pd5", "pd6",
+            ],
+        }
+    }
+}
+
+struct Chip {
+    /// Cargo feature selecting the chip
+    feature: &'static str,
+    /// Flash size in KiB
+    flash: u32,
+    /// RAM size in KiB
+    ram: u32,
+    /// Stack reserved at the top of RAM in KiB
+    stack: u32,
+    package: Package,
+}
+
+const fn chip(feature: &'static str, flash: u32, ram: u32, stack: u32, package: Package) -> Chip {
+    Chip {
+        feature,
+        flash,
+        ram,
+        stack,
+        package,
+    }
+}
+
+/// Every supported part, the first one is used when no feature is enabled
+const CHIPS: &[Chip] = &[
+    chip("ch32v003f4p6", 16, 2, 1, Package::Pin20),
+    chip("ch32v003f4u6", 16, 2, 1, Package::Pin20),
+    chip("ch32v003a4m6", 16, 2, 1, Package::Sop16),
+    chip("ch32v003j4m6", 16, 2, 1, Package::Sop8),
+    chip("ch32v002f4p6", 16, 4, 2, Package::Pin20),
+    chip("ch32v002f4u6", 16, 4, 2, Package::Pin20),
+    chip("ch32v002a4m6", 16, 4, 2, Package::Sop16),
+    chip("ch32v002j4m6", 16, 4, 2, Package::Sop8),
+    chip("ch32v004f6p1", 32, 6, 2, Package::Pin20),
+    chip("ch32v004f6u1", 32, 6, 2, Package::Pin20),
+    chip("ch32v006f8p6", 62, 8, 2, Package::Pin20),
+    chip("ch32v006f8u6", 62, 8, 2, Package::Pin20),
+];
+
+/// Flash reserved at the end for data written at runtime, in KiB
+const STORAGE: u32 = 1;
+
 fn main() {
+    let enabled: Vec<&Chip> = CHIPS
+        .iter()
+        .filter(|chip| {
+            env::var_os(format!("CARGO_FEATURE_{}", chip.feature.to_uppercase())).is_some()
+        })
+        .collect();
+    let chip = match enabled[..] {
+        [] => &CHIPS[0],
+        [chip] => chip,
+        _ => {
+            let names: Vec<&str> = enabled.iter().map(|chip| chip.feature).collect();
+            panic!(
+                "only one chip feature can be enabled, got {}",
+                names.join(", ")
+            );
+        }
+    };
+
+    let out = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+
+    let memory = format!(
+        "\
+PROVIDE(_hart_stack_size = {stack}K);
+
+MEMORY
+{{
+	FLASH (rx)  : ORIGIN = 0x00000000, LENGTH = {code}K
+	STORAGE (r) : ORIGIN = {storage_start:#010x}, LENGTH = {storage}K
+	RAM (xrw)   : ORIGIN = 0x20000000, LENGTH = {ram}K
+}}
+
+REGION_ALIAS(\"REGION_TEXT\", FLASH);
+REGION_ALIAS(\"REGION_RODATA\", FLASH);
+REGION_ALIAS(\"REGION_DATA\", RAM);
+REGION_ALIAS(\"REGION_BSS\", RAM);
+REGION_ALIAS(\"REGION_HEAP\", RAM);
+REGION_ALIAS(\"REGION_STACK\", RAM);
+",
+        stack = chip.stack,
+        code = chip.flash - STORAGE,
+        storage_start = (chip.flash - STORAGE) * 1024,
+        storage = STORAGE,
+        ram = chip.ram,
+    );
+    fs::write(out.join("memory.x"), memory).unwrap();
+
+    let mut pins = String::new();
+    for pin in chip.package.pins() {
+        let (port, number) = pin.split_at(2);
+        write!(pins, "('{}', {number}), ", port[1..].to_uppercase()).unwrap();
+    }
+    let constants = format!(
+        "\
+/// Part number
+pub const NAME: &str = \"{name}\";
+/// Flash size in bytes
+pub const FLASH_SIZE: u32 = {flash};
+/// Start of the `STORAGE` region at the end of flash, as an offset from its
+/// start
+pub const STORAGE_START: u32 = {storage_start};
+/// Size of the `STORAGE` region in bytes
+pub const STORAGE_SIZE: u32 = {storage};
+/// RAM size in bytes
+pub const RAM_SIZE: u32 = {ram};
+/// Pins bonded out in the package, as port and number
+pub const PINS: &[(char, u8)] = &[{pins}];
+",
+        name = chip.feature.to_uppercase(),
+        flash = chip.flash * 1024,
+        storage_start = (chip.flash - STORAGE) * 1024,
+        storage = STORAGE * 1024,
+        ram = chip.ram * 1024,
+    );
+    fs::write(out.join("chip.rs"), constants).unwrap();
+
+    // Pin availability, `#[cfg(pin = "pc5")]`
+    let all = Package::Pin20.pins().join("\", \"");
+    println!("cargo:rustc-check-cfg=cfg(pin, values(\"{all}\"))");
+    for pin in chip.package.pins() {
+        println!("cargo:rustc-cfg=pin=\"{pin}\"");
+    }
+
     // Tell rustc to pass linker scripts to LLD
+    println!("cargo:rustc-link-search={}", out.display());
     println!("cargo:rustc-link-arg=-Tmemory.x");
+    println!("cargo:rustc-link-arg=-Tdevice.x");
     println!("cargo:rustc-link-arg=-Tlink.x");
 
     // Rerun this script only when necesary
-    println!("cargo:rerun-if-changed=memory.x");
+    println!("cargo:rerun-if-changed=device.x");
     println!("cargo:rerun-if-changed=build.rs");
 }
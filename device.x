@@ -0,0 +1,28 @@
+/* Interrupts without a handler fall back to DefaultHandler */
+PROVIDE(NMI = DefaultHandler);
+PROVIDE(HardFault = DefaultHandler);
+PROVIDE(SysTick = DefaultHandler);
+PROVIDE(Software = DefaultHandler);
+PROVIDE(WWDG = DefaultHandler);
+PROVIDE(PVD = DefaultHandler);
+PROVIDE(FLASH = DefaultHandler);
+PROVIDE(RCC = DefaultHandler);
+PROVIDE(EXTI7_0 = DefaultHandler);
+PROVIDE(AWU = DefaultHandler);
+PROVIDE(DMA1_CHANNEL1 = DefaultHandler);
+PROVIDE(DMA1_CHANNEL2 = DefaultHandler);
+PROVIDE(DMA1_CHANNEL3 = DefaultHandler);
+PROVIDE(DMA1_CHANNEL4 = DefaultHandler);
+PROVIDE(DMA1_CHANNEL5 = DefaultHandler);
+PROVIDE(DMA1_CHANNEL6 = DefaultHandler);
+PROVIDE(DMA1_CHANNEL7 = DefaultHandler);
+PROVIDE(ADC = DefaultHandler);
+PROVIDE(I2C1_EV = DefaultHandler);
+PROVIDE(I2C1_ER = DefaultHandler);
+PROVIDE(USART1 = DefaultHandler);
+PROVIDE(SPI1 = DefaultHandler);
+PROVIDE(TIM1_BRK = DefaultHandler);
+PROVIDE(TIM1_UP = DefaultHandler);
+PROVIDE(TIM1_TRG_COM = DefaultHandler);
+PROVIDE(TIM1_CC = DefaultHandler);
+PROVIDE(TIM2 = DefaultHandler);
@@ -0,0 +1,437 @@
+//! Memory map of the CH32V003
+
+use std::collections::HashMap;
+
+use crate::gpio::{self, Gpio, Pin};
+use crate::rcc::{self, Rcc};
+use crate::systick::{self, SysTick};
+use crate::tim::{self, Timer};
+use crate::trace::{Signal, Trace};
+use crate::usart::{self, Usart};
+
+/// Start of the code flash
+pub const FLASH_BASE: u32 = 0x0000_0000;
+/// Size of the code flash
+pub const FLASH_SIZE: u32 = 16 * 1024;
+/// Start of the SRAM
+pub const RAM_BASE: u32 = 0x2000_0000;
+/// Size of the SRAM
+pub const RAM_SIZE: u32 = 2 * 1024;
+
+/// Whether `addr` belongs to a peripheral or the core private region
+fn is_mmio(addr: u32) -> bool {
+    (0x4000_0000..0x4002_4000).contains(&addr) || addr >= 0xE000_0000
+}
+
+/// Ports in the order of [`Bus::gpio`], with their base addresses
+const PORTS: [(char, u32); 3] = [('A', gpio::GPIOA), ('C', gpio::GPIOC), ('D', gpio::GPIOD)];
+
+/// AFIO remap register, the only one that affects the models
+const AFIO_PCFR1: u32 = 0x4001_0004;
+
+/// Pins of TIM1 channels 1 to 4 for each value of TIM1_RM
+const TIM1_PINS: [[Pin; 4]; 4] = [
+    [
+        Pin::new('D', 2),
+        Pin::new('A', 1),
+        Pin::new('C', 3),
+        Pin::new('C', 4),
+    ],
+    [
+        Pin::new('C', 6),
+        Pin::new('C', 7),
+        Pin::new('C', 0),
+        Pin::new('D', 3),
+    ],
+    [
+        Pin::new('D', 2),
+        Pin::new('A', 1),
+        Pin::new('C', 3),
+        Pin::new('C', 4),
+    ],
+    [
+        Pin::new('C', 4),
+        Pin::new('C', 7),
+        Pin::new('C', 5),
+        Pin::new('D', 4),
+    ],
+];
+
+/// Pins of TIM2 channels 1 to 4 for each value of TIM2_RM
+const TIM2_PINS: [[Pin; 4]; 4] = [
+    [
+        Pin::new('D', 4),
+        Pin::new('D', 3),
+        Pin::new('C', 0),
+        Pin::new('D', 7),
+    ],
+    [
+        Pin::new('C', 5),
+        Pin::new('C', 2),
+        Pin::new('D', 2),
+        Pin::new('C', 1),
+    ],
+    [
+        Pin::new('C', 1),
+        Pin::new('D', 3),
+        Pin::new('C', 0),
+        Pin::new('D', 7),
+    ],
+    [
+        Pin::new('C', 1),
+        Pin::new('C', 7),
+        Pin::new('D', 6),
+        Pin::new('D', 5),
+    ],
+];
+
+/// Pin of USART1 TX for each value of USART1_RM1:USART1_RM
+const USART1_TX_PINS: [Pin; 4] = [
+    Pin::new('D', 5),
+    Pin::new('D', 0),
+    Pin::new('D', 6),
+    Pin::new('C', 0),
+];
+
+/// A peripheral output, the pin it is remapped to and the level it drives
+type Output = (Signal, Pin, Option<bool>);
+
+fn port_index(port: char) -> usize {
+    PORTS
+        .iter()
+        .position(|&(p, _)| p == port)
+        .unwrap_or_else(|| panic!("no GPIO port {port}"))
+}
+
+/// Flash, RAM and the peripheral registers
+///
+/// Peripherals without a model keep the last written value of every
+/// register, which is enough for code that only configures them.
+pub struct Bus {
+    flash: Vec<u8>,
+    ram: Vec<u8>,
+    registers: HashMap<u32, u32>,
+    /// Cycle of the instruction currently accessing the bus
+    cycle: u64,
+    pub rcc: Rcc,
+    gpio: [Gpio; 3],
+    pub systick: SysTick,
+    pub tim1: Timer,
+    pub tim2: Timer,
+    pub usart: Usart,
+    /// Peripheral outputs as of the last [`Bus::route`]
+    outputs: [Output; 9],
+    /// Every pin and peripheral output transition so far
+    pub trace: Trace,
+}
+
+impl Default for Bus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Bus {
+    pub fn new() -> Self {
+        Self {
+            flash: vec![0xFF; FLASH_SIZE as usize],
+            ram: vec![0; RAM_SIZE as usize],
+            registers: HashMap::new(),
+            cycle: 0,
+            rcc: Rcc::new(),
+            gpio: [Gpio::new(), Gpio::new(), Gpio::new()],
+            systick: SysTick::default(),
+            tim1: Timer::new(true),
+            tim2: Timer::new(false),
+            usart: Usart::new(),
+            outputs: [(Signal::UsartTx, USART1_TX_PINS[0], None); 9],
+            trace: Trace::default(),
+        }
+    }
+
+    /// Advance the peripherals to `cycle`, one cycle after the last call
+    pub fn tick(&mut self, cycle: u64) {
+        self.cycle = cycle;
+        self.systick.tick();
+        if self.rcc.apb2_enabled(rcc::TIM1EN) {
+            self.tim1.tick();
+        }
+        if self.rcc.apb1_enabled(rcc::TIM2EN) {
+            self.tim2.tick();
+        }
+        if self.rcc.apb2_enabled(rcc::USART1EN) {
+            self.usart.tick();
+        }
+        self.route();
+    }
+
+    /// Peripheral outputs with the current remapping
+    fn outputs(&self) -> [Output; 9] {
+        let pcfr1 = self.registers.get(&AFIO_PCFR1).copied().unwrap_or(0);
+        let tim1 = TIM1_PINS[(pcfr1 >> 6 & 0b11) as usize];
+        let tim2 = TIM2_PINS[(pcfr1 >> 8 & 0b11) as usize];
+        let usart = (pcfr1 >> 2 & 1 | pcfr1 >> 20 & 0b10) as usize;
+
+        let mut outputs = [(Signal::UsartTx, USART1_TX_PINS[usart], self.usart.tx()); 9];
+        for channel in 0..4 {
+            let signal = |timer| Signal::Timer {
+                timer,
+                channel: channel as u8 + 1,
+            };
+            outputs[channel] = (signal(1), tim1[channel], self.tim1.output(channel));
+            outputs[4 + channel] = (signal(2), tim2[channel], self.tim2.output(channel));
+        }
+        outputs
+    }
+
+    /// Record the peripheral outputs and drive the pins they are mapped to
+    fn route(&mut self) {
+        let outputs = self.outputs();
+        if outputs == self.outputs {
+            return;
+        }
+        self.outputs = outputs;
+
+        for (signal, _, level) in outputs {
+            self.trace
+                .record(self.cycle, signal, level.unwrap_or(false));
+        }
+        for (port, _) in PORTS {
+            self.update_port(port, |gpio| {
+                for pin in 0..8 {
+                    gpio.set_alternate(pin, false);
+                }
+                for (_, pin, level) in outputs {
+                    if let (true, Some(level)) = (pin.port == port, level) {
+                        gpio.set_alternate(pin.pin, level);
+                    }
+                }
+            });
+        }
+    }
+
+    pub fn gpio(&self, port: char) -> &Gpio {
+        &self.gpio[port_index(port)]
+    }
+
+    /// Drive a pin from outside the chip, or release it with `None`
+    pub fn set_input(&mut self, pin: Pin, level: Option<bool>) {
+        self.update_port(pin.port, |gpio| gpio.set_external(pin.pin, level));
+    }
+
+    /// Change a port, recording the pins whose level changed
+    fn update_port(&mut self, port: char, f: impl FnOnce(&mut Gpio)) {
+        let gpio = &mut self.gpio[port_index(port)];
+        let before = gpio.levels();
+        f(gpio);
+        let after = gpio.levels();
+
+        for pin in (0..8).filter(|pin| (before ^ after) & 1 << pin != 0) {
+            self.trace
+                .record(self.cycle, Pin::new(port, pin), after & 1 << pin != 0);
+        }
+    }
+
+    /// Backing memory of a range, if it lies entirely in flash or RAM
+    fn memory(&self, addr: u32, len: u32) -> Option<&[u8]> {
+        let (base, memory) = if addr >= RAM_BASE {
+            (RAM_BASE, &self.ram)
+        } else {
+            (FLASH_BASE, &self.flash)
+        };
+        let start = addr.checked_sub(base)? as usize;
+        memory.get(start..start.checked_add(len as usize)?)
+    }
+
+    fn memory_mut(&mut self, addr: u32, len: u32) -> Option<&mut [u8]> {
+        let (base, memory) = if addr >= RAM_BASE {
+            (RAM_BASE, &mut self.ram)
+        } else {
+            (FLASH_BASE, &mut self.flash)
+        };
+        let start = addr.checked_sub(base)? as usize;
+        memory.get_mut(start..start.checked_add(len as usize)?)
+    }
+
+    /// Write to flash or RAM directly, as a programmer would
+    pub fn program(&mut self, addr: u32, data: &[u8]) -> Option<()> {
+        self.memory_mut(addr, data.len() as u32)?
+            .copy_from_slice(data);
+        Some(())
+    }
+
+    /// Read `len` of 1, 2 or 4 bytes
+    pub fn read(&mut self, addr: u32, len: u32) -> Option<u32> {
+        if is_mmio(addr) {
+            return Some(self.read_register(addr, len));
+        }
+
+        let bytes = self.memory(addr, len)?;
+        Some(
+            bytes
+                .iter()
+                .rev()
+                .fold(0, |value, &byte| value << 8 | byte as u32),
+        )
+    }
+
+    /// Write the low `len` bytes of `value`
+    ///
+    /// Flash is only writable through [`Bus::program`].
+    pub fn write(&mut self, addr: u32, len: u32, value: u32) -> Option<()> {
+        if is_mmio(addr) {
+            self.write_register(addr, len, value);
+            return Some(());
+        }
+        if addr < RAM_BASE {
+            return None;
+        }
+
+        let bytes = self.memory_mut(addr, len)?;
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = (value >> (8 * i)) as u8;
+        }
+        Some(())
+    }
+
+    fn read_word(&self, addr: u32) -> u32 {
+        if let Some(offset) = offset(addr, rcc::BASE, rcc::SIZE) {
+            return self.rcc.read(offset);
+        }
+        if let Some(offset) = offset(addr, systick::BASE, systick::SIZE) {
+            return self.systick.read(offset);
+        }
+        // Peripherals without their clock read as zero
+        if let Some(offset) = offset(addr, tim::TIM1, tim::SIZE) {
+            return if self.rcc.apb2_enabled(rcc::TIM1EN) {
+                self.tim1.read(offset)
+            } else {
+                0
+            };
+        }
+        if let Some(offset) = offset(addr, tim::TIM2, tim::SIZE) {
+            return if self.rcc.apb1_enabled(rcc::TIM2EN) {
+                self.tim2.read(offset)
+            } else {
+                0
+            };
+        }
+        if let Some(offset) = offset(addr, usart::BASE, usart::SIZE) {
+            return if self.rcc.apb2_enabled(rcc::USART1EN) {
+                self.usart.read(offset)
+            } else {
+                0
+            };
+        }
+        for (i, &(port, base)) in PORTS.iter().enumerate() {
+            if let Some(offset) = offset(addr, base, gpio::SIZE) {
+                if !self.rcc.apb2_enabled(rcc::gpio_bit(port)) {
+                    return 0;
+                }
+                return self.gpio[i].read(offset);
+            }
+        }
+        self.registers.get(&addr).copied().unwrap_or(0)
+    }
+
+    fn write_word(&mut self, addr: u32, value: u32) {
+        self.write_peripheral(addr, value);
+        // Outputs may have been enabled, remapped or forced
+        self.route();
+    }
+
+    fn write_peripheral(&mut self, addr: u32, value: u32) {
+        if let Some(offset) = offset(addr, rcc::BASE, rcc::SIZE) {
+            self.rcc.write(offset, value);
+            // Peripherals held in reset return to their reset state
+            for (port, _) in PORTS {
+                if self.rcc.apb2_in_reset(rcc::gpio_bit(port)) {
+                    self.update_port(port, Gpio::reset);
+                }
+            }
+            if self.rcc.apb2_in_reset(rcc::TIM1EN) {
+                self.tim1 = Timer::new(true);
+            }
+            if self.rcc.apb1_in_reset(rcc::TIM2EN) {
+                self.tim2 = Timer::new(false);
+            }
+            if self.rcc.apb2_in_reset(rcc::USART1EN) {
+                self.usart = Usart::new();
+            }
+            return;
+        }
+        if let Some(offset) = offset(addr, systick::BASE, systick::SIZE) {
+            return self.systick.write(offset, value);
+        }
+
+        // Writes are ignored while a peripheral has no clock or is held in
+        // reset
+        if let Some(offset) = offset(addr, tim::TIM1, tim::SIZE) {
+            if self.rcc.apb2_active(rcc::TIM1EN) {
+                self.tim1.write(offset, value);
+            }
+            return;
+        }
+        if let Some(offset) = offset(addr, tim::TIM2, tim::SIZE) {
+            if self.rcc.apb1_active(rcc::TIM2EN) {
+                self.tim2.write(offset, value);
+            }
+            return;
+        }
+        if let Some(offset) = offset(addr, usart::BASE, usart::SIZE) {
+            if self.rcc.apb2_active(rcc::USART1EN) {
+                self.usart.write(offset, value);
+            }
+            return;
+        }
+        for (port, base) in PORTS {
+            if let Some(offset) = offset(addr, base, gpio::SIZE) {
+                if self.rcc.apb2_active(rcc::gpio_bit(port)) {
+                    self.update_port(port, |gpio| gpio.write(offset, value));
+                }
+                return;
+            }
+        }
+        self.registers.insert(addr, value);
+    }
+
+    fn read_register(&mut self, addr: u32, len: u32) -> u32 {
+        narrow(self.read_word(addr & !3), addr, len)
+    }
+
+    fn write_register(&mut self, addr: u32, len: u32, value: u32) {
+        let word = if len == 4 {
+            value
+        } else {
+            widen(self.read_word(addr & !3), addr, len, value)
+        };
+        self.write_word(addr & !3, word);
+    }
+}
+
+/// Offset of `addr` into a register block
+fn offset(addr: u32, base: u32, size: u32) -> Option<u32> {
+    addr.checked_sub(base).filter(|&offset| offset < size)
+}
+
+/// Part of a register read with an access of `len` bytes
+fn narrow(word: u32, addr: u32, len: u32) -> u32 {
+    let shift = 8 * (addr & 3);
+    let mask = if len == 4 {
+        u32::MAX
+    } else {
+        (1 << (8 * len)) - 1
+    };
+    word >> shift & mask
+}
+
+/// Register after writing `len` bytes of `value` into it
+fn widen(word: u32, addr: u32, len: u32, value: u32) -> u32 {
+    let shift = 8 * (addr & 3);
+    let mask = if len == 4 {
+        u32::MAX
+    } else {
+        (1 << (8 * len)) - 1
+    };
+    word & !(mask << shift) | (value & mask) << shift
+}
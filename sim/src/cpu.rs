@@ -0,0 +1,354 @@
+//! QingKe V2 core, executing RV32EC
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::bus::Bus;
+use crate::decode::{self, Alu, Branch, Csr, CsrSource, Instruction, Width};
+
+/// Reasons the core stops executing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// The fetched bits are not an RV32EC instruction
+    IllegalInstruction { pc: u32, instruction: u32 },
+    /// The program counter left flash and RAM
+    InstructionFetch { pc: u32 },
+    /// A load from unmapped memory
+    Load { pc: u32, addr: u32 },
+    /// A store to unmapped memory or flash
+    Store { pc: u32, addr: u32 },
+    /// An `ecall` instruction
+    EnvironmentCall { pc: u32 },
+    /// An `ebreak` instruction
+    Breakpoint { pc: u32 },
+    /// A `wfi` instruction, which would never return as interrupts are not
+    /// modelled
+    WaitForInterrupt { pc: u32 },
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Fault::IllegalInstruction { pc, instruction } => {
+                write!(f, "illegal instruction {instruction:#010x} at {pc:#010x}")
+            }
+            Fault::InstructionFetch { pc } => write!(f, "instruction fetch from {pc:#010x}"),
+            Fault::Load { pc, addr } => write!(f, "load from {addr:#010x} at {pc:#010x}"),
+            Fault::Store { pc, addr } => write!(f, "store to {addr:#010x} at {pc:#010x}"),
+            Fault::EnvironmentCall { pc } => write!(f, "ecall at {pc:#010x}"),
+            Fault::Breakpoint { pc } => write!(f, "ebreak at {pc:#010x}"),
+            Fault::WaitForInterrupt { pc } => {
+                write!(f, "wfi at {pc:#010x}, interrupts are not simulated")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Fault {}
+
+pub const MSTATUS: u16 = 0x300;
+pub const MEPC: u16 = 0x341;
+
+/// `mstatus.MIE`
+const MIE: u32 = 1 << 3;
+/// `mstatus.MPIE`
+const MPIE: u32 = 1 << 7;
+
+/// Registers and CSRs of the core
+pub struct Cpu {
+    regs: [u32; 16],
+    pc: u32,
+    csrs: HashMap<u16, u32>,
+    cycles: u64,
+}
+
+impl Cpu {
+    /// A core about to execute from `pc`
+    pub fn new(pc: u32) -> Self {
+        Self {
+            regs: [0; 16],
+            pc,
+            csrs: HashMap::new(),
+            cycles: 0,
+        }
+    }
+
+    pub fn pc(&self) -> u32 {
+        self.pc
+    }
+
+    pub fn set_pc(&mut self, pc: u32) {
+        self.pc = pc;
+    }
+
+    pub fn reg(&self, r: u8) -> u32 {
+        self.regs[r as usize]
+    }
+
+    pub fn set_reg(&mut self, r: u8, value: u32) {
+        if r != 0 {
+            self.regs[r as usize] = value;
+        }
+    }
+
+    pub fn csr(&self, csr: u16) -> u32 {
+        self.csrs.get(&csr).copied().unwrap_or(0)
+    }
+
+    pub fn set_csr(&mut self, csr: u16, value: u32) {
+        self.csrs.insert(csr, value);
+    }
+
+    /// Instructions executed so far, each taking one cycle
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    fn fetch(&self, bus: &mut Bus) -> Result<(Instruction, u32), Fault> {
+        let fetch = |bus: &mut Bus, addr| {
+            bus.read(addr, 2)
+                .ok_or(Fault::InstructionFetch { pc: self.pc })
+        };
+
+        let low = fetch(bus, self.pc)?;
+        if decode::is_full(low as u16) {
+            let inst = low | fetch(bus, self.pc + 2)? << 16;
+            let decoded = decode::decode(inst).ok_or(Fault::IllegalInstruction {
+                pc: self.pc,
+                instruction: inst,
+            })?;
+            Ok((decoded, 4))
+        } else {
+            let decoded =
+                decode::decode_compressed(low as u16).ok_or(Fault::IllegalInstruction {
+                    pc: self.pc,
+                    instruction: low,
+                })?;
+            Ok((decoded, 2))
+        }
+    }
+
+    /// Execute one instruction
+    pub fn step(&mut self, bus: &mut Bus) -> Result<(), Fault> {
+        self.cycles += 1;
+
+        let pc = self.pc;
+        let (inst, len) = self.fetch(bus)?;
+        let mut next = pc.wrapping_add(len);
+
+        match inst {
+            Instruction::Lui { rd, imm } => self.set_reg(rd, imm),
+            Instruction::Auipc { rd, imm } => self.set_reg(rd, pc.wrapping_add(imm)),
+            Instruction::Jal { rd, offset } => {
+                self.set_reg(rd, next);
+                next = pc.wrapping_add(offset as u32);
+            }
+            Instruction::Jalr { rd, rs1, offset } => {
+                let target = self.reg(rs1).wrapping_add(offset as u32) & !1;
+                self.set_reg(rd, next);
+                next = target;
+            }
+            Instruction::Branch {
+                op,
+                rs1,
+                rs2,
+                offset,
+            } => {
+                let (a, b) = (self.reg(rs1), self.reg(rs2));
+                let taken = match op {
+                    Branch::Eq => a == b,
+                    Branch::Ne => a != b,
+                    Branch::Lt => (a as i32) < (b as i32),
+                    Branch::Ge => (a as i32) >= (b as i32),
+                    Branch::Ltu => a < b,
+                    Branch::Geu => a >= b,
+                };
+                if taken {
+                    next = pc.wrapping_add(offset as u32);
+                }
+            }
+            Instruction::Load {
+                width,
+                rd,
+                rs1,
+                offset,
+            } => {
+                let addr = self.reg(rs1).wrapping_add(offset as u32);
+                let len = match width {
+                    Width::Byte | Width::ByteUnsigned => 1,
+                    Width::Half | Width::HalfUnsigned => 2,
+                    Width::Word => 4,
+                };
+                let value = bus.read(addr, len).ok_or(Fault::Load { pc, addr })?;
+                let value = match width {
+                    Width::Byte => value as i8 as u32,
+                    Width::Half => value as i16 as u32,
+                    _ => value,
+                };
+                self.set_reg(rd, value);
+            }
+            Instruction::Store {
+                width,
+                rs1,
+                rs2,
+                offset,
+            } => {
+                let addr = self.reg(rs1).wrapping_add(offset as u32);
+                let len = match width {
+                    Width::Byte => 1,
+                    Width::Half => 2,
+                    _ => 4,
+                };
+                bus.write(addr, len, self.reg(rs2))
+                    .ok_or(Fault::Store { pc, addr })?;
+            }
+            Instruction::OpImm { op, rd, rs1, imm } => {
+                let value = alu(op, self.reg(rs1), imm as u32);
+                self.set_reg(rd, value);
+            }
+            Instruction::Op { op, rd, rs1, rs2 } => {
+                let value = alu(op, self.reg(rs1), self.reg(rs2));
+                self.set_reg(rd, value);
+            }
+            Instruction::Csr { op, rd, src, csr } => {
+                let old = self.csr(csr);
+                let (operand, writes) = match src {
+                    CsrSource::Reg(rs1) => (self.reg(rs1), op == Csr::Write || rs1 != 0),
+                    CsrSource::Imm(imm) => (imm, op == Csr::Write || imm != 0),
+                };
+                if writes {
+                    let new = match op {
+                        Csr::Write => operand,
+                        Csr::Set => old | operand,
+                        Csr::Clear => old & !operand,
+                    };
+                    self.set_csr(csr, new);
+                }
+                self.set_reg(rd, old);
+            }
+            Instruction::Fence => {}
+            Instruction::Ecall => return Err(Fault::EnvironmentCall { pc }),
+            Instruction::Ebreak => return Err(Fault::Breakpoint { pc }),
+            Instruction::Mret => {
+                let mstatus = self.csr(MSTATUS);
+                let mie = if mstatus & MPIE != 0 { MIE } else { 0 };
+                self.set_csr(MSTATUS, mstatus & !MIE | mie | MPIE);
+                next = self.csr(MEPC);
+            }
+            Instruction::Wfi => return Err(Fault::WaitForInterrupt { pc }),
+        }
+
+        self.pc = next;
+        Ok(())
+    }
+}
+
+fn alu(op: Alu, a: u32, b: u32) -> u32 {
+    match op {
+        Alu::Add => a.wrapping_add(b),
+        Alu::Sub => a.wrapping_sub(b),
+        Alu::Sll => a << (b & 31),
+        Alu::Slt => ((a as i32) < (b as i32)) as u32,
+        Alu::Sltu => (a < b) as u32,
+        Alu::Xor => a ^ b,
+        Alu::Srl => a >> (b & 31),
+        Alu::Sra => ((a as i32) >> (b & 31)) as u32,
+        Alu::Or => a | b,
+        Alu::And => a & b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::RAM_BASE;
+
+    /// A core about to run `program` from the start of flash, with the
+    /// stack pointer in RAM
+    fn load(program: &[u32]) -> (Cpu, Bus) {
+        let mut bus = Bus::new();
+        let bytes: Vec<u8> = program.iter().flat_map(|i| i.to_le_bytes()).collect();
+        bus.program(0, &bytes).unwrap();
+        let mut cpu = Cpu::new(0);
+        cpu.set_reg(2, RAM_BASE + 0x400);
+        (cpu, bus)
+    }
+
+    /// Run the single instruction `inst` with `a0` and `a1` set, returning
+    /// the next program counter
+    fn branch(inst: u32, a0: i32, a1: i32) -> u32 {
+        let (mut cpu, mut bus) = load(&[inst]);
+        cpu.set_reg(10, a0 as u32);
+        cpu.set_reg(11, a1 as u32);
+        cpu.step(&mut bus).unwrap();
+        cpu.pc()
+    }
+
+    #[test]
+    fn branches_compare_signed_and_unsigned() {
+        // blt, bltu, bge and bgeu a0, a1, 8
+        let (blt, bltu, bge, bgeu) = (0x00B5_4463, 0x00B5_6463, 0x00B5_5463, 0x00B5_7463);
+
+        assert_eq!(branch(blt, -1, 1), 8);
+        assert_eq!(branch(bltu, -1, 1), 4);
+        assert_eq!(branch(bge, -1, 1), 4);
+        assert_eq!(branch(bgeu, -1, 1), 8);
+
+        assert_eq!(branch(blt, 1, -1), 4);
+        assert_eq!(branch(bltu, 1, -1), 8);
+        assert_eq!(branch(bge, 1, 1), 8);
+        assert_eq!(branch(bgeu, 1, 1), 8);
+    }
+
+    #[test]
+    fn shifts_right_keep_or_drop_the_sign() {
+        // srai a0, a1, 31; srli a0, a1, 31
+        for (inst, result) in [(0x41F5_D513, 0xFFFF_FFFF), (0x01F5_D513, 1)] {
+            let (mut cpu, mut bus) = load(&[inst]);
+            cpu.set_reg(11, 0x8000_0000);
+            cpu.step(&mut bus).unwrap();
+            assert_eq!(cpu.reg(10), result);
+        }
+    }
+
+    #[test]
+    fn compressed_stack_access() {
+        // c.addi16sp sp, -512; c.lwsp a0, 252(sp)
+        let (mut cpu, mut bus) = load(&[0x557E_7101]);
+        let sp = RAM_BASE + 0x400 - 512;
+        bus.write(sp + 252, 4, 0x1234_5678).unwrap();
+
+        cpu.step(&mut bus).unwrap();
+        assert_eq!(cpu.reg(2), sp);
+        cpu.step(&mut bus).unwrap();
+        assert_eq!(cpu.reg(10), 0x1234_5678);
+        assert_eq!(cpu.pc(), 4);
+    }
+
+    #[test]
+    fn compressed_jump_backwards() {
+        // c.nop; c.j -2
+        let (mut cpu, mut bus) = load(&[0xBFFD_0001]);
+        cpu.step(&mut bus).unwrap();
+        cpu.step(&mut bus).unwrap();
+        assert_eq!(cpu.pc(), 0);
+    }
+
+    #[test]
+    fn byte_loads_extend() {
+        // lb a0, 0(a1); lbu a0, 0(a1)
+        for (inst, result) in [(0x0005_8503, 0xFFFF_FF80), (0x0005_C503, 0x80)] {
+            let (mut cpu, mut bus) = load(&[inst]);
+            bus.write(RAM_BASE, 1, 0x80).unwrap();
+            cpu.set_reg(11, RAM_BASE);
+            cpu.step(&mut bus).unwrap();
+            assert_eq!(cpu.reg(10), result);
+        }
+    }
+
+    #[test]
+    fn wfi_faults() {
+        let (mut cpu, mut bus) = load(&[0x1050_0073]);
+        assert_eq!(cpu.step(&mut bus), Err(Fault::WaitForInterrupt { pc: 0 }));
+    }
+}
@@ -0,0 +1,710 @@
+//! RV32EC instruction decoding
+//!
+//! Compressed instructions decode to the same [`Instruction`] as their
+//! 32-bit equivalents, so the core only has to execute one form.
+
+/// Register index, always below 16 on RV32E
+pub type Reg = u8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Alu {
+    Add,
+    Sub,
+    Sll,
+    Slt,
+    Sltu,
+    Xor,
+    Srl,
+    Sra,
+    Or,
+    And,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Branch {
+    Eq,
+    Ne,
+    Lt,
+    Ge,
+    Ltu,
+    Geu,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Width {
+    Byte,
+    Half,
+    Word,
+    ByteUnsigned,
+    HalfUnsigned,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Csr {
+    Write,
+    Set,
+    Clear,
+}
+
+/// Source operand of a CSR instruction
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CsrSource {
+    Reg(Reg),
+    Imm(u32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    Lui {
+        rd: Reg,
+        imm: u32,
+    },
+    Auipc {
+        rd: Reg,
+        imm: u32,
+    },
+    Jal {
+        rd: Reg,
+        offset: i32,
+    },
+    Jalr {
+        rd: Reg,
+        rs1: Reg,
+        offset: i32,
+    },
+    Branch {
+        op: Branch,
+        rs1: Reg,
+        rs2: Reg,
+        offset: i32,
+    },
+    Load {
+        width: Width,
+        rd: Reg,
+        rs1: Reg,
+        offset: i32,
+    },
+    Store {
+        width: Width,
+        rs1: Reg,
+        rs2: Reg,
+        offset: i32,
+    },
+    OpImm {
+        op: Alu,
+        rd: Reg,
+        rs1: Reg,
+        imm: i32,
+    },
+    Op {
+        op: Alu,
+        rd: Reg,
+        rs1: Reg,
+        rs2: Reg,
+    },
+    Csr {
+        op: Csr,
+        rd: Reg,
+        src: CsrSource,
+        csr: u16,
+    },
+    Fence,
+    Ecall,
+    Ebreak,
+    Mret,
+    Wfi,
+}
+
+/// Whether a halfword starts a 32-bit instruction
+pub fn is_full(low: u16) -> bool {
+    low & 0b11 == 0b11
+}
+
+/// Sign extend the low `bits` bits of `value`
+fn sext(value: u32, bits: u32) -> i32 {
+    ((value << (32 - bits)) as i32) >> (32 - bits)
+}
+
+/// Bit `from` of `value`, moved to bit `to`
+fn bit(value: u32, from: u32, to: u32) -> u32 {
+    (value >> from & 1) << to
+}
+
+/// Bits `hi..=lo` of `value`
+fn bits(value: u32, hi: u32, lo: u32) -> u32 {
+    value >> lo & ((1 << (hi - lo + 1)) - 1)
+}
+
+/// Register field, rejecting the upper 16 registers RV32E lacks
+fn reg(value: u32) -> Option<Reg> {
+    (value < 16).then_some(value as Reg)
+}
+
+/// Decode a 32-bit instruction
+pub fn decode(inst: u32) -> Option<Instruction> {
+    let rd = || reg(bits(inst, 11, 7));
+    let rs1 = || reg(bits(inst, 19, 15));
+    let rs2 = || reg(bits(inst, 24, 20));
+    let funct3 = bits(inst, 14, 12);
+    let funct7 = bits(inst, 31, 25);
+
+    let imm_i = sext(inst >> 20, 12);
+    let imm_s = sext(bits(inst, 31, 25) << 5 | bits(inst, 11, 7), 12);
+    let imm_b = sext(
+        bit(inst, 31, 12) | bit(inst, 7, 11) | bits(inst, 30, 25) << 5 | bits(inst, 11, 8) << 1,
+        13,
+    );
+    let imm_j = sext(
+        bit(inst, 31, 20) | bits(inst, 19, 12) << 12 | bit(inst, 20, 11) | bits(inst, 30, 21) << 1,
+        21,
+    );
+
+    Some(match inst & 0x7F {
+        0x37 => Instruction::Lui {
+            rd: rd()?,
+            imm: inst & 0xFFFF_F000,
+        },
+        0x17 => Instruction::Auipc {
+            rd: rd()?,
+            imm: inst & 0xFFFF_F000,
+        },
+        0x6F => Instruction::Jal {
+            rd: rd()?,
+            offset: imm_j,
+        },
+        0x67 if funct3 == 0 => Instruction::Jalr {
+            rd: rd()?,
+            rs1: rs1()?,
+            offset: imm_i,
+        },
+        0x63 => Instruction::Branch {
+            op: match funct3 {
+                0 => Branch::Eq,
+                1 => Branch::Ne,
+                4 => Branch::Lt,
+                5 => Branch::Ge,
+                6 => Branch::Ltu,
+                7 => Branch::Geu,
+                _ => return None,
+            },
+            rs1: rs1()?,
+            rs2: rs2()?,
+            offset: imm_b,
+        },
+        0x03 => Instruction::Load {
+            width: match funct3 {
+                0 => Width::Byte,
+                1 => Width::Half,
+                2 => Width::Word,
+                4 => Width::ByteUnsigned,
+                5 => Width::HalfUnsigned,
+                _ => return None,
+            },
+            rd: rd()?,
+            rs1: rs1()?,
+            offset: imm_i,
+        },
+        0x23 => Instruction::Store {
+            width: match funct3 {
+                0 => Width::Byte,
+                1 => Width::Half,
+                2 => Width::Word,
+                _ => return None,
+            },
+            rs1: rs1()?,
+            rs2: rs2()?,
+            offset: imm_s,
+        },
+        0x13 => {
+            let shamt = bits(inst, 24, 20) as i32;
+            let (op, imm) = match (funct3, funct7) {
+                (0, _) => (Alu::Add, imm_i),
+                (2, _) => (Alu::Slt, imm_i),
+                (3, _) => (Alu::Sltu, imm_i),
+                (4, _) => (Alu::Xor, imm_i),
+                (6, _) => (Alu::Or, imm_i),
+                (7, _) => (Alu::And, imm_i),
+                (1, 0x00) => (Alu::Sll, shamt),
+                (5, 0x00) => (Alu::Srl, shamt),
+                (5, 0x20) => (Alu::Sra, shamt),
+                _ => return None,
+            };
+            Instruction::OpImm {
+                op,
+                rd: rd()?,
+                rs1: rs1()?,
+                imm,
+            }
+        }
+        0x33 => Instruction::Op {
+            op: match (funct3, funct7) {
+                (0, 0x00) => Alu::Add,
+                (0, 0x20) => Alu::Sub,
+                (1, 0x00) => Alu::Sll,
+                (2, 0x00) => Alu::Slt,
+                (3, 0x00) => Alu::Sltu,
+                (4, 0x00) => Alu::Xor,
+                (5, 0x00) => Alu::Srl,
+                (5, 0x20) => Alu::Sra,
+                (6, 0x00) => Alu::Or,
+                (7, 0x00) => Alu::And,
+                _ => return None,
+            },
+            rd: rd()?,
+            rs1: rs1()?,
+            rs2: rs2()?,
+        },
+        0x0F => Instruction::Fence,
+        0x73 => {
+            let csr = (inst >> 20) as u16;
+            let (op, src) = match funct3 {
+                0 => {
+                    return match inst {
+                        0x0000_0073 => Some(Instruction::Ecall),
+                        0x0010_0073 => Some(Instruction::Ebreak),
+                        0x3020_0073 => Some(Instruction::Mret),
+                        0x1050_0073 => Some(Instruction::Wfi),
+                        _ => None,
+                    };
+                }
+                1 => (Csr::Write, CsrSource::Reg(rs1()?)),
+                2 => (Csr::Set, CsrSource::Reg(rs1()?)),
+                3 => (Csr::Clear, CsrSource::Reg(rs1()?)),
+                5 => (Csr::Write, CsrSource::Imm(bits(inst, 19, 15))),
+                6 => (Csr::Set, CsrSource::Imm(bits(inst, 19, 15))),
+                7 => (Csr::Clear, CsrSource::Imm(bits(inst, 19, 15))),
+                _ => return None,
+            };
+            Instruction::Csr {
+                op,
+                rd: rd()?,
+                src,
+                csr,
+            }
+        }
+        _ => return None,
+    })
+}
+
+/// Decode a 16-bit compressed instruction
+pub fn decode_compressed(inst: u16) -> Option<Instruction> {
+    let inst = inst as u32;
+    let funct3 = bits(inst, 15, 13);
+    // Full and compressed register fields
+    let rd = bits(inst, 11, 7);
+    let rs2 = bits(inst, 6, 2);
+    let rd_c = 8 + bits(inst, 4, 2) as Reg;
+    let rs1_c = 8 + bits(inst, 9, 7) as Reg;
+
+    let imm6 = sext(bit(inst, 12, 5) | bits(inst, 6, 2), 6);
+    let jump = sext(
+        bit(inst, 12, 11)
+            | bit(inst, 11, 4)
+            | bits(inst, 10, 9) << 8
+            | bit(inst, 8, 10)
+            | bit(inst, 7, 6)
+            | bit(inst, 6, 7)
+            | bits(inst, 5, 3) << 1
+            | bit(inst, 2, 5),
+        12,
+    );
+    let branch = sext(
+        bit(inst, 12, 8)
+            | bits(inst, 11, 10) << 3
+            | bits(inst, 6, 5) << 6
+            | bits(inst, 4, 3) << 1
+            | bit(inst, 2, 5),
+        9,
+    );
+    // Offset of C.LW and C.SW
+    let word = (bits(inst, 12, 10) << 3 | bit(inst, 6, 2) | bit(inst, 5, 6)) as i32;
+
+    Some(match (inst & 0b11, funct3) {
+        (0b00, 0b000) => {
+            let imm = bits(inst, 12, 11) << 4
+                | bits(inst, 10, 7) << 6
+                | bit(inst, 6, 2)
+                | bit(inst, 5, 3);
+            if imm == 0 {
+                return None;
+            }
+            Instruction::OpImm {
+                op: Alu::Add,
+                rd: rd_c,
+                rs1: 2,
+                imm: imm as i32,
+            }
+        }
+        (0b00, 0b010) => Instruction::Load {
+            width: Width::Word,
+            rd: rd_c,
+            rs1: rs1_c,
+            offset: word,
+        },
+        (0b00, 0b110) => Instruction::Store {
+            width: Width::Word,
+            rs1: rs1_c,
+            rs2: rd_c,
+            offset: word,
+        },
+        (0b01, 0b000) => Instruction::OpImm {
+            op: Alu::Add,
+            rd: reg(rd)?,
+            rs1: reg(rd)?,
+            imm: imm6,
+        },
+        (0b01, 0b001) => Instruction::Jal {
+            rd: 1,
+            offset: jump,
+        },
+        (0b01, 0b010) => Instruction::OpImm {
+            op: Alu::Add,
+            rd: reg(rd)?,
+            rs1: 0,
+            imm: imm6,
+        },
+        (0b01, 0b011) if rd == 2 => {
+            let imm = sext(
+                bit(inst, 12, 9)
+                    | bit(inst, 6, 4)
+                    | bit(inst, 5, 6)
+                    | bits(inst, 4, 3) << 7
+                    | bit(inst, 2, 5),
+                10,
+            );
+            if imm == 0 {
+                return None;
+            }
+            Instruction::OpImm {
+                op: Alu::Add,
+                rd: 2,
+                rs1: 2,
+                imm,
+            }
+        }
+        (0b01, 0b011) => {
+            if imm6 == 0 {
+                return None;
+            }
+            Instruction::Lui {
+                rd: reg(rd)?,
+                imm: (imm6 << 12) as u32,
+            }
+        }
+        (0b01, 0b100) => {
+            let shamt = bits(inst, 6, 2) as i32;
+            match bits(inst, 11, 10) {
+                0b00 if bit(inst, 12, 0) == 0 => Instruction::OpImm {
+                    op: Alu::Srl,
+                    rd: rs1_c,
+                    rs1: rs1_c,
+                    imm: shamt,
+                },
+                0b01 if bit(inst, 12, 0) == 0 => Instruction::OpImm {
+                    op: Alu::Sra,
+                    rd: rs1_c,
+                    rs1: rs1_c,
+                    imm: shamt,
+                },
+                0b10 => Instruction::OpImm {
+                    op: Alu::And,
+                    rd: rs1_c,
+                    rs1: rs1_c,
+                    imm: imm6,
+                },
+                0b11 if bit(inst, 12, 0) == 0 => Instruction::Op {
+                    op: match bits(inst, 6, 5) {
+                        0b00 => Alu::Sub,
+                        0b01 => Alu::Xor,
+                        0b10 => Alu::Or,
+                        _ => Alu::And,
+                    },
+                    rd: rs1_c,
+                    rs1: rs1_c,
+                    rs2: rd_c,
+                },
+                _ => return None,
+            }
+        }
+        (0b01, 0b101) => Instruction::Jal {
+            rd: 0,
+            offset: jump,
+        },
+        (0b01, 0b110) => Instruction::Branch {
+            op: Branch::Eq,
+            rs1: rs1_c,
+            rs2: 0,
+            offset: branch,
+        },
+        (0b01, 0b111) => Instruction::Branch {
+            op: Branch::Ne,
+            rs1: rs1_c,
+            rs2: 0,
+            offset: branch,
+        },
+        (0b10, 0b000) if bit(inst, 12, 0) == 0 => Instruction::OpImm {
+            op: Alu::Sll,
+            rd: reg(rd)?,
+            rs1: reg(rd)?,
+            imm: rs2 as i32,
+        },
+        (0b10, 0b010) if rd != 0 => Instruction::Load {
+            width: Width::Word,
+            rd: reg(rd)?,
+            rs1: 2,
+            offset: (bit(inst, 12, 5) | bits(inst, 6, 4) << 2 | bits(inst, 3, 2) << 6) as i32,
+        },
+        (0b10, 0b100) => match (bit(inst, 12, 0), rd, rs2) {
+            (0, 0, _) => return None,
+            (0, _, 0) => Instruction::Jalr {
+                rd: 0,
+                rs1: reg(rd)?,
+                offset: 0,
+            },
+            (0, _, _) => Instruction::Op {
+                op: Alu::Add,
+                rd: reg(rd)?,
+                rs1: 0,
+                rs2: reg(rs2)?,
+            },
+            (_, 0, 0) => Instruction::Ebreak,
+            (_, _, 0) => Instruction::Jalr {
+                rd: 1,
+                rs1: reg(rd)?,
+                offset: 0,
+            },
+            (_, _, _) => Instruction::Op {
+                op: Alu::Add,
+                rd: reg(rd)?,
+                rs1: reg(rd)?,
+                rs2: reg(rs2)?,
+            },
+        },
+        (0b10, 0b110) => Instruction::Store {
+            width: Width::Word,
+            rs1: 2,
+            rs2: reg(rs2)?,
+            offset: (bits(inst, 12, 9) << 2 | bits(inst, 8, 7) << 6) as i32,
+        },
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compressed_jump_offsets() {
+        // c.j -2048, c.j 2046, c.j -4
+        assert_eq!(
+            decode_compressed(0xB001),
+            Some(Instruction::Jal {
+                rd: 0,
+                offset: -2048
+            })
+        );
+        assert_eq!(
+            decode_compressed(0xAFFD),
+            Some(Instruction::Jal {
+                rd: 0,
+                offset: 2046
+            })
+        );
+        assert_eq!(
+            decode_compressed(0xBFF5),
+            Some(Instruction::Jal { rd: 0, offset: -4 })
+        );
+        // c.jal -4
+        assert_eq!(
+            decode_compressed(0x3FF5),
+            Some(Instruction::Jal { rd: 1, offset: -4 })
+        );
+    }
+
+    #[test]
+    fn compressed_branch_offsets() {
+        // c.beqz s0, -256
+        assert_eq!(
+            decode_compressed(0xD001),
+            Some(Instruction::Branch {
+                op: Branch::Eq,
+                rs1: 8,
+                rs2: 0,
+                offset: -256
+            })
+        );
+        // c.beqz s0, 254
+        assert_eq!(
+            decode_compressed(0xCC7D),
+            Some(Instruction::Branch {
+                op: Branch::Eq,
+                rs1: 8,
+                rs2: 0,
+                offset: 254
+            })
+        );
+        // c.bnez a5, -8
+        assert_eq!(
+            decode_compressed(0xFFE5),
+            Some(Instruction::Branch {
+                op: Branch::Ne,
+                rs1: 15,
+                rs2: 0,
+                offset: -8
+            })
+        );
+    }
+
+    #[test]
+    fn compressed_stack_pointer_immediates() {
+        let addi16sp = |imm| Instruction::OpImm {
+            op: Alu::Add,
+            rd: 2,
+            rs1: 2,
+            imm,
+        };
+        assert_eq!(decode_compressed(0x7101), Some(addi16sp(-512)));
+        assert_eq!(decode_compressed(0x617D), Some(addi16sp(496)));
+        assert_eq!(decode_compressed(0x6141), Some(addi16sp(16)));
+        // c.addi4spn a0, sp, 1020
+        assert_eq!(
+            decode_compressed(0x1FE8),
+            Some(Instruction::OpImm {
+                op: Alu::Add,
+                rd: 10,
+                rs1: 2,
+                imm: 1020
+            })
+        );
+    }
+
+    #[test]
+    fn compressed_stack_loads_and_stores() {
+        // c.lwsp a0, 252(sp)
+        assert_eq!(
+            decode_compressed(0x557E),
+            Some(Instruction::Load {
+                width: Width::Word,
+                rd: 10,
+                rs1: 2,
+                offset: 252
+            })
+        );
+        // c.lwsp ra, 4(sp)
+        assert_eq!(
+            decode_compressed(0x4092),
+            Some(Instruction::Load {
+                width: Width::Word,
+                rd: 1,
+                rs1: 2,
+                offset: 4
+            })
+        );
+        // c.swsp a0, 252(sp)
+        assert_eq!(
+            decode_compressed(0xDFAA),
+            Some(Instruction::Store {
+                width: Width::Word,
+                rs1: 2,
+                rs2: 10,
+                offset: 252
+            })
+        );
+    }
+
+    #[test]
+    fn compressed_lui_is_sign_extended() {
+        // c.lui a0, 0xfffff
+        assert_eq!(
+            decode_compressed(0x757D),
+            Some(Instruction::Lui {
+                rd: 10,
+                imm: 0xFFFF_F000
+            })
+        );
+    }
+
+    #[test]
+    fn shifts_by_immediate() {
+        // srai a0, a1, 31
+        assert_eq!(
+            decode(0x41F5_D513),
+            Some(Instruction::OpImm {
+                op: Alu::Sra,
+                rd: 10,
+                rs1: 11,
+                imm: 31
+            })
+        );
+        // srai t0, t0, 1
+        assert_eq!(
+            decode(0x4012_D293),
+            Some(Instruction::OpImm {
+                op: Alu::Sra,
+                rd: 5,
+                rs1: 5,
+                imm: 1
+            })
+        );
+        // srli a0, a1, 31
+        assert_eq!(
+            decode(0x01F5_D513),
+            Some(Instruction::OpImm {
+                op: Alu::Srl,
+                rd: 10,
+                rs1: 11,
+                imm: 31
+            })
+        );
+        // c.srai a0, 31
+        assert_eq!(
+            decode_compressed(0x857D),
+            Some(Instruction::OpImm {
+                op: Alu::Sra,
+                rd: 10,
+                rs1: 10,
+                imm: 31
+            })
+        );
+    }
+
+    #[test]
+    fn branch_offsets() {
+        let branch = |op, offset| Instruction::Branch {
+            op,
+            rs1: 10,
+            rs2: 11,
+            offset,
+        };
+        // blt a0, a1, -16
+        assert_eq!(decode(0xFEB5_48E3), Some(branch(Branch::Lt, -16)));
+        // bltu a0, a1, 8
+        assert_eq!(decode(0x00B5_6463), Some(branch(Branch::Ltu, 8)));
+        // bge a0, a1, 4094
+        assert_eq!(decode(0x7EB5_5FE3), Some(branch(Branch::Ge, 4094)));
+        // bgeu a0, a1, -4096
+        assert_eq!(decode(0x80B5_7063), Some(branch(Branch::Geu, -4096)));
+    }
+
+    #[test]
+    fn immediates_are_sign_extended() {
+        // addi a0, a1, -2048
+        assert_eq!(
+            decode(0x8005_8513),
+            Some(Instruction::OpImm {
+                op: Alu::Add,
+                rd: 10,
+                rs1: 11,
+                imm: -2048
+            })
+        );
+    }
+
+    #[test]
+    fn upper_registers_are_rejected() {
+        // addi x16, a0, 0
+        assert_eq!(decode(0x0005_0813), None);
+        assert_eq!(decode(0x1050_0073), Some(Instruction::Wfi));
+    }
+}
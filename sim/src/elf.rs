@@ -0,0 +1,134 @@
+//! Minimal ELF32 loader
+//!
+//! Only what is needed to place firmware in memory: the entry point, the
+//! loadable segments and the symbol table.
+
+use std::collections::HashMap;
+use std::fmt;
+
+const PT_LOAD: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const EM_RISCV: u16 = 243;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// Not a little endian 32-bit ELF file
+    NotElf32,
+    /// Built for another architecture
+    WrongMachine(u16),
+    /// A header or table points outside the file
+    Truncated,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::NotElf32 => write!(f, "not a little endian ELF32 file"),
+            Error::WrongMachine(machine) => write!(f, "machine {machine} is not RISC-V"),
+            Error::Truncated => write!(f, "file is truncated"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Bytes to place in memory
+pub struct Segment {
+    /// Load address, which for initialized data is its copy in flash
+    pub addr: u32,
+    pub data: Vec<u8>,
+}
+
+pub struct Elf {
+    pub entry: u32,
+    pub segments: Vec<Segment>,
+    symbols: HashMap<String, u32>,
+}
+
+fn u16_at(file: &[u8], offset: usize) -> Result<u16, Error> {
+    let bytes = file.get(offset..offset + 2).ok_or(Error::Truncated)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn u32_at(file: &[u8], offset: usize) -> Result<u32, Error> {
+    let bytes = file.get(offset..offset + 4).ok_or(Error::Truncated)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn slice(file: &[u8], offset: u32, len: u32) -> Result<&[u8], Error> {
+    let start = offset as usize;
+    file.get(start..start + len as usize)
+        .ok_or(Error::Truncated)
+}
+
+impl Elf {
+    pub fn parse(file: &[u8]) -> Result<Self, Error> {
+        if file.get(..6) != Some(b"\x7fELF\x01\x01") {
+            return Err(Error::NotElf32);
+        }
+        let machine = u16_at(file, 18)?;
+        if machine != EM_RISCV {
+            return Err(Error::WrongMachine(machine));
+        }
+
+        let entry = u32_at(file, 24)?;
+        let phoff = u32_at(file, 28)? as usize;
+        let shoff = u32_at(file, 32)? as usize;
+        let phentsize = u16_at(file, 42)? as usize;
+        let phnum = u16_at(file, 44)? as usize;
+        let shentsize = u16_at(file, 46)? as usize;
+        let shnum = u16_at(file, 48)? as usize;
+
+        let mut segments = Vec::new();
+        for i in 0..phnum {
+            let header = phoff + i * phentsize;
+            let filesz = u32_at(file, header + 16)?;
+            if u32_at(file, header)? != PT_LOAD || filesz == 0 {
+                continue;
+            }
+            let offset = u32_at(file, header + 4)?;
+            segments.push(Segment {
+                addr: u32_at(file, header + 12)?,
+                data: slice(file, offset, filesz)?.to_vec(),
+            });
+        }
+
+        let mut symbols = HashMap::new();
+        for i in 0..shnum {
+            let header = shoff + i * shentsize;
+            if u32_at(file, header + 4)? != SHT_SYMTAB {
+                continue;
+            }
+            let table = slice(file, u32_at(file, header + 16)?, u32_at(file, header + 20)?)?;
+            let strtab_header = shoff + u32_at(file, header + 24)? as usize * shentsize;
+            let strtab = slice(
+                file,
+                u32_at(file, strtab_header + 16)?,
+                u32_at(file, strtab_header + 20)?,
+            )?;
+
+            for entry in table.chunks_exact(16) {
+                let name = u32_at(entry, 0)? as usize;
+                let name = strtab.get(name..).ok_or(Error::Truncated)?;
+                let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+                if !name.is_empty() {
+                    symbols.insert(
+                        String::from_utf8_lossy(name).into_owned(),
+                        u32_at(entry, 4)?,
+                    );
+                }
+            }
+        }
+
+        Ok(Self {
+            entry,
+            segments,
+            symbols,
+        })
+    }
+
+    /// Address of a symbol
+    pub fn symbol(&self, name: &str) -> Option<u32> {
+        self.symbols.get(name).copied()
+    }
+}
@@ -0,0 +1,204 @@
+//! GPIO port model
+
+use std::fmt;
+
+pub const GPIOA: u32 = 0x4001_0800;
+pub const GPIOC: u32 = 0x4001_1000;
+pub const GPIOD: u32 = 0x4001_1400;
+pub const SIZE: u32 = 0x400;
+
+const CFGLR: u32 = 0x00;
+const INDR: u32 = 0x08;
+const OUTDR: u32 = 0x0C;
+const BSHR: u32 = 0x10;
+const BCR: u32 = 0x14;
+const LCKR: u32 = 0x18;
+
+/// A single pin, for example `Pin::new('C', 1)` is PC1
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Pin {
+    pub port: char,
+    pub pin: u8,
+}
+
+impl Pin {
+    pub const fn new(port: char, pin: u8) -> Self {
+        Self { port, pin }
+    }
+}
+
+impl fmt::Display for Pin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "P{}{}", self.port, self.pin)
+    }
+}
+
+/// Output slew rate
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Speed {
+    Mhz10,
+    Mhz2,
+    Mhz30,
+}
+
+/// Pin configuration, decoded from its 4 bits of CFGLR
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Analog,
+    Floating,
+    /// Pulled up or down depending on the output data bit
+    Pull,
+    Output {
+        open_drain: bool,
+        speed: Speed,
+    },
+    /// Driven by a peripheral
+    Alternate {
+        open_drain: bool,
+        speed: Speed,
+    },
+}
+
+impl Mode {
+    fn decode(cfg: u32) -> Self {
+        let speed = match cfg & 0b11 {
+            0b00 => {
+                return match cfg >> 2 {
+                    0b00 => Mode::Analog,
+                    0b10 => Mode::Pull,
+                    _ => Mode::Floating,
+                };
+            }
+            0b01 => Speed::Mhz10,
+            0b10 => Speed::Mhz2,
+            _ => Speed::Mhz30,
+        };
+        let open_drain = cfg & 0b0100 != 0;
+        if cfg & 0b1000 != 0 {
+            Mode::Alternate { open_drain, speed }
+        } else {
+            Mode::Output { open_drain, speed }
+        }
+    }
+}
+
+/// One GPIO port with 8 pins
+pub struct Gpio {
+    cfglr: u32,
+    outdr: u8,
+    lckr: u32,
+    /// Level forced onto each pin from outside the chip
+    external: [Option<bool>; 8],
+    /// Level of each pin's peripheral output
+    alternate: u8,
+}
+
+impl Default for Gpio {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Gpio {
+    pub fn new() -> Self {
+        Self {
+            cfglr: 0x4444_4444,
+            outdr: 0,
+            lckr: 0,
+            external: [None; 8],
+            alternate: 0,
+        }
+    }
+
+    /// Return to the reset state, keeping what is connected outside
+    pub fn reset(&mut self) {
+        *self = Self {
+            external: self.external,
+            alternate: self.alternate,
+            ..Self::new()
+        };
+    }
+
+    pub fn mode(&self, pin: u8) -> Mode {
+        Mode::decode(self.cfglr >> (pin * 4) & 0b1111)
+    }
+
+    /// Level of a pin as seen from outside the chip
+    pub fn level(&self, pin: u8) -> bool {
+        let external = self.external[pin as usize];
+        let odr = self.outdr & 1 << pin != 0;
+        let af = self.alternate & 1 << pin != 0;
+        match self.mode(pin) {
+            Mode::Analog => external.unwrap_or(false),
+            Mode::Floating => external.unwrap_or(false),
+            Mode::Pull => external.unwrap_or(odr),
+            Mode::Output {
+                open_drain: false, ..
+            } => odr,
+            Mode::Alternate {
+                open_drain: false, ..
+            } => af,
+            // Released open drain lines are assumed to be pulled up
+            Mode::Output {
+                open_drain: true, ..
+            } => odr && external.unwrap_or(true),
+            Mode::Alternate {
+                open_drain: true, ..
+            } => af && external.unwrap_or(true),
+        }
+    }
+
+    /// Levels of all pins, bit `n` for pin `n`
+    pub fn levels(&self) -> u8 {
+        (0..8).fold(0, |levels, pin| levels | (self.level(pin) as u8) << pin)
+    }
+
+    /// Drive a pin from outside, or release it with `None`
+    pub fn set_external(&mut self, pin: u8, level: Option<bool>) {
+        self.external[pin as usize] = level;
+    }
+
+    /// Set the output of the peripheral a pin is connected to
+    pub fn set_alternate(&mut self, pin: u8, level: bool) {
+        self.alternate = self.alternate & !(1 << pin) | (level as u8) << pin;
+    }
+
+    pub fn read(&self, offset: u32) -> u32 {
+        match offset {
+            CFGLR => self.cfglr,
+            // Analog pins have their input buffer disconnected
+            INDR => (0..8)
+                .filter(|&pin| self.mode(pin) != Mode::Analog)
+                .fold(0, |indr, pin| indr | (self.level(pin) as u32) << pin),
+            OUTDR => self.outdr as u32,
+            LCKR => self.lckr,
+            _ => 0,
+        }
+    }
+
+    pub fn write(&mut self, offset: u32, value: u32) {
+        let locked = if self.lckr & 1 << 8 != 0 {
+            self.lckr as u8
+        } else {
+            0
+        };
+
+        match offset {
+            CFGLR => {
+                let mask = (0..8)
+                    .filter(|pin| locked & 1 << pin != 0)
+                    .fold(0, |mask, pin| mask | 0b1111 << (pin * 4));
+                self.cfglr = self.cfglr & mask | value & !mask;
+            }
+            OUTDR => self.outdr = value as u8,
+            BSHR => {
+                // Setting wins when both bits of a pin are written
+                self.outdr &= !(value >> 16) as u8;
+                self.outdr |= value as u8;
+            }
+            BCR => self.outdr &= !value as u8,
+            LCKR => self.lckr = value & 0x1FF,
+            _ => {}
+        }
+    }
+}
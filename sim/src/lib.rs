@@ -0,0 +1,127 @@
+//! CH32V003 simulator
+//!
+//! Runs firmware built for the chip on the host, so it can be tested without
+//! hardware:
+//!
+//! ```ignore
+//! let mut sim = Simulator::from_elf(&std::fs::read(path)?)?;
+//! let main = sim.elf().symbol("main").unwrap();
+//! sim.run_until(main, 100_000)?;
+//! ```
+//!
+//! Pin and peripheral output changes are recorded in [`bus::Bus::trace`], and
+//! [`Simulator::write_vcd`] saves them for a waveform viewer such as GTKWave.
+
+pub mod bus;
+pub mod cpu;
+pub mod decode;
+pub mod elf;
+pub mod gpio;
+pub mod rcc;
+pub mod systick;
+pub mod tim;
+pub mod trace;
+pub mod usart;
+pub mod vcd;
+
+use bus::Bus;
+use cpu::{Cpu, Fault};
+use elf::Elf;
+
+/// The core and memory of one chip
+pub struct Simulator {
+    pub cpu: Cpu,
+    pub bus: Bus,
+    elf: Elf,
+}
+
+/// Errors while loading firmware
+#[derive(Debug)]
+pub enum LoadError {
+    Elf(elf::Error),
+    /// A segment does not fit in flash or RAM
+    Segment {
+        addr: u32,
+        len: usize,
+    },
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LoadError::Elf(e) => write!(f, "{e}"),
+            LoadError::Segment { addr, len } => {
+                write!(
+                    f,
+                    "segment of {len} bytes at {addr:#010x} is outside memory"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<elf::Error> for LoadError {
+    fn from(e: elf::Error) -> Self {
+        LoadError::Elf(e)
+    }
+}
+
+impl Simulator {
+    /// Load firmware and reset into its entry point
+    pub fn from_elf(file: &[u8]) -> Result<Self, LoadError> {
+        let elf = Elf::parse(file)?;
+        let mut bus = Bus::new();
+        for segment in &elf.segments {
+            bus.program(segment.addr, &segment.data)
+                .ok_or(LoadError::Segment {
+                    addr: segment.addr,
+                    len: segment.data.len(),
+                })?;
+        }
+
+        Ok(Self {
+            cpu: Cpu::new(elf.entry),
+            bus,
+            elf,
+        })
+    }
+
+    /// The loaded firmware, for looking up symbols
+    pub fn elf(&self) -> &Elf {
+        &self.elf
+    }
+
+    pub fn step(&mut self) -> Result<(), Fault> {
+        self.bus.tick(self.cpu.cycles());
+        self.cpu.step(&mut self.bus)
+    }
+
+    /// Write the trace so far as a value change dump
+    ///
+    /// Cycles are converted to time at the current core clock.
+    pub fn write_vcd(&self, out: impl std::io::Write) -> std::io::Result<()> {
+        vcd::write(out, &self.bus.trace, self.bus.rcc.hclk(), self.cpu.cycles())
+    }
+
+    /// Execute up to `steps` instructions
+    pub fn run(&mut self, steps: u64) -> Result<(), Fault> {
+        for _ in 0..steps {
+            self.step()?;
+        }
+        Ok(())
+    }
+
+    /// Execute until the program counter reaches `pc`, returning whether it
+    /// did within `steps` instructions
+    pub fn run_until(&mut self, pc: u32, steps: u64) -> Result<bool, Fault> {
+        for _ in 0..steps {
+            if self.cpu.pc() == pc {
+                return Ok(true);
+            }
+            self.step()?;
+        }
+        Ok(self.cpu.pc() == pc)
+    }
+}
@@ -0,0 +1,80 @@
+//! Run firmware in the simulator
+//!
+//! ```text
+//! hello-wch-sim [--vcd <waves.vcd>] <firmware.elf> [instructions]
+//! ```
+//!
+//! With `--vcd`, every pin and peripheral output change is saved for viewing
+//! in GTKWave.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::process::ExitCode;
+
+use hello_wch_sim::Simulator;
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1).peekable();
+    let vcd = if args.peek().map(String::as_str) == Some("--vcd") {
+        args.next();
+        args.next()
+    } else {
+        None
+    };
+    let Some(path) = args.next() else {
+        eprintln!("usage: hello-wch-sim [--vcd <waves.vcd>] <firmware.elf> [instructions]");
+        return ExitCode::FAILURE;
+    };
+    let steps = match args.next().map(|s| s.parse()) {
+        None => 10_000_000,
+        Some(Ok(steps)) => steps,
+        Some(Err(e)) => {
+            eprintln!("invalid instruction count: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let file = match std::fs::read(&path) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("{path}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut sim = match Simulator::from_elf(&file) {
+        Ok(sim) => sim,
+        Err(e) => {
+            eprintln!("{path}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let result = sim.run(steps);
+
+    if let Some(vcd) = vcd {
+        let written = File::create(&vcd).and_then(|file| {
+            let mut out = BufWriter::new(file);
+            sim.write_vcd(&mut out)?;
+            out.flush()
+        });
+        if let Err(e) = written {
+            eprintln!("{vcd}: {e}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    match result {
+        Ok(()) => {
+            println!(
+                "ran {} cycles, pc at {:#010x}",
+                sim.cpu.cycles(),
+                sim.cpu.pc()
+            );
+            ExitCode::SUCCESS
+        }
+        Err(fault) => {
+            eprintln!("{fault} after {} cycles", sim.cpu.cycles());
+            ExitCode::FAILURE
+        }
+    }
+}
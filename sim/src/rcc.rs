@@ -0,0 +1,148 @@
+//! Reset and clock control model
+//!
+//! Oscillators and the PLL become ready as soon as they are turned on, and
+//! the system clock switch takes effect immediately.
+
+pub const BASE: u32 = 0x4002_1000;
+pub const SIZE: u32 = 0x400;
+
+const CTLR: u32 = 0x00;
+const CFGR0: u32 = 0x04;
+const APB2PRSTR: u32 = 0x0C;
+const APB1PRSTR: u32 = 0x10;
+const APB2PCENR: u32 = 0x18;
+const APB1PCENR: u32 = 0x1C;
+const RSTSCKR: u32 = 0x24;
+
+/// Register values after a power-on reset, by word offset
+const RESET: [u32; 10] = [
+    0x0000_0083,
+    0x0000_0020,
+    0,
+    0,
+    0,
+    0x0000_0014,
+    0,
+    0,
+    0,
+    0x0C00_0000,
+];
+
+/// Frequency of the internal oscillator
+pub const HSI_FREQ: u32 = 24_000_000;
+/// Frequency assumed for an external crystal
+pub const HSE_FREQ: u32 = 24_000_000;
+
+/// APB2 clock enable and reset bits
+pub const AFIOEN: u32 = 1 << 0;
+pub const TIM1EN: u32 = 1 << 11;
+pub const USART1EN: u32 = 1 << 14;
+/// APB1 clock enable and reset bit of TIM2
+pub const TIM2EN: u32 = 1 << 0;
+
+/// APB2 clock enable and reset bit of each GPIO port
+pub fn gpio_bit(port: char) -> u32 {
+    match port {
+        'A' => 1 << 2,
+        'C' => 1 << 4,
+        _ => 1 << 5,
+    }
+}
+
+pub struct Rcc {
+    regs: [u32; 10],
+}
+
+impl Default for Rcc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Rcc {
+    pub fn new() -> Self {
+        Self { regs: RESET }
+    }
+
+    pub fn read(&self, offset: u32) -> u32 {
+        let Some(&value) = self.regs.get(offset as usize / 4) else {
+            return 0;
+        };
+
+        match offset {
+            // Each ready flag follows its enable bit
+            CTLR => {
+                let on = value & (1 << 0 | 1 << 16 | 1 << 24);
+                value & !(1 << 1 | 1 << 17 | 1 << 25) | on << 1
+            }
+            // SWS follows SW
+            CFGR0 => value & !0b1100 | (value & 0b11) << 2,
+            // LSIRDY follows LSION
+            RSTSCKR => value & !0b10 | (value & 1) << 1,
+            _ => value,
+        }
+    }
+
+    pub fn write(&mut self, offset: u32, value: u32) {
+        match offset {
+            // Reset flags are cleared by RMVF
+            RSTSCKR if value & (1 << 24) != 0 => self.regs[9] = value & 0x00FF_FFFF,
+            RSTSCKR => self.regs[9] = self.regs[9] & 0xFF00_0000 | value & 0x00FF_FFFF,
+            _ => {
+                if let Some(reg) = self.regs.get_mut(offset as usize / 4) {
+                    *reg = value;
+                }
+            }
+        }
+    }
+
+    /// Whether the clock of an APB2 peripheral is on
+    pub fn apb2_enabled(&self, bit: u32) -> bool {
+        self.regs[APB2PCENR as usize / 4] & bit != 0
+    }
+
+    /// Whether an APB2 peripheral is held in reset
+    pub fn apb2_in_reset(&self, bit: u32) -> bool {
+        self.regs[APB2PRSTR as usize / 4] & bit != 0
+    }
+
+    /// Whether the clock of an APB1 peripheral is on
+    pub fn apb1_enabled(&self, bit: u32) -> bool {
+        self.regs[APB1PCENR as usize / 4] & bit != 0
+    }
+
+    /// Whether an APB1 peripheral is held in reset
+    pub fn apb1_in_reset(&self, bit: u32) -> bool {
+        self.regs[APB1PRSTR as usize / 4] & bit != 0
+    }
+
+    /// Whether an APB2 peripheral has its clock and is out of reset
+    pub fn apb2_active(&self, bit: u32) -> bool {
+        self.apb2_enabled(bit) && !self.apb2_in_reset(bit)
+    }
+
+    /// Whether an APB1 peripheral has its clock and is out of reset
+    pub fn apb1_active(&self, bit: u32) -> bool {
+        self.apb1_enabled(bit) && !self.apb1_in_reset(bit)
+    }
+
+    /// Current core clock in Hz
+    pub fn hclk(&self) -> u32 {
+        let cfgr0 = self.regs[CFGR0 as usize / 4];
+        let pll_source = if cfgr0 & 1 << 16 != 0 {
+            HSE_FREQ
+        } else {
+            HSI_FREQ
+        };
+        let sysclk = match cfgr0 & 0b11 {
+            0b00 => HSI_FREQ,
+            0b01 => HSE_FREQ,
+            _ => pll_source * 2,
+        };
+        let divider = match cfgr0 >> 4 & 0b1111 {
+            hpre @ 0..=0b0111 => hpre + 1,
+            hpre => [2, 4, 8, 16, 32, 64, 128, 256][hpre as usize - 8],
+        };
+        sysclk / divider
+    }
+}
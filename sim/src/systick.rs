@@ -0,0 +1,77 @@
+//! QingKe V2 system timer model
+//!
+//! Counts up from CNT, either every core cycle or every eighth one, and
+//! flags a match with CMP.
+
+pub const BASE: u32 = 0xE000_F000;
+pub const SIZE: u32 = 0x20;
+
+const CTLR: u32 = 0x00;
+const SR: u32 = 0x04;
+const CNT: u32 = 0x08;
+const CMP: u32 = 0x10;
+
+const STE: u32 = 1 << 0;
+const STCLK: u32 = 1 << 2;
+const STRE: u32 = 1 << 3;
+
+#[derive(Default)]
+pub struct SysTick {
+    ctlr: u32,
+    sr: u32,
+    cnt: u32,
+    cmp: u32,
+    /// Core cycles not yet counted with the /8 clock
+    prescaler: u32,
+}
+
+impl SysTick {
+    pub fn read(&self, offset: u32) -> u32 {
+        match offset {
+            CTLR => self.ctlr,
+            SR => self.sr,
+            CNT => self.cnt,
+            CMP => self.cmp,
+            _ => 0,
+        }
+    }
+
+    pub fn write(&mut self, offset: u32, value: u32) {
+        match offset {
+            CTLR => self.ctlr = value,
+            // CNTIF can only be cleared
+            SR => self.sr &= value,
+            CNT => self.cnt = value,
+            CMP => self.cmp = value,
+            _ => {}
+        }
+    }
+
+    /// Advance by one core cycle
+    pub fn tick(&mut self) {
+        if self.ctlr & STE == 0 {
+            return;
+        }
+        if self.ctlr & STCLK == 0 {
+            self.prescaler += 1;
+            if self.prescaler < 8 {
+                return;
+            }
+            self.prescaler = 0;
+        }
+
+        if self.cnt == self.cmp {
+            self.sr |= 1;
+            if self.ctlr & STRE != 0 {
+                self.cnt = 0;
+                return;
+            }
+        }
+        self.cnt = self.cnt.wrapping_add(1);
+    }
+
+    /// Whether the counter matched the compare value since CNTIF was cleared
+    pub fn is_pending(&self) -> bool {
+        self.sr & 1 != 0
+    }
+}
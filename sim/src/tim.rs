@@ -0,0 +1,190 @@
+//! TIM1 and TIM2 model
+//!
+//! The counter runs from the core clock through the prescaler in any of the
+//! counting modes, and the four channels produce output compare and PWM
+//! waveforms. Preload, slave modes, input capture and the complementary
+//! outputs of TIM1 are not modelled, writes take effect immediately.
+
+pub const TIM1: u32 = 0x4001_2C00;
+pub const TIM2: u32 = 0x4000_0000;
+pub const SIZE: u32 = 0x400;
+
+const CTLR1: u32 = 0x00;
+const INTFR: u32 = 0x10;
+const SWEVGR: u32 = 0x14;
+const CHCTLR1: u32 = 0x18;
+const CCER: u32 = 0x20;
+const CNT: u32 = 0x24;
+const PSC: u32 = 0x28;
+const ATRLR: u32 = 0x2C;
+const CHCVR: u32 = 0x34;
+const BDTR: u32 = 0x44;
+
+const CEN: u32 = 1 << 0;
+const UDIS: u32 = 1 << 1;
+const OPM: u32 = 1 << 3;
+const DIR: u32 = 1 << 4;
+const CMS: u32 = 0b11 << 5;
+const MOE: u32 = 1 << 15;
+
+pub struct Timer {
+    regs: [u32; 20],
+    /// Whether the outputs are gated by BDTR.MOE, as on TIM1
+    advanced: bool,
+    /// Counter clocks not yet counted by the prescaler
+    prescaler: u32,
+    /// Reference output of each channel, before the polarity
+    reference: [bool; 4],
+}
+
+impl Timer {
+    pub fn new(advanced: bool) -> Self {
+        let mut regs = [0; 20];
+        regs[ATRLR as usize / 4] = 0xFFFF;
+        Self {
+            regs,
+            advanced,
+            prescaler: 0,
+            reference: [false; 4],
+        }
+    }
+
+    fn reg(&self, offset: u32) -> u32 {
+        self.regs[offset as usize / 4]
+    }
+
+    fn reg_mut(&mut self, offset: u32) -> &mut u32 {
+        &mut self.regs[offset as usize / 4]
+    }
+
+    pub fn read(&self, offset: u32) -> u32 {
+        match offset {
+            SWEVGR => 0,
+            _ => self.regs.get(offset as usize / 4).copied().unwrap_or(0),
+        }
+    }
+
+    pub fn write(&mut self, offset: u32, value: u32) {
+        match offset {
+            // Flags can only be cleared
+            INTFR => *self.reg_mut(INTFR) &= value,
+            SWEVGR => {
+                if value & 1 != 0 {
+                    self.prescaler = 0;
+                    let top = if self.reg(CTLR1) & DIR != 0 {
+                        self.reg(ATRLR)
+                    } else {
+                        0
+                    };
+                    *self.reg_mut(CNT) = top;
+                    *self.reg_mut(INTFR) |= 1;
+                }
+                // Generated compare events only set their flags
+                *self.reg_mut(INTFR) |= value & 0b1_1110;
+            }
+            CNT | PSC | ATRLR => *self.reg_mut(offset) = value & 0xFFFF,
+            _ => {
+                if let Some(reg) = self.regs.get_mut(offset as usize / 4) {
+                    *reg = value;
+                }
+            }
+        }
+        self.compare(false);
+    }
+
+    /// Output mode bits of a channel, or `None` if it is an input
+    fn output_mode(&self, channel: usize) -> Option<u32> {
+        let chctlr = self.reg(CHCTLR1 + 4 * (channel as u32 / 2)) >> (8 * (channel % 2));
+        (chctlr & 0b11 == 0).then_some(chctlr >> 4 & 0b111)
+    }
+
+    /// Advance by one core cycle
+    pub fn tick(&mut self) {
+        let ctlr1 = self.reg(CTLR1);
+        if ctlr1 & CEN == 0 {
+            return;
+        }
+        self.prescaler += 1;
+        if self.prescaler <= self.reg(PSC) {
+            return;
+        }
+        self.prescaler = 0;
+
+        let arr = self.reg(ATRLR);
+        let cnt = self.reg(CNT);
+        let down = ctlr1 & DIR != 0;
+        let (cnt, update) = if ctlr1 & CMS != 0 {
+            // Center aligned, turning around at both ends
+            let cnt = if down {
+                cnt.saturating_sub(1)
+            } else {
+                (cnt + 1).min(arr)
+            };
+            let turn = if down { cnt == 0 } else { cnt == arr };
+            if turn {
+                *self.reg_mut(CTLR1) ^= DIR;
+            }
+            (cnt, turn)
+        } else if down {
+            match cnt {
+                0 => (arr, true),
+                _ => (cnt - 1, false),
+            }
+        } else if cnt >= arr {
+            (0, true)
+        } else {
+            (cnt + 1, false)
+        };
+
+        *self.reg_mut(CNT) = cnt;
+        if update {
+            if ctlr1 & UDIS == 0 {
+                *self.reg_mut(INTFR) |= 1;
+            }
+            if ctlr1 & OPM != 0 {
+                *self.reg_mut(CTLR1) &= !CEN;
+            }
+        }
+        self.compare(true);
+    }
+
+    /// Update the channel references, `counted` when the counter just changed
+    fn compare(&mut self, counted: bool) {
+        let cnt = self.reg(CNT);
+        let down = self.reg(CTLR1) & DIR != 0;
+        for channel in 0..4 {
+            let Some(mode) = self.output_mode(channel) else {
+                continue;
+            };
+            let ccr = self.reg(CHCVR + 4 * channel as u32);
+            let matched = counted && cnt == ccr;
+            if matched {
+                *self.reg_mut(INTFR) |= 1 << (channel + 1);
+            }
+
+            // PWM mode 1 is active below the compare value when counting up,
+            // and up to it when counting down
+            let active = if down { cnt <= ccr } else { cnt < ccr };
+            let reference = &mut self.reference[channel];
+            *reference = match mode {
+                0b001 if matched => true,
+                0b010 if matched => false,
+                0b011 if matched => !*reference,
+                0b100 => false,
+                0b101 => true,
+                0b110 => active,
+                0b111 => !active,
+                _ => *reference,
+            };
+        }
+    }
+
+    /// Level driven by a channel, counting from 0, or `None` while its
+    /// output is disabled
+    pub fn output(&self, channel: usize) -> Option<bool> {
+        let ccer = self.reg(CCER) >> (4 * channel);
+        let enabled = ccer & 1 != 0 && self.output_mode(channel).is_some();
+        let gated = self.advanced && self.reg(BDTR) & MOE == 0;
+        (enabled && !gated).then(|| self.reference[channel] != (ccer & 0b10 != 0))
+    }
+}
@@ -0,0 +1,107 @@
+//! Recording of pin and peripheral signal transitions
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::gpio::Pin;
+
+/// A digital line that is traced
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Signal {
+    /// Level of a GPIO pin as seen from outside the chip
+    Pin(Pin),
+    /// Output of a timer channel, counting both from 1 like the reference
+    /// manual does
+    Timer { timer: u8, channel: u8 },
+    /// Transmit line of USART1
+    UsartTx,
+}
+
+impl Signal {
+    /// Module the signal belongs to, in lowercase
+    pub fn scope(&self) -> String {
+        match self {
+            Signal::Pin(pin) => format!("gpio{}", pin.port.to_ascii_lowercase()),
+            Signal::Timer { timer, .. } => format!("tim{timer}"),
+            Signal::UsartTx => "usart1".into(),
+        }
+    }
+}
+
+impl From<Pin> for Signal {
+    fn from(pin: Pin) -> Self {
+        Signal::Pin(pin)
+    }
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Signal::Pin(pin) => write!(f, "{pin}"),
+            Signal::Timer { timer, channel } => write!(f, "TIM{timer}_CH{channel}"),
+            Signal::UsartTx => write!(f, "USART1_TX"),
+        }
+    }
+}
+
+/// A signal changing level
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Transition {
+    /// Core cycle of the instruction that caused the change
+    pub cycle: u64,
+    pub signal: Signal,
+    pub level: bool,
+}
+
+/// Every transition, in the order they happened
+///
+/// All signals start out low.
+#[derive(Default)]
+pub struct Trace {
+    transitions: Vec<Transition>,
+    levels: HashMap<Signal, bool>,
+}
+
+impl Trace {
+    /// Record a signal's level, if it changed
+    pub fn record(&mut self, cycle: u64, signal: impl Into<Signal>, level: bool) {
+        let signal = signal.into();
+        if self.levels.insert(signal, level).unwrap_or(false) != level {
+            self.transitions.push(Transition {
+                cycle,
+                signal,
+                level,
+            });
+        }
+    }
+
+    /// Current level of a signal
+    pub fn level(&self, signal: impl Into<Signal>) -> bool {
+        self.levels.get(&signal.into()).copied().unwrap_or(false)
+    }
+
+    pub fn transitions(&self) -> &[Transition] {
+        &self.transitions
+    }
+
+    /// Transitions of a single signal
+    pub fn of(&self, signal: impl Into<Signal>) -> impl Iterator<Item = &Transition> {
+        let signal = signal.into();
+        self.transitions.iter().filter(move |t| t.signal == signal)
+    }
+
+    /// Cycles between consecutive rising edges of a signal
+    pub fn periods(&self, signal: impl Into<Signal>) -> Vec<u64> {
+        let rising: Vec<u64> = self
+            .of(signal)
+            .filter(|t| t.level)
+            .map(|t| t.cycle)
+            .collect();
+        rising.windows(2).map(|w| w[1] - w[0]).collect()
+    }
+
+    /// Forget the recorded transitions, keeping the current levels
+    pub fn clear(&mut self) {
+        self.transitions.clear();
+    }
+}
@@ -0,0 +1,181 @@
+//! USART1 model, transmit side only
+//!
+//! Frames written to DATAR are shifted out on the TX line with the bit time
+//! from BRR, so a write completes like it would on the chip. Nothing is ever
+//! received.
+
+pub const BASE: u32 = 0x4001_3800;
+pub const SIZE: u32 = 0x400;
+
+const STATR: u32 = 0x00;
+const DATAR: u32 = 0x04;
+const BRR: u32 = 0x08;
+const CTLR1: u32 = 0x0C;
+const CTLR2: u32 = 0x10;
+
+const TC: u32 = 1 << 6;
+const TXE: u32 = 1 << 7;
+const RXNE: u32 = 1 << 5;
+
+const TE: u32 = 1 << 3;
+const PS: u32 = 1 << 9;
+const PCE: u32 = 1 << 10;
+const M: u32 = 1 << 12;
+const UE: u32 = 1 << 13;
+
+/// A frame on its way out
+struct Frame {
+    /// Start bit and data bits, LSB first
+    bits: u32,
+    /// Data and parity bits
+    len: u32,
+    /// Cycles since the start bit began
+    elapsed: u32,
+    word: u16,
+}
+
+pub struct Usart {
+    regs: [u32; 7],
+    /// Written to DATAR while a frame was being sent
+    pending: Option<u16>,
+    frame: Option<Frame>,
+    transmitted: Vec<u16>,
+}
+
+impl Default for Usart {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Usart {
+    pub fn new() -> Self {
+        let mut regs = [0; 7];
+        regs[STATR as usize / 4] = TC;
+        Self {
+            regs,
+            pending: None,
+            frame: None,
+            transmitted: Vec::new(),
+        }
+    }
+
+    fn reg(&self, offset: u32) -> u32 {
+        self.regs[offset as usize / 4]
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.reg(CTLR1) & (UE | TE) == UE | TE
+    }
+
+    pub fn read(&self, offset: u32) -> u32 {
+        match offset {
+            STATR => {
+                let txe = if self.pending.is_none() { TXE } else { 0 };
+                self.reg(STATR) | txe
+            }
+            _ => self.regs.get(offset as usize / 4).copied().unwrap_or(0),
+        }
+    }
+
+    pub fn write(&mut self, offset: u32, value: u32) {
+        match offset {
+            // TC and RXNE are cleared by writing 0, the rest is read only
+            STATR => self.regs[0] &= value | !(TC | RXNE),
+            DATAR => {
+                self.regs[STATR as usize / 4] &= !TC;
+                if !self.is_enabled() {
+                    return;
+                }
+                let word = value as u16 & 0x1FF;
+                match self.frame {
+                    None => self.frame = Some(self.frame(word)),
+                    Some(_) => self.pending = Some(word),
+                }
+            }
+            _ => {
+                if let Some(reg) = self.regs.get_mut(offset as usize / 4) {
+                    *reg = value;
+                }
+            }
+        }
+    }
+
+    /// Build the frame for a word with the current format
+    fn frame(&self, word: u16) -> Frame {
+        let ctlr1 = self.reg(CTLR1);
+        let len = if ctlr1 & M != 0 { 9 } else { 8 };
+        let mut data = word as u32 & ((1 << len) - 1);
+        if ctlr1 & PCE != 0 {
+            // The parity bit replaces the most significant data bit
+            data &= !(1 << (len - 1));
+            let odd = data.count_ones() % 2 == 1;
+            let parity = odd != (ctlr1 & PS != 0);
+            data |= (parity as u32) << (len - 1);
+        }
+        Frame {
+            bits: data << 1,
+            len,
+            elapsed: 0,
+            word,
+        }
+    }
+
+    /// Cycles per bit
+    fn bit_time(&self) -> u32 {
+        self.reg(BRR).max(1)
+    }
+
+    /// Frame length in cycles, including the stop bits
+    fn frame_time(&self, frame: &Frame) -> u32 {
+        let half_stops = match self.reg(CTLR2) >> 12 & 0b11 {
+            0b00 => 2,
+            0b01 => 1,
+            0b10 => 4,
+            _ => 3,
+        };
+        self.bit_time() * (1 + frame.len) + self.bit_time() * half_stops / 2
+    }
+
+    /// Advance by one core cycle
+    pub fn tick(&mut self) {
+        let Some(frame) = &self.frame else {
+            return;
+        };
+        if frame.elapsed + 1 < self.frame_time(frame) {
+            self.frame.as_mut().unwrap().elapsed += 1;
+            return;
+        }
+
+        let word = self.frame.take().unwrap().word;
+        self.transmitted.push(word);
+        match self.pending.take() {
+            Some(word) => self.frame = Some(self.frame(word)),
+            None => self.regs[STATR as usize / 4] |= TC,
+        }
+    }
+
+    /// Level of the TX line, or `None` while the transmitter is disabled
+    pub fn tx(&self) -> Option<bool> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let Some(frame) = &self.frame else {
+            return Some(true);
+        };
+        let bit = frame.elapsed / self.bit_time();
+        // Stop bits are high
+        Some(bit > frame.len || frame.bits & 1 << bit != 0)
+    }
+
+    /// Every word sent so far
+    pub fn transmitted(&self) -> &[u16] {
+        &self.transmitted
+    }
+
+    /// The words sent so far as text
+    pub fn text(&self) -> String {
+        let bytes: Vec<u8> = self.transmitted.iter().map(|&word| word as u8).collect();
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+}
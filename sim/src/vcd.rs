@@ -0,0 +1,90 @@
+//! Value change dump of a [`Trace`], for viewing in GTKWave
+//!
+//! Every GPIO pin and traced peripheral output is declared, grouped by the
+//! peripheral it belongs to. The trace counts core cycles, which are turned
+//! into time with a fixed clock frequency.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::gpio::Pin;
+use crate::trace::{Signal, Trace};
+
+/// Signals in the dump, in declaration order
+pub fn signals() -> Vec<Signal> {
+    let pins = ['A', 'C', 'D']
+        .into_iter()
+        .flat_map(|port| (0..8).map(move |pin| Signal::Pin(Pin::new(port, pin))));
+    let timers = [1, 2]
+        .into_iter()
+        .flat_map(|timer| (1..=4).map(move |channel| Signal::Timer { timer, channel }));
+    pins.chain(timers).chain([Signal::UsartTx]).collect()
+}
+
+/// Short printable identifier of the `index`th signal
+fn identifier(mut index: usize) -> String {
+    let mut id = String::new();
+    loop {
+        id.push((b'!' + (index % 94) as u8) as char);
+        index /= 94;
+        if index == 0 {
+            return id;
+        }
+    }
+}
+
+/// Write a trace that ran for `end` cycles with the core at `hclk` Hz
+pub fn write(mut out: impl Write, trace: &Trace, hclk: u32, end: u64) -> io::Result<()> {
+    let signals = signals();
+    let ids: HashMap<Signal, String> = signals
+        .iter()
+        .enumerate()
+        .map(|(index, &signal)| (signal, identifier(index)))
+        .collect();
+
+    writeln!(out, "$version hello-wch-sim $end")?;
+    writeln!(out, "$timescale 1ps $end")?;
+    writeln!(out, "$scope module ch32v003 $end")?;
+    let mut scope = None;
+    for signal in &signals {
+        let name = signal.scope();
+        if scope.as_ref() != Some(&name) {
+            if scope.is_some() {
+                writeln!(out, "$upscope $end")?;
+            }
+            writeln!(out, "$scope module {name} $end")?;
+            scope = Some(name);
+        }
+        writeln!(out, "$var wire 1 {} {signal} $end", ids[signal])?;
+    }
+    writeln!(out, "$upscope $end")?;
+    writeln!(out, "$upscope $end")?;
+    writeln!(out, "$enddefinitions $end")?;
+
+    // Everything starts out low
+    writeln!(out, "#0")?;
+    writeln!(out, "$dumpvars")?;
+    for signal in &signals {
+        writeln!(out, "0{}", ids[signal])?;
+    }
+    writeln!(out, "$end")?;
+
+    let time = |cycle: u64| (cycle as u128 * 1_000_000_000_000 / hclk as u128) as u64;
+    let mut now = 0;
+    for transition in trace.transitions() {
+        let Some(id) = ids.get(&transition.signal) else {
+            continue;
+        };
+        let at = time(transition.cycle);
+        if at != now {
+            writeln!(out, "#{at}")?;
+            now = at;
+        }
+        writeln!(out, "{}{id}", transition.level as u8)?;
+    }
+    // Mark the end so the last levels show up
+    if time(end) > now {
+        writeln!(out, "#{}", time(end))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,93 @@
+//! Runs the firmware of this repository, building it first with `cargo
+//! build` in the repository root.
+
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::OnceLock;
+
+use hello_wch_sim::gpio::{Mode, Pin, Speed};
+use hello_wch_sim::{bus, rcc, Simulator};
+
+/// Build the firmware once for all tests, returning the path of the ELF
+fn build() -> &'static PathBuf {
+    static ELF: OnceLock<PathBuf> = OnceLock::new();
+    ELF.get_or_init(|| {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("..");
+        let status = Command::new(env!("CARGO"))
+            .args(["build", "-p", "hello-wch"])
+            .current_dir(&root)
+            .status()
+            .expect("failed to run cargo");
+        assert!(status.success(), "building the firmware failed");
+        root.join("target/riscv32ec-unknown-none-elf/debug/hello-wch")
+    })
+}
+
+fn firmware() -> Simulator {
+    let path = build();
+    let file = std::fs::read(path).unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+    Simulator::from_elf(&file).unwrap()
+}
+
+#[test]
+fn startup_reaches_main() {
+    let mut sim = firmware();
+    let main = sim.elf().symbol("main").unwrap();
+
+    assert!(sim.run_until(main, 100_000).unwrap());
+
+    // The stack sits at the top of RAM
+    let sp = sim.cpu.reg(2);
+    assert!(sp > bus::RAM_BASE && sp <= bus::RAM_BASE + bus::RAM_SIZE);
+}
+
+#[test]
+fn led_is_push_pull_output() {
+    let mut sim = firmware();
+    let main = sim.elf().symbol("main").unwrap();
+    sim.run_until(main, 100_000).unwrap();
+    sim.run(200_000).unwrap();
+
+    assert!(sim.bus.rcc.apb2_enabled(rcc::gpio_bit('C')));
+    assert_eq!(
+        sim.bus.gpio('C').mode(1),
+        Mode::Output {
+            open_drain: false,
+            speed: Speed::Mhz10
+        }
+    );
+}
+
+#[test]
+fn led_blinks_once_a_second() {
+    let mut sim = firmware();
+    // A bit over two periods at 24 MHz
+    sim.run(60_000_000).unwrap();
+
+    let periods = sim.bus.trace.periods(Pin::new('C', 1));
+    assert!(!periods.is_empty());
+    for period in periods {
+        // Logging adds a little on top of the two delays
+        assert!(period.abs_diff(24_000_000) < 240_000, "period of {period}");
+    }
+}
+
+#[test]
+fn logs_blink_count() {
+    let mut sim = firmware();
+    sim.run(30_000_000).unwrap();
+
+    assert!(sim.bus.usart.text().starts_with("blink 0\n"));
+}
+
+#[test]
+fn vcd_declares_every_pin() {
+    let mut sim = firmware();
+    sim.run(1_000_000).unwrap();
+
+    let mut vcd = Vec::new();
+    sim.write_vcd(&mut vcd).unwrap();
+    let vcd = String::from_utf8(vcd).unwrap();
+    assert!(vcd.contains(" PC1 $end"));
+    assert!(vcd.contains(" USART1_TX $end"));
+}
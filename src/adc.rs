@@ -0,0 +1,460 @@
+//! ADC1 analog to digital converter
+//!
+//! A 10-bit converter with eight pin channels and the internal reference
+//! voltage. Pins are read by passing them in analog mode:
+//!
+//! ```ignore
+//! let mut adc = Adc::new(p.ADC1, &clocks);
+//! let mut pot = gpioc.pc4.into_analog();
+//! let raw = adc.convert(&mut pot);
+//! let vdd_mv = adc.supply_voltage();
+//! ```
+//!
+//! Several channels are converted in one go by scanning a [`Sequence`], a
+//! tuple of channels, either into a buffer with DMA or as injected
+//! conversions. Both can be started by TIM1 and TIM2 events.
+
+use core::convert::Infallible;
+
+use embedded_hal::delay::DelayNs;
+
+use crate::delay::CycleDelay;
+use crate::dma::{self, CircBuffer, Direction, Transfer, WriteBuffer};
+use crate::gpio::{Analog, PA1, PA2, PC4, PD2, PD3, PD4, PD5, PD6};
+use crate::pac;
+use crate::rcc::{Clocks, Enable};
+
+/// Highest conversion result
+pub const MAX: u16 = 1023;
+
+/// Typical voltage of [`Vrefint`] in millivolts
+pub const VREFINT_MV: u32 = 1200;
+
+/// Highest ADC clock
+const ADC_CLOCK_MAX: u32 = 24_000_000;
+
+/// Time the converter takes to power up after ADON is set, tSTAB in the
+/// datasheet
+const POWER_UP_US: u32 = 1;
+
+/// An input of the converter
+pub trait Channel {
+    const CHANNEL: u8;
+}
+
+macro_rules! channels {
+    ($($PIN:ident: $channel:literal,)+) => {
+        $(
+            impl Channel for $PIN<Analog> {
+                const CHANNEL: u8 = $channel;
+            }
+        )+
+    };
+}
+
+channels! {
+    PA2: 0,
+    PA1: 1,
+    PC4: 2,
+    PD2: 3,
+    PD3: 4,
+    PD5: 5,
+    PD6: 6,
+    PD4: 7,
+}
+
+/// The internal reference voltage, about [`VREFINT_MV`]
+pub struct Vrefint;
+
+impl Channel for Vrefint {
+    const CHANNEL: u8 = 8;
+}
+
+/// Channels converted one after another, as a tuple of up to 8
+pub trait Sequence {
+    const CHANNELS: &'static [u8];
+}
+
+macro_rules! sequence {
+    ($(($($C:ident),+),)+) => {
+        $(
+            impl<$($C: Channel),+> Sequence for ($($C,)+) {
+                const CHANNELS: &'static [u8] = &[$($C::CHANNEL),+];
+            }
+        )+
+    };
+}
+
+sequence! {
+    (A),
+    (A, B),
+    (A, B, C),
+    (A, B, C, D),
+    (A, B, C, D, E),
+    (A, B, C, D, E, F),
+    (A, B, C, D, E, F, G),
+    (A, B, C, D, E, F, G, H),
+}
+
+/// Sequences that fit the 4 injected slots
+pub trait InjectedSequence: Sequence {}
+
+impl<A: Channel> InjectedSequence for (A,) {}
+impl<A: Channel, B: Channel> InjectedSequence for (A, B) {}
+impl<A: Channel, B: Channel, C: Channel> InjectedSequence for (A, B, C) {}
+impl<A: Channel, B: Channel, C: Channel, D: Channel> InjectedSequence for (A, B, C, D) {}
+
+/// Time the input is sampled for, in ADC clock cycles
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SampleTime {
+    Cycles3 = 0b000,
+    Cycles9 = 0b001,
+    Cycles15 = 0b010,
+    Cycles30 = 0b011,
+    Cycles43 = 0b100,
+    Cycles57 = 0b101,
+    Cycles73 = 0b110,
+    Cycles241 = 0b111,
+}
+
+/// Placement of the 10 result bits in the 16-bit data registers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Align {
+    Right,
+    Left,
+}
+
+/// What starts a scan of the regular sequence
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegularTrigger {
+    Tim1Trgo = 0b000,
+    Tim1Cc1 = 0b001,
+    Tim1Cc2 = 0b010,
+    Tim2Trgo = 0b011,
+    Tim2Cc1 = 0b100,
+    Tim2Cc2 = 0b101,
+    /// The external trigger pin, selected in AFIO
+    Pin = 0b110,
+    /// Scans run back to back as soon as started
+    Software = 0b111,
+}
+
+/// What starts the injected conversions
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InjectedTrigger {
+    Tim1Cc3 = 0b000,
+    Tim1Cc4 = 0b001,
+    Tim2Cc3 = 0b010,
+    Tim2Cc4 = 0b011,
+    /// The external trigger pin, selected in AFIO
+    Pin = 0b110,
+    /// [`Adc::start_injected`]
+    Software = 0b111,
+}
+
+/// The ADC1 converter
+pub struct Adc {
+    adc: pac::ADC1,
+}
+
+impl Adc {
+    /// Power up and calibrate the converter
+    pub fn new(adc: pac::ADC1, clocks: &Clocks) -> Self {
+        pac::ADC1::enable_and_reset();
+
+        // HCLK divided by 2, or by 4 when that would be too fast
+        let adcpre = if clocks.hclk() / 2 <= ADC_CLOCK_MAX {
+            0b00000
+        } else {
+            0b01000
+        };
+        let rcc = unsafe { &*pac::RCC::PTR };
+        critical_section::with(|_| rcc.cfgr0.modify(|_, w| w.adcpre().bits(adcpre)));
+
+        // Regular conversions always need a trigger, SWSTART by default
+        adc.ctlr2.write(|w| {
+            w.adon()
+                .set_bit()
+                .extsel()
+                .bits(RegularTrigger::Software as u32)
+                .exttrig()
+                .set_bit()
+        });
+
+        // Calibrating before the converter is powered up gives a wrong offset
+        CycleDelay::new(clocks).delay_us(POWER_UP_US);
+
+        let mut adc = Self { adc };
+        adc.calibrate();
+        adc
+    }
+
+    /// Give back the peripheral, powered down
+    pub fn release(self) -> pac::ADC1 {
+        self.adc.ctlr2.reset();
+        self.adc
+    }
+
+    /// Measure the offset of the converter, which is then subtracted from
+    /// every result
+    pub fn calibrate(&mut self) {
+        self.adc.ctlr2.modify(|_, w| w.rstcal().set_bit());
+        while self.adc.ctlr2.read().rstcal().bit_is_set() {}
+        self.adc.ctlr2.modify(|_, w| w.cal().set_bit());
+        while self.adc.ctlr2.read().cal().bit_is_set() {}
+    }
+
+    /// Sample `channel` for `time` in every conversion
+    pub fn set_sample_time<C: Channel>(&mut self, _channel: &C, time: SampleTime) {
+        let shift = 3 * C::CHANNEL as u32;
+        self.adc.samptr2.modify(|r, w| unsafe {
+            w.bits(r.bits() & !(0b111 << shift) | (time as u32) << shift)
+        });
+    }
+
+    pub fn set_align(&mut self, align: Align) {
+        self.adc
+            .ctlr2
+            .modify(|_, w| w.align().bit(align == Align::Left));
+    }
+
+    /// Convert a channel once
+    pub fn convert<C: Channel>(&mut self, _channel: &mut C) -> u16 {
+        self.set_regular(&[C::CHANNEL]);
+        self.adc
+            .ctlr2
+            .modify(|_, w| w.extsel().bits(RegularTrigger::Software as u32));
+        self.adc.ctlr2.modify(|_, w| w.swstart().set_bit());
+        while self.adc.statr.read().eoc().bit_is_clear() {}
+        self.adc.rdatar.read().data().bits() as u16
+    }
+
+    /// The supply voltage in millivolts, measured against [`Vrefint`]
+    pub fn supply_voltage(&mut self) -> u32 {
+        let raw = self.convert(&mut Vrefint) as u32;
+        VREFINT_MV * MAX as u32 / raw.max(1)
+    }
+
+    /// Keep converting a channel until [`Adc::stop`]
+    pub fn start_continuous<C: Channel>(&mut self, _channel: &mut C) {
+        self.set_regular(&[C::CHANNEL]);
+        self.adc.ctlr2.modify(|_, w| {
+            w.extsel()
+                .bits(RegularTrigger::Software as u32)
+                .cont()
+                .set_bit()
+        });
+        self.adc.ctlr2.modify(|_, w| w.swstart().set_bit());
+    }
+
+    /// Take the latest continuous conversion if there is a new one
+    pub fn read(&mut self) -> nb::Result<u16, Infallible> {
+        if self.adc.statr.read().eoc().bit_is_set() {
+            // Reading the data clears EOC
+            Ok(self.adc.rdatar.read().data().bits() as u16)
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// Stop continuous conversions and scans
+    pub fn stop(&mut self) {
+        self.adc.ctlr2.modify(|_, w| {
+            w.cont()
+                .clear_bit()
+                .dma()
+                .clear_bit()
+                .extsel()
+                .bits(RegularTrigger::Software as u32)
+        });
+        self.adc.ctlr1.modify(|_, w| w.scan().clear_bit());
+    }
+
+    /// Scan a sequence on every trigger, filling a buffer through DMA
+    ///
+    /// Results are stored in sequence order, so the buffer should hold a
+    /// multiple of the sequence length. With [`RegularTrigger::Software`]
+    /// scans repeat until the buffer is full, call [`Adc::stop`] once the
+    /// transfer is done.
+    pub fn scan_dma<S, B>(
+        mut self,
+        sequence: S,
+        trigger: RegularTrigger,
+        mut channel: dma::C1,
+        mut buffer: B,
+    ) -> Transfer<1, B, (Adc, S)>
+    where
+        S: Sequence,
+        B: WriteBuffer<Word = u16>,
+    {
+        let (ptr, len) = buffer.write_buffer();
+        channel.configure(
+            self.adc.rdatar.as_ptr() as u32,
+            ptr,
+            len,
+            Direction::PeripheralToMemory,
+        );
+        self.start_scan(S::CHANNELS, trigger);
+        let transfer = Transfer::start(channel, buffer, (self, sequence));
+        Self::start_software(trigger);
+        transfer
+    }
+
+    /// Keep scanning a sequence into the two halves of a buffer with DMA
+    ///
+    /// Each half should hold a multiple of the sequence length.
+    pub fn scan_circular<S: Sequence, const M: usize>(
+        mut self,
+        sequence: S,
+        trigger: RegularTrigger,
+        mut channel: dma::C1,
+        buffer: &'static mut [[u16; M]; 2],
+    ) -> CircBuffer<1, [u16; M], (Adc, S)> {
+        channel.configure(
+            self.adc.rdatar.as_ptr() as u32,
+            buffer.as_ptr() as *const u16,
+            2 * M,
+            Direction::PeripheralToMemory,
+        );
+        self.start_scan(S::CHANNELS, trigger);
+        let transfer = CircBuffer::start(channel, buffer, (self, sequence));
+        Self::start_software(trigger);
+        transfer
+    }
+
+    /// Set up scanning with DMA requests, conversions back to back with
+    /// the software trigger
+    fn start_scan(&mut self, channels: &[u8], trigger: RegularTrigger) {
+        self.set_regular(channels);
+        self.adc.ctlr1.modify(|_, w| w.scan().set_bit());
+        self.adc.ctlr2.modify(|_, w| {
+            w.dma()
+                .set_bit()
+                .cont()
+                .bit(trigger == RegularTrigger::Software)
+                .extsel()
+                .bits(trigger as u32)
+        });
+    }
+
+    /// Kick off the first scan once DMA is ready for it
+    fn start_software(trigger: RegularTrigger) {
+        if trigger == RegularTrigger::Software {
+            let adc = unsafe { &*pac::ADC1::PTR };
+            adc.ctlr2.modify(|_, w| w.swstart().set_bit());
+        }
+    }
+
+    /// Set up the injected conversions, up to 4 channels
+    ///
+    /// Injected conversions interrupt regular ones and keep their results
+    /// in separate registers.
+    pub fn set_injected<S: InjectedSequence>(
+        &mut self,
+        _sequence: &mut S,
+        trigger: InjectedTrigger,
+    ) {
+        let channels = S::CHANNELS;
+        // A shorter sequence takes the last slots
+        let start = 4 - channels.len();
+        let mut isqr = (channels.len() as u32 - 1) << 20;
+        for (i, &channel) in channels.iter().enumerate() {
+            isqr |= (channel as u32) << (5 * (start + i));
+        }
+        self.adc.isqr.write(|w| unsafe { w.bits(isqr) });
+        self.adc.ctlr1.modify(|_, w| w.scan().set_bit());
+        self.adc
+            .ctlr2
+            .modify(|_, w| w.jextsel().bits(trigger as u32).jexttrig().set_bit());
+    }
+
+    /// Subtract `offset` from the `index`th injected result
+    pub fn set_injected_offset(&mut self, index: usize, offset: u16) {
+        self.adc.iofr[index].write(|w| w.joffset().bits(offset as u32));
+    }
+
+    /// Start the injected conversions, with [`InjectedTrigger::Software`]
+    pub fn start_injected(&mut self) {
+        self.adc.ctlr2.modify(|_, w| w.jswstart().set_bit());
+    }
+
+    /// Copy the injected results once they are all done
+    pub fn read_injected(&mut self, values: &mut [u16]) -> nb::Result<(), Infallible> {
+        if self.adc.statr.read().jeoc().bit_is_clear() {
+            return Err(nb::Error::WouldBlock);
+        }
+        self.adc.statr.write(|w| unsafe { w.bits(!(1 << 2)) });
+        for (value, data) in values.iter_mut().zip(&self.adc.idatar) {
+            *value = data.read().jdata().bits() as u16;
+        }
+        Ok(())
+    }
+
+    /// Flag results of any channel outside `low..=high`
+    pub fn watch_all(&mut self, low: u16, high: u16) {
+        self.set_watchdog(low, high);
+        self.adc
+            .ctlr1
+            .modify(|_, w| w.awdsgl().clear_bit().awden().set_bit().jawden().set_bit());
+    }
+
+    /// Flag results of `channel` outside `low..=high`
+    pub fn watch<C: Channel>(&mut self, _channel: &C, low: u16, high: u16) {
+        self.set_watchdog(low, high);
+        self.adc.ctlr1.modify(|_, w| {
+            w.awdch()
+                .bits(C::CHANNEL as u32)
+                .awdsgl()
+                .set_bit()
+                .awden()
+                .set_bit()
+                .jawden()
+                .set_bit()
+        });
+    }
+
+    pub fn unwatch(&mut self) {
+        self.adc
+            .ctlr1
+            .modify(|_, w| w.awden().clear_bit().jawden().clear_bit());
+    }
+
+    /// Whether a result fell outside the window
+    pub fn is_out_of_window(&self) -> bool {
+        self.adc.statr.read().awd().bit_is_set()
+    }
+
+    pub fn clear_out_of_window(&mut self) {
+        self.adc.statr.write(|w| unsafe { w.bits(!(1 << 0)) });
+    }
+
+    /// Raise the ADC interrupt when a result falls outside the window
+    pub fn enable_watchdog_interrupt(&mut self) {
+        self.adc.ctlr1.modify(|_, w| w.awdie().set_bit());
+    }
+
+    pub fn disable_watchdog_interrupt(&mut self) {
+        self.adc.ctlr1.modify(|_, w| w.awdie().clear_bit());
+    }
+
+    fn set_watchdog(&mut self, low: u16, high: u16) {
+        self.adc.wdltr.write(|w| w.lt().bits(low as u32));
+        self.adc.wdhtr.write(|w| w.ht().bits(high as u32));
+    }
+
+    /// Write the regular sequence
+    fn set_regular(&mut self, channels: &[u8]) {
+        let (mut rsqr2, mut rsqr3) = (0, 0);
+        for (i, &channel) in channels.iter().enumerate() {
+            match i {
+                0..=5 => rsqr3 |= (channel as u32) << (5 * i),
+                _ => rsqr2 |= (channel as u32) << (5 * (i - 6)),
+            }
+        }
+        self.adc.rsqr3.write(|w| unsafe { w.bits(rsqr3) });
+        self.adc.rsqr2.write(|w| unsafe { w.bits(rsqr2) });
+        self.adc
+            .rsqr1
+            .write(|w| w.l().bits(channels.len() as u32 - 1));
+    }
+}
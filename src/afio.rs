@@ -0,0 +1,326 @@
+//! Alternate function I/O remapping
+//!
+//! USART1, SPI1, I2C1, TIM1 and TIM2 can each be moved between a few pin
+//! sets through PCFR1. Every set is a type implementing [`Remap`], and the
+//! pins of a peripheral implement its pin traits only for the sets they are
+//! part of. Drivers pick the set from the pins they are given, so mixing
+//! pins of different sets fails to compile:
+//!
+//! ```ignore
+//! // TX on PD6 and RX on PD5 select usart1::Remap2
+//! let tx = gpiod.pd6.into_alternate_push_pull();
+//! let rx = gpiod.pd5.into_floating_input();
+//! let serial = Serial::new(p.USART1, (tx, rx), serial::Config::default(), &clocks);
+//! ```
+//!
+//! PD1 is the serial wire debug pin after reset. It is handed out as
+//! `PD1<Debugger>` and has to be released with [`Afio::disable_swd`] before
+//! it can be used for anything else.
+
+use crate::gpio::{Active, Debugger, Floating, Input, PD1};
+use crate::pac;
+use crate::rcc::Enable;
+
+pub trait AfioExt {
+    /// Enable the AFIO clock
+    fn constrain(self) -> Afio;
+}
+
+impl AfioExt for pac::AFIO {
+    fn constrain(self) -> Afio {
+        pac::AFIO::enable();
+        Afio { afio: self }
+    }
+}
+
+/// The AFIO block, for the settings that are not tied to a driver
+pub struct Afio {
+    afio: pac::AFIO,
+}
+
+impl Afio {
+    /// Turn off serial wire debug to use PD1 as a regular pin
+    ///
+    /// A debugger can not attach until [`Afio::enable_swd`] is called, or
+    /// the chip is reset.
+    pub fn disable_swd(&mut self, pd1: PD1<Debugger>) -> PD1<Input<Floating>> {
+        critical_section::with(|_| self.afio.pcfr1.modify(|_, w| w.swcfg().bits(0b100)));
+        pd1.into_active()
+    }
+
+    /// Give PD1 back to the debugger
+    pub fn enable_swd<MODE: Active>(&mut self, pd1: PD1<MODE>) -> PD1<Debugger> {
+        let pd1 = pd1.into_debugger();
+        critical_section::with(|_| self.afio.pcfr1.modify(|_, w| w.swcfg().bits(0b000)));
+        pd1
+    }
+
+    /// Connect EXTI line `line` to the pin with that number on `port`
+    pub(crate) fn set_exti_port(&mut self, line: u8, port: char) {
+        let bits = match port {
+            'A' => 0b00,
+            'C' => 0b10,
+            _ => 0b11,
+        };
+        let shift = line * 2;
+        self.afio
+            .exticr
+            .modify(|r, w| unsafe { w.bits(r.bits() & !(0b11 << shift) | bits << shift) });
+    }
+
+    pub fn release(self) -> pac::AFIO {
+        self.afio
+    }
+}
+
+/// A pin set of a peripheral
+pub trait Remap {
+    /// The peripheral being moved
+    type Peripheral;
+    /// Bits of PCFR1 that select the pin set of the peripheral
+    const MASK: u32;
+    /// Value of those bits for this set
+    const BITS: u32;
+}
+
+/// Select a pin set, leaving the other peripherals alone
+pub(crate) fn remap<R: Remap>() {
+    pac::AFIO::enable();
+    let afio = unsafe { &*pac::AFIO::PTR };
+    critical_section::with(|_| {
+        afio.pcfr1
+            .modify(|r, w| unsafe { w.bits(r.bits() & !R::MASK | R::BITS) })
+    });
+}
+
+macro_rules! remap {
+    ($PER:ident, $mask:expr, [$($(#[$meta:meta])* $Remap:ident = $bits:expr,)+]) => {
+        $(
+            $(#[$meta])*
+            pub struct $Remap;
+
+            impl super::Remap for $Remap {
+                type Peripheral = crate::pac::$PER;
+                const MASK: u32 = $mask;
+                const BITS: u32 = $bits;
+            }
+        )+
+    };
+}
+
+/// Implement a pin trait for pins in any mode
+macro_rules! pins {
+    ($($Trait:ident$(<$C:literal>)?: [$($Remap:ident => $PIN:ident,)+])+) => {
+        $(pins!(@impl $Trait$(<$C>)? [$($Remap $PIN)+]);)+
+    };
+    (@impl $Trait:ident<$C:literal> [$($Remap:ident $PIN:ident)+]) => {
+        $(impl<MODE: Active> $Trait<$Remap, $C> for $PIN<MODE> {})+
+    };
+    (@impl $Trait:ident [$($Remap:ident $PIN:ident)+]) => {
+        $(impl<MODE: Active> $Trait<$Remap> for $PIN<MODE> {})+
+    };
+}
+
+/// Channel `C` of a timer in pin set `R`
+///
+/// Shared by TIM1 and TIM2 so timer drivers can be generic over the two.
+pub trait TimChannel<R, const C: u8> {}
+
+/// Complementary output of channel `C` of TIM1 in pin set `R`
+pub trait TimChannelN<R, const C: u8> {}
+
+/// USART1 pin sets, the RTS, CTS and CK pins are not used by the driver
+pub mod usart1 {
+    use crate::gpio::{Alternate, Input, PushPull, PC0, PC1, PD0, PD1, PD5, PD6};
+
+    remap!(
+        USART1,
+        1 << 2 | 1 << 21,
+        [
+            /// TX on PD5, RX on PD6
+            Remap0 = 0,
+            /// TX on PD0, RX on PD1
+            Remap1 = 1 << 2,
+            /// TX on PD6, RX on PD5
+            Remap2 = 1 << 21,
+            /// TX on PC0, RX on PC1
+            Remap3 = 1 << 2 | 1 << 21,
+        ]
+    );
+
+    /// Transmit pin of pin set `R`
+    pub trait Tx<R> {}
+    /// Receive pin of pin set `R`
+    pub trait Rx<R> {}
+
+    impl Tx<Remap0> for PD5<Alternate<PushPull>> {}
+    impl Tx<Remap1> for PD0<Alternate<PushPull>> {}
+    impl Tx<Remap2> for PD6<Alternate<PushPull>> {}
+    impl Tx<Remap3> for PC0<Alternate<PushPull>> {}
+
+    impl<MODE> Rx<Remap0> for PD6<Input<MODE>> {}
+    impl<MODE> Rx<Remap1> for PD1<Input<MODE>> {}
+    impl<MODE> Rx<Remap2> for PD5<Input<MODE>> {}
+    impl<MODE> Rx<Remap3> for PC1<Input<MODE>> {}
+}
+
+/// SPI1 pin sets, only NSS moves
+pub mod spi1 {
+    use crate::gpio::{Alternate, Input, PushPull, PC0, PC1, PC5, PC6, PC7};
+
+    remap!(
+        SPI1,
+        1 << 0,
+        [
+            /// NSS on PC1, SCK on PC5, MISO on PC7, MOSI on PC6
+            Remap0 = 0,
+            /// NSS on PC0, SCK on PC5, MISO on PC7, MOSI on PC6
+            Remap1 = 1 << 0,
+        ]
+    );
+
+    /// Clock output of pin set `R`
+    pub trait Sck<R> {}
+    /// Data input of pin set `R`, in master mode
+    pub trait Miso<R> {}
+    /// Data output of pin set `R`, in master mode
+    pub trait Mosi<R> {}
+    /// Hardware chip select output of pin set `R`
+    pub trait Nss<R> {}
+
+    impl Sck<Remap0> for PC5<Alternate<PushPull>> {}
+    impl Sck<Remap1> for PC5<Alternate<PushPull>> {}
+    impl<MODE> Miso<Remap0> for PC7<Input<MODE>> {}
+    impl<MODE> Miso<Remap1> for PC7<Input<MODE>> {}
+    impl Mosi<Remap0> for PC6<Alternate<PushPull>> {}
+    impl Mosi<Remap1> for PC6<Alternate<PushPull>> {}
+    impl Nss<Remap0> for PC1<Alternate<PushPull>> {}
+    impl Nss<Remap1> for PC0<Alternate<PushPull>> {}
+
+    /// Clock input of pin set `R`, in slave mode
+    pub trait SlaveSck<R> {}
+    /// Data output of pin set `R`, in slave mode
+    pub trait SlaveMiso<R> {}
+    /// Data input of pin set `R`, in slave mode
+    pub trait SlaveMosi<R> {}
+    /// Chip select input of pin set `R`, in slave mode
+    pub trait SlaveNss<R> {}
+
+    impl<MODE> SlaveSck<Remap0> for PC5<Input<MODE>> {}
+    impl<MODE> SlaveSck<Remap1> for PC5<Input<MODE>> {}
+    impl SlaveMiso<Remap0> for PC7<Alternate<PushPull>> {}
+    impl SlaveMiso<Remap1> for PC7<Alternate<PushPull>> {}
+    impl<MODE> SlaveMosi<Remap0> for PC6<Input<MODE>> {}
+    impl<MODE> SlaveMosi<Remap1> for PC6<Input<MODE>> {}
+    impl<MODE> SlaveNss<Remap0> for PC1<Input<MODE>> {}
+    impl<MODE> SlaveNss<Remap1> for PC0<Input<MODE>> {}
+}
+
+/// I2C1 pin sets
+pub mod i2c1 {
+    use crate::gpio::{Alternate, OpenDrain, PC1, PC2, PC5, PC6, PD0, PD1};
+
+    remap!(
+        I2C1,
+        1 << 1 | 1 << 22,
+        [
+            /// SCL on PC2, SDA on PC1
+            Remap0 = 0,
+            /// SCL on PD1, SDA on PD0
+            Remap1 = 1 << 1,
+            /// SCL on PC5, SDA on PC6
+            Remap2 = 1 << 22,
+        ]
+    );
+
+    /// Clock pin of pin set `R`
+    pub trait Scl<R> {}
+    /// Data pin of pin set `R`
+    pub trait Sda<R> {}
+
+    impl Scl<Remap0> for PC2<Alternate<OpenDrain>> {}
+    impl Scl<Remap1> for PD1<Alternate<OpenDrain>> {}
+    impl Scl<Remap2> for PC5<Alternate<OpenDrain>> {}
+
+    impl Sda<Remap0> for PC1<Alternate<OpenDrain>> {}
+    impl Sda<Remap1> for PD0<Alternate<OpenDrain>> {}
+    impl Sda<Remap2> for PC6<Alternate<OpenDrain>> {}
+}
+
+/// TIM1 pin sets
+///
+/// Channels can be outputs or inputs, so the pins are accepted in any mode.
+pub mod tim1 {
+    use super::{TimChannel, TimChannelN};
+    use crate::gpio::{Active, PA1, PA2, PC0, PC1, PC2, PC3, PC4, PC5, PC6, PC7};
+    use crate::gpio::{PD0, PD1, PD2, PD3, PD4};
+
+    remap!(
+        TIM1,
+        0b11 << 6,
+        [
+            /// CH1 on PD2, CH2 on PA1, CH3 on PC3, CH4 on PC4, CH1N on PD0,
+            /// CH2N on PA2, CH3N on PD1, BKIN on PC2 and ETR on PC5
+            Remap0 = 0b00 << 6,
+            /// CH1 on PC6, CH2 on PC7, CH3 on PC0, CH4 on PD3, CH1N on PC3,
+            /// CH2N on PC4, CH3N on PD1, BKIN on PC1 and ETR on PC5
+            Remap1 = 0b01 << 6,
+            /// Like [`Remap0`], with ETR on PD4
+            Remap2 = 0b10 << 6,
+            /// CH1 on PC4, CH2 on PC7, CH3 on PC5, CH4 on PD4, CH1N on PC3,
+            /// CH2N on PD2, CH3N on PC6, BKIN on PC1 and ETR on PC2
+            Remap3 = 0b11 << 6,
+        ]
+    );
+
+    /// Break input of pin set `R`
+    pub trait Bkin<R> {}
+    /// External trigger input of pin set `R`
+    pub trait Etr<R> {}
+
+    pins! {
+        TimChannel<1>: [Remap0 => PD2, Remap1 => PC6, Remap2 => PD2, Remap3 => PC4,]
+        TimChannel<2>: [Remap0 => PA1, Remap1 => PC7, Remap2 => PA1, Remap3 => PC7,]
+        TimChannel<3>: [Remap0 => PC3, Remap1 => PC0, Remap2 => PC3, Remap3 => PC5,]
+        TimChannel<4>: [Remap0 => PC4, Remap1 => PD3, Remap2 => PC4, Remap3 => PD4,]
+        TimChannelN<1>: [Remap0 => PD0, Remap1 => PC3, Remap2 => PD0, Remap3 => PC3,]
+        TimChannelN<2>: [Remap0 => PA2, Remap1 => PC4, Remap2 => PA2, Remap3 => PD2,]
+        TimChannelN<3>: [Remap0 => PD1, Remap1 => PD1, Remap2 => PD1, Remap3 => PC6,]
+        Bkin: [Remap0 => PC2, Remap1 => PC1, Remap2 => PC2, Remap3 => PC1,]
+        Etr: [Remap0 => PC5, Remap1 => PC5, Remap2 => PD4, Remap3 => PC2,]
+    }
+}
+
+/// TIM2 pin sets
+///
+/// Channels can be outputs or inputs, so the pins are accepted in any mode.
+pub mod tim2 {
+    use super::TimChannel;
+    use crate::gpio::{Active, PC0, PC1, PC2, PC5, PC7, PD2, PD3, PD4, PD5, PD6, PD7};
+
+    remap!(
+        TIM2,
+        0b11 << 8,
+        [
+            /// CH1 and ETR on PD4, CH2 on PD3, CH3 on PC0, CH4 on PD7
+            Remap0 = 0b00 << 8,
+            /// CH1 and ETR on PC5, CH2 on PC2, CH3 on PD2, CH4 on PC1
+            Remap1 = 0b01 << 8,
+            /// CH1 and ETR on PC1, CH2 on PD3, CH3 on PC0, CH4 on PD7
+            Remap2 = 0b10 << 8,
+            /// CH1 and ETR on PC1, CH2 on PC7, CH3 on PD6, CH4 on PD5
+            Remap3 = 0b11 << 8,
+        ]
+    );
+
+    /// External trigger input of pin set `R`
+    pub trait Etr<R> {}
+
+    pins! {
+        TimChannel<1>: [Remap0 => PD4, Remap1 => PC5, Remap2 => PC1, Remap3 => PC1,]
+        TimChannel<2>: [Remap0 => PD3, Remap1 => PC2, Remap2 => PD3, Remap3 => PC7,]
+        TimChannel<3>: [Remap0 => PC0, Remap1 => PD2, Remap2 => PC0, Remap3 => PD6,]
+        TimChannel<4>: [Remap0 => PD7, Remap1 => PC1, Remap2 => PD7, Remap3 => PD5,]
+        Etr: [Remap0 => PD4, Remap1 => PC5, Remap2 => PC1, Remap3 => PC1,]
+    }
+}
@@ -0,0 +1,5 @@
+//! The chip this crate is built for
+//!
+//! Selected with one of the chip features, generated by `build.rs`.
+
+include!(concat!(env!("OUT_DIR"), "/chip.rs"));
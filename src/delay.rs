@@ -0,0 +1,34 @@
+//! Busy-wait delays
+
+use embedded_hal::delay::DelayNs;
+
+use crate::rcc::Clocks;
+
+/// Delay that spins for a computed number of core clock cycles
+///
+/// Flash wait states and interrupts make it run long, never short.
+pub struct CycleDelay {
+    hclk: u32,
+}
+
+impl CycleDelay {
+    pub fn new(clocks: &Clocks) -> Self {
+        Self {
+            hclk: clocks.hclk(),
+        }
+    }
+}
+
+impl DelayNs for CycleDelay {
+    fn delay_ns(&mut self, ns: u32) {
+        let cycles = (ns as u64 * self.hclk as u64).div_ceil(1_000_000_000);
+        riscv::asm::delay(cycles as u32);
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        for _ in 0..us / 1000 {
+            self.delay_ns(1_000_000);
+        }
+        self.delay_ns(us % 1000 * 1000);
+    }
+}
@@ -0,0 +1,565 @@
+//! Direct memory access controller
+//!
+//! DMA1 is split into its seven channels, which are then handed to the
+//! drivers that move data with them. Each peripheral request is wired to a
+//! fixed channel, for example USART1 TX to channel 4.
+//!
+//! A running [`Transfer`] owns its channel, its buffer and the peripheral,
+//! and gives them back once it is done. Buffers are `'static` references,
+//! plain or pinned, so the memory stays valid and in place even if a
+//! transfer is leaked:
+//!
+//! ```ignore
+//! static mut BUF: [u8; 16] = [0; 16];
+//! let dma = p.DMA1.split();
+//! let transfer = rx.read_dma(dma.ch5, unsafe { &mut *addr_of_mut!(BUF) });
+//! let (buf, ch5, rx) = transfer.wait();
+//! ```
+//!
+//! In circular mode the channel keeps refilling a buffer split into two
+//! halves, and [`CircBuffer`] hands out the half that was last completed
+//! while the other one is being written. Any free channel can also copy
+//! between two buffers with [`Channel::copy`].
+
+use core::pin::Pin;
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use crate::pac::{self, dma::CH};
+
+/// Extension trait to split DMA1 into channels
+pub trait DmaExt {
+    fn split(self) -> Channels;
+}
+
+/// The channels of DMA1
+pub struct Channels {
+    pub ch1: C1,
+    pub ch2: C2,
+    pub ch3: C3,
+    pub ch4: C4,
+    pub ch5: C5,
+    pub ch6: C6,
+    pub ch7: C7,
+}
+
+impl DmaExt for pac::DMA1 {
+    fn split(self) -> Channels {
+        // DMA1 has no reset bit, so only the clock is turned on
+        let rcc = unsafe { &*pac::RCC::PTR };
+        critical_section::with(|_| rcc.ahbpcenr.modify(|_, w| w.dma1en().set_bit()));
+
+        Channels {
+            ch1: Channel { _0: () },
+            ch2: Channel { _0: () },
+            ch3: Channel { _0: () },
+            ch4: Channel { _0: () },
+            ch5: Channel { _0: () },
+            ch6: Channel { _0: () },
+            ch7: Channel { _0: () },
+        }
+    }
+}
+
+/// Transfer errors
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// Both halves of a circular buffer were written before one was read
+    Overrun,
+}
+
+/// Which way data moves
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Direction {
+    PeripheralToMemory,
+    MemoryToPeripheral,
+}
+
+/// Channel events that can raise its interrupt, same bits as the flags
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    TransferComplete = 0b0010,
+    HalfTransfer = 0b0100,
+    TransferError = 0b1000,
+}
+
+/// Half of a circular buffer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Half {
+    First,
+    Second,
+}
+
+/// Data items DMA can move
+///
+/// # Safety
+/// `SIZE` has to match the size of the type, as PSIZE and MSIZE encode it
+pub unsafe trait Word {
+    const SIZE: u32;
+}
+
+unsafe impl Word for u8 {
+    const SIZE: u32 = 0b00;
+}
+
+unsafe impl Word for i8 {
+    const SIZE: u32 = 0b00;
+}
+
+unsafe impl Word for u16 {
+    const SIZE: u32 = 0b01;
+}
+
+unsafe impl Word for i16 {
+    const SIZE: u32 = 0b01;
+}
+
+unsafe impl Word for u32 {
+    const SIZE: u32 = 0b10;
+}
+
+unsafe impl Word for i32 {
+    const SIZE: u32 = 0b10;
+}
+
+/// Memory a transfer reads from
+///
+/// # Safety
+/// The returned memory has to stay valid and in place for as long as the
+/// buffer exists, even if it is leaked, and must not be written through
+/// anything else.
+pub unsafe trait ReadBuffer {
+    type Word: Word;
+
+    /// Address and length in words
+    fn read_buffer(&self) -> (*const Self::Word, usize);
+}
+
+/// Memory a transfer writes to
+///
+/// # Safety
+/// The returned memory has to stay valid and in place for as long as the
+/// buffer exists, even if it is leaked, and must not be accessed through
+/// anything else.
+pub unsafe trait WriteBuffer {
+    type Word: Word;
+
+    /// Address and length in words
+    fn write_buffer(&mut self) -> (*mut Self::Word, usize);
+}
+
+unsafe impl<W: Word> ReadBuffer for &'static [W] {
+    type Word = W;
+
+    fn read_buffer(&self) -> (*const W, usize) {
+        (self.as_ptr(), self.len())
+    }
+}
+
+unsafe impl<W: Word, const L: usize> ReadBuffer for &'static [W; L] {
+    type Word = W;
+
+    fn read_buffer(&self) -> (*const W, usize) {
+        (self.as_ptr(), L)
+    }
+}
+
+unsafe impl<W: Word> ReadBuffer for &'static mut [W] {
+    type Word = W;
+
+    fn read_buffer(&self) -> (*const W, usize) {
+        (self.as_ptr(), self.len())
+    }
+}
+
+unsafe impl<W: Word, const L: usize> ReadBuffer for &'static mut [W; L] {
+    type Word = W;
+
+    fn read_buffer(&self) -> (*const W, usize) {
+        (self.as_ptr(), L)
+    }
+}
+
+unsafe impl<W: Word> WriteBuffer for &'static mut [W] {
+    type Word = W;
+
+    fn write_buffer(&mut self) -> (*mut W, usize) {
+        (self.as_mut_ptr(), self.len())
+    }
+}
+
+unsafe impl<W: Word, const L: usize> WriteBuffer for &'static mut [W; L] {
+    type Word = W;
+
+    fn write_buffer(&mut self) -> (*mut W, usize) {
+        (self.as_mut_ptr(), L)
+    }
+}
+
+// Only pinned `'static` references, which always point at memory that stays
+// put. An owning pointer such as a `Vec` can be pinned too, but as the slice
+// is `Unpin` that does not stop its contents moving along with it.
+unsafe impl<W: Word> ReadBuffer for Pin<&'static mut [W]> {
+    type Word = W;
+
+    fn read_buffer(&self) -> (*const W, usize) {
+        (self.as_ptr(), self.len())
+    }
+}
+
+unsafe impl<W: Word, const L: usize> ReadBuffer for Pin<&'static mut [W; L]> {
+    type Word = W;
+
+    fn read_buffer(&self) -> (*const W, usize) {
+        (self.as_ptr(), L)
+    }
+}
+
+unsafe impl<W: Word> WriteBuffer for Pin<&'static mut [W]> {
+    type Word = W;
+
+    fn write_buffer(&mut self) -> (*mut W, usize) {
+        // Only the address is taken, the slice is not moved
+        let slice = unsafe { self.as_mut().get_unchecked_mut() };
+        (slice.as_mut_ptr(), slice.len())
+    }
+}
+
+unsafe impl<W: Word, const L: usize> WriteBuffer for Pin<&'static mut [W; L]> {
+    type Word = W;
+
+    fn write_buffer(&mut self) -> (*mut W, usize) {
+        let array = unsafe { self.as_mut().get_unchecked_mut() };
+        (array.as_mut_ptr(), L)
+    }
+}
+
+fn regs() -> &'static pac::dma::RegisterBlock {
+    unsafe { &*pac::DMA1::PTR }
+}
+
+/// A single DMA channel, numbered from 1
+pub struct Channel<const N: u8> {
+    _0: (),
+}
+
+pub type C1 = Channel<1>;
+pub type C2 = Channel<2>;
+pub type C3 = Channel<3>;
+pub type C4 = Channel<4>;
+pub type C5 = Channel<5>;
+pub type C6 = Channel<6>;
+pub type C7 = Channel<7>;
+
+impl<const N: u8> Channel<N> {
+    fn ch(&self) -> &'static CH {
+        &regs().ch[N as usize - 1]
+    }
+
+    /// Offset of this channel's flags in INTFR and INTFCR
+    const FLAGS: u32 = 4 * (N as u32 - 1);
+
+    /// Set up a transfer of `len` words, incrementing the memory address
+    ///
+    /// The channel has to be stopped.
+    pub(crate) fn configure<W: Word>(
+        &mut self,
+        peripheral: u32,
+        memory: *const W,
+        len: usize,
+        direction: Direction,
+    ) {
+        assert!(len <= u16::MAX as usize);
+
+        let ch = self.ch();
+        ch.paddr.write(|w| w.pa().bits(peripheral));
+        ch.maddr.write(|w| w.ma().bits(memory as u32));
+        ch.cntr.write(|w| w.ndt().bits(len as u32));
+        ch.cfgr.write(|w| {
+            w.dir()
+                .bit(direction == Direction::MemoryToPeripheral)
+                .minc()
+                .set_bit()
+                .psize()
+                .bits(W::SIZE)
+                .msize()
+                .bits(W::SIZE)
+        });
+    }
+
+    /// Keep the memory address on the same word, for padding and dummy reads
+    pub(crate) fn set_fixed_memory(&mut self) {
+        self.ch().cfgr.modify(|_, w| w.minc().clear_bit());
+    }
+
+    /// Restart from the beginning of the buffer when it is full
+    pub(crate) fn set_circular(&mut self) {
+        self.ch().cfgr.modify(|_, w| w.circ().set_bit());
+    }
+
+    pub(crate) fn start(&mut self) {
+        self.clear_flags();
+        // Memory accesses before this point have to reach the buffer first
+        compiler_fence(Ordering::SeqCst);
+        self.ch().cfgr.modify(|_, w| w.en().set_bit());
+    }
+
+    pub(crate) fn stop(&mut self) {
+        self.ch().cfgr.modify(|_, w| w.en().clear_bit());
+        compiler_fence(Ordering::SeqCst);
+    }
+
+    fn clear_flags(&mut self) {
+        regs()
+            .intfcr
+            .write(|w| unsafe { w.bits(0b1111 << Self::FLAGS) });
+    }
+
+    /// Copy `src` into `dst` without involving a peripheral
+    ///
+    /// As many words as fit in the shorter buffer are copied.
+    pub fn copy<SRC, DST>(mut self, src: SRC, mut dst: DST) -> Transfer<N, (SRC, DST), ()>
+    where
+        SRC: ReadBuffer,
+        DST: WriteBuffer<Word = SRC::Word>,
+    {
+        let (from, from_len) = src.read_buffer();
+        let (to, to_len) = dst.write_buffer();
+        // The source takes the place of the peripheral
+        self.configure(
+            from as u32,
+            to as *const SRC::Word,
+            from_len.min(to_len),
+            Direction::PeripheralToMemory,
+        );
+        self.ch()
+            .cfgr
+            .modify(|_, w| w.pinc().set_bit().mem2mem().set_bit());
+        Transfer::start(self, (src, dst), ())
+    }
+
+    /// Raise the channel interrupt on `event`
+    pub fn listen(&mut self, event: Event) {
+        self.ch()
+            .cfgr
+            .modify(|r, w| unsafe { w.bits(r.bits() | event as u32) });
+    }
+
+    pub fn unlisten(&mut self, event: Event) {
+        self.ch()
+            .cfgr
+            .modify(|r, w| unsafe { w.bits(r.bits() & !(event as u32)) });
+    }
+
+    /// Whether `event` happened, for use in the channel's handler
+    pub fn is_pending(event: Event) -> bool {
+        regs().intfr.read().bits() & (event as u32) << Self::FLAGS != 0
+    }
+
+    /// Clear the flag of `event`, for use in the channel's handler
+    pub fn unpend(event: Event) {
+        regs()
+            .intfcr
+            .write(|w| unsafe { w.bits((event as u32) << Self::FLAGS) });
+    }
+
+    /// Whether all data was moved
+    pub fn is_complete(&self) -> bool {
+        Self::is_pending(Event::TransferComplete)
+    }
+
+    /// Whether a bus error aborted the transfer
+    pub fn has_error(&self) -> bool {
+        Self::is_pending(Event::TransferError)
+    }
+
+    /// Number of data items left to move
+    pub fn remaining(&self) -> u16 {
+        self.ch().cntr.read().ndt().bits() as u16
+    }
+}
+
+/// A running transfer, owning the buffer, channel and peripheral until done
+pub struct Transfer<const N: u8, BUF, PAYLOAD> {
+    channel: Channel<N>,
+    buffer: BUF,
+    payload: PAYLOAD,
+}
+
+impl<const N: u8, BUF, PAYLOAD> Transfer<N, BUF, PAYLOAD> {
+    /// Start a transfer on a configured channel
+    pub(crate) fn start(mut channel: Channel<N>, buffer: BUF, payload: PAYLOAD) -> Self {
+        channel.start();
+        Self {
+            channel,
+            buffer,
+            payload,
+        }
+    }
+
+    /// Whether the transfer finished, successfully or not
+    pub fn is_done(&self) -> bool {
+        self.channel.is_complete() || self.channel.has_error()
+    }
+
+    /// Whether at least half of the data was moved
+    pub fn is_half_done(&self) -> bool {
+        Channel::<N>::is_pending(Event::HalfTransfer) || self.is_done()
+    }
+
+    /// Whether a bus error aborted the transfer
+    pub fn has_error(&self) -> bool {
+        self.channel.has_error()
+    }
+
+    /// Number of data items left to move
+    pub fn remaining(&self) -> u16 {
+        self.channel.remaining()
+    }
+
+    /// Raise the channel interrupt on `event`
+    pub fn listen(&mut self, event: Event) {
+        self.channel.listen(event);
+    }
+
+    pub fn unlisten(&mut self, event: Event) {
+        self.channel.unlisten(event);
+    }
+
+    /// Block until the transfer is done, giving back its parts
+    pub fn wait(self) -> (BUF, Channel<N>, PAYLOAD) {
+        while !self.is_done() {}
+        self.abort()
+    }
+
+    /// Stop the transfer where it is, giving back its parts
+    pub fn abort(mut self) -> (BUF, Channel<N>, PAYLOAD) {
+        self.channel.stop();
+        (self.buffer, self.channel, self.payload)
+    }
+}
+
+/// A circular transfer into a buffer of two halves
+///
+/// Nothing is readable until the channel filled the first half. From then
+/// on the halves take turns, the first one becoming readable at the half
+/// transfer event and the second at transfer complete:
+///
+/// ```ignore
+/// let mut rx = rx.read_circular(dma.ch5, unsafe { &mut *addr_of_mut!(BUF) });
+/// assert_eq!(rx.readable_half(), Err(nb::Error::WouldBlock));
+/// loop {
+///     // Half::First, Half::Second, Half::First, ...
+///     let (half, sum) = block!(rx.read(|data, half| (half, checksum(data))))?;
+/// }
+/// ```
+pub struct CircBuffer<const N: u8, B: 'static, PAYLOAD> {
+    channel: Channel<N>,
+    buffer: &'static mut [B; 2],
+    payload: PAYLOAD,
+    readable: Option<Half>,
+    /// The half last handed out by [`CircBuffer::read`]
+    consumed: Option<Half>,
+}
+
+impl<const N: u8, B, PAYLOAD> CircBuffer<N, B, PAYLOAD> {
+    /// Start a circular transfer on a configured channel
+    pub(crate) fn start(
+        mut channel: Channel<N>,
+        buffer: &'static mut [B; 2],
+        payload: PAYLOAD,
+    ) -> Self {
+        channel.set_circular();
+        channel.start();
+        Self {
+            channel,
+            buffer,
+            payload,
+            readable: None,
+            consumed: None,
+        }
+    }
+
+    /// The half that was completed last and is not being written
+    ///
+    /// Blocks until the first half has been filled once.
+    pub fn readable_half(&mut self) -> nb::Result<Half, Error> {
+        let first_done = Channel::<N>::is_pending(Event::HalfTransfer);
+        let second_done = Channel::<N>::is_pending(Event::TransferComplete);
+        if first_done && second_done {
+            return Err(nb::Error::Other(Error::Overrun));
+        }
+
+        match self.readable {
+            None | Some(Half::Second) if first_done => {
+                Channel::<N>::unpend(Event::HalfTransfer);
+                self.readable = Some(Half::First);
+            }
+            Some(Half::First) if second_done => {
+                Channel::<N>::unpend(Event::TransferComplete);
+                self.readable = Some(Half::Second);
+            }
+            _ => {}
+        }
+        self.readable.ok_or(nb::Error::WouldBlock)
+    }
+
+    /// Look at the readable half, which stays readable until the other one
+    /// is completed
+    ///
+    /// Fails if the half was overwritten before `f` returned.
+    pub fn peek<R>(&mut self, f: impl FnOnce(&B, Half) -> R) -> nb::Result<R, Error> {
+        let half = self.readable_half()?;
+        self.access(half, f)
+    }
+
+    /// Hand each completed half to `f` once
+    ///
+    /// Blocks until the half after the one last read is completed. Fails if
+    /// the half was overwritten before `f` returned.
+    pub fn read<R>(&mut self, f: impl FnOnce(&B, Half) -> R) -> nb::Result<R, Error> {
+        let half = self.readable_half()?;
+        if self.consumed == Some(half) {
+            return Err(nb::Error::WouldBlock);
+        }
+        let result = self.access(half, f)?;
+        self.consumed = Some(half);
+        Ok(result)
+    }
+
+    /// Pass `half` to `f`, checking it was not overwritten meanwhile
+    fn access<R>(&mut self, half: Half, f: impl FnOnce(&B, Half) -> R) -> nb::Result<R, Error> {
+        compiler_fence(Ordering::SeqCst);
+        let data = match half {
+            Half::First => &self.buffer[0],
+            Half::Second => &self.buffer[1],
+        };
+        let result = f(data, half);
+        compiler_fence(Ordering::SeqCst);
+        if self.readable_half()? == half {
+            Ok(result)
+        } else {
+            Err(nb::Error::Other(Error::Overrun))
+        }
+    }
+
+    /// Number of data items left before the end of the buffer
+    pub fn remaining(&self) -> u16 {
+        self.channel.remaining()
+    }
+
+    /// Raise the channel interrupt on `event`
+    pub fn listen(&mut self, event: Event) {
+        self.channel.listen(event);
+    }
+
+    pub fn unlisten(&mut self, event: Event) {
+        self.channel.unlisten(event);
+    }
+
+    /// Stop the transfer, giving back its parts
+    pub fn stop(mut self) -> (&'static mut [B; 2], Channel<N>, PAYLOAD) {
+        self.channel.stop();
+        (self.buffer, self.channel, self.payload)
+    }
+}
@@ -0,0 +1,171 @@
+//! External interrupts and events
+//!
+//! Lines 0 to 7 each follow the pin with the same number on one of the ports,
+//! selected through AFIO. They share the EXTI7_0 interrupt, so the handler
+//! checks which line is pending:
+//!
+//! ```ignore
+//! hello_wch::interrupt!(EXTI7_0, on_button);
+//!
+//! fn on_button() {
+//!     if Exti::is_pending(4) {
+//!         Exti::unpend(4);
+//!         // ...
+//!     }
+//! }
+//!
+//! let mut afio = p.AFIO.constrain();
+//! let mut exti = Exti::new(p.EXTI);
+//! let mut button = gpioc.pc4.into_pull_up_input();
+//! button.make_interrupt_source(&mut afio);
+//! button.trigger_on_edge(&mut exti, Edge::Falling);
+//! button.enable_interrupt(&mut exti);
+//! unsafe { Pfic::unmask(Interrupt::EXTI7_0) };
+//! ```
+//!
+//! In event mode a line only wakes the core from `wfe`, without running a
+//! handler or setting its pending flag.
+
+use crate::afio::Afio;
+use crate::gpio::{Input, Pin};
+use crate::pac;
+
+/// Line of the programmable voltage detector
+pub const PVD: u8 = 8;
+/// Line of the auto-wakeup timer
+pub const AWU: u8 = 9;
+/// Number of lines, every function taking a line panics on higher ones
+pub const LINES: u8 = 10;
+
+/// Bit of `line` in the EXTI registers
+fn mask(line: u8) -> u32 {
+    assert!(line < LINES, "EXTI line {line} does not exist");
+    1 << line
+}
+
+/// Edges that trigger a line
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edge {
+    Rising,
+    Falling,
+    Both,
+}
+
+/// The EXTI controller
+pub struct Exti {
+    exti: pac::EXTI,
+}
+
+impl Exti {
+    pub fn new(exti: pac::EXTI) -> Self {
+        Self { exti }
+    }
+
+    pub fn release(self) -> pac::EXTI {
+        self.exti
+    }
+
+    /// Trigger `line` on `edge`
+    pub fn set_edge(&mut self, line: u8, edge: Edge) {
+        let mask = mask(line);
+        let rising = matches!(edge, Edge::Rising | Edge::Both);
+        let falling = matches!(edge, Edge::Falling | Edge::Both);
+        self.exti
+            .rtenr
+            .modify(|r, w| unsafe { w.bits(r.bits() & !mask | (rising as u32) << line) });
+        self.exti
+            .ftenr
+            .modify(|r, w| unsafe { w.bits(r.bits() & !mask | (falling as u32) << line) });
+    }
+
+    /// Flag `line` as pending and raise its interrupt when it triggers
+    pub fn enable_interrupt(&mut self, line: u8) {
+        self.exti
+            .intenr
+            .modify(|r, w| unsafe { w.bits(r.bits() | mask(line)) });
+    }
+
+    pub fn disable_interrupt(&mut self, line: u8) {
+        self.exti
+            .intenr
+            .modify(|r, w| unsafe { w.bits(r.bits() & !mask(line)) });
+    }
+
+    /// Generate a wakeup event on `line`
+    pub fn enable_event(&mut self, line: u8) {
+        self.exti
+            .evenr
+            .modify(|r, w| unsafe { w.bits(r.bits() | mask(line)) });
+    }
+
+    pub fn disable_event(&mut self, line: u8) {
+        self.exti
+            .evenr
+            .modify(|r, w| unsafe { w.bits(r.bits() & !mask(line)) });
+    }
+
+    /// Trigger `line` from software, as if its edge had been detected
+    pub fn generate(&mut self, line: u8) {
+        self.exti.swievr.write(|w| unsafe { w.bits(mask(line)) });
+    }
+
+    /// Whether `line` has been triggered since its flag was last cleared
+    pub fn is_pending(line: u8) -> bool {
+        let exti = unsafe { &*pac::EXTI::PTR };
+        exti.intfr.read().bits() & mask(line) != 0
+    }
+
+    /// Clear the pending flag of `line`
+    pub fn unpend(line: u8) {
+        let exti = unsafe { &*pac::EXTI::PTR };
+        exti.intfr.write(|w| unsafe { w.bits(mask(line)) });
+    }
+}
+
+/// Pins that can trigger their EXTI line
+pub trait ExtiPin {
+    /// Connect the pin to the EXTI line with its number, replacing the pin
+    /// with the same number on another port
+    fn make_interrupt_source(&mut self, afio: &mut Afio);
+    fn trigger_on_edge(&mut self, exti: &mut Exti, edge: Edge);
+    fn enable_interrupt(&mut self, exti: &mut Exti);
+    fn disable_interrupt(&mut self, exti: &mut Exti);
+    fn enable_event(&mut self, exti: &mut Exti);
+    fn disable_event(&mut self, exti: &mut Exti);
+    fn clear_interrupt_pending_bit(&mut self);
+    fn check_interrupt(&self) -> bool;
+}
+
+impl<const P: char, const N: u8, MODE> ExtiPin for Pin<P, N, Input<MODE>> {
+    fn make_interrupt_source(&mut self, afio: &mut Afio) {
+        afio.set_exti_port(N, P);
+    }
+
+    fn trigger_on_edge(&mut self, exti: &mut Exti, edge: Edge) {
+        exti.set_edge(N, edge);
+    }
+
+    fn enable_interrupt(&mut self, exti: &mut Exti) {
+        exti.enable_interrupt(N);
+    }
+
+    fn disable_interrupt(&mut self, exti: &mut Exti) {
+        exti.disable_interrupt(N);
+    }
+
+    fn enable_event(&mut self, exti: &mut Exti) {
+        exti.enable_event(N);
+    }
+
+    fn disable_event(&mut self, exti: &mut Exti) {
+        exti.disable_event(N);
+    }
+
+    fn clear_interrupt_pending_bit(&mut self) {
+        Exti::unpend(N);
+    }
+
+    fn check_interrupt(&self) -> bool {
+        Exti::is_pending(N)
+    }
+}
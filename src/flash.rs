@@ -0,0 +1,309 @@
+//! Flash programming
+//!
+//! The flash is erased in 64-byte pages and programmed a half-word or a
+//! whole page at a time, after unlocking the controller. Only the `STORAGE`
+//! region at the end of flash can be changed, kept apart from the program in
+//! `FLASH` by `memory.x`. Offsets count from its start:
+//!
+//! ```ignore
+//! const SETTINGS: u32 = 0;
+//!
+//! let mut flash = Flash::new(p.FLASH);
+//! flash.unlock();
+//! flash.erase(SETTINGS, SETTINGS + 64)?;
+//! flash.write(SETTINGS, &calibration.to_le_bytes())?;
+//! flash.lock();
+//! ```
+//!
+//! The core stalls on flash reads while an operation runs, so waiting for
+//! one is done from RAM with interrupts masked.
+
+use embedded_storage::nor_flash::{
+    self, ErrorType, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash,
+};
+
+use crate::chip;
+use crate::pac::FLASH;
+
+/// Size of a page, the unit of erasing and fast programming
+pub const PAGE_SIZE: usize = 64;
+
+/// Address the flash is programmed through, it is also mapped at 0
+const FLASH_BASE: u32 = 0x0800_0000;
+
+/// Address of the `STORAGE` region
+const STORAGE_BASE: u32 = FLASH_BASE + chip::STORAGE_START;
+
+/// Keys written in order to unlock the controller, and fast mode
+const KEY1: u32 = 0x4567_0123;
+const KEY2: u32 = 0xCDEF_89AB;
+
+/// Busy bit of STATR
+const BSY: u32 = 1 << 0;
+
+/// Bits of CTLR, as raw values for the functions running from RAM
+const STRT: u32 = 1 << 6;
+const PAGE_ER: u32 = 1 << 17;
+const BUFLOAD: u32 = 1 << 18;
+const BUFRST: u32 = 1 << 19;
+
+/// Flash errors
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The offset or length is not a multiple of the write or erase size
+    NotAligned,
+    /// The range is not inside the `STORAGE` region
+    OutOfBounds,
+    /// The controller has not been unlocked
+    Locked,
+    /// The page is write protected by the option bytes
+    WriteProtected,
+    /// The flash does not read back what was written, usually because it
+    /// was not erased first
+    Verify,
+}
+
+impl NorFlashError for Error {
+    fn kind(&self) -> NorFlashErrorKind {
+        match self {
+            Error::NotAligned => NorFlashErrorKind::NotAligned,
+            Error::OutOfBounds => NorFlashErrorKind::OutOfBounds,
+            _ => NorFlashErrorKind::Other,
+        }
+    }
+}
+
+impl From<NorFlashErrorKind> for Error {
+    fn from(kind: NorFlashErrorKind) -> Self {
+        match kind {
+            NorFlashErrorKind::NotAligned => Error::NotAligned,
+            _ => Error::OutOfBounds,
+        }
+    }
+}
+
+/// Set `trigger` in CTLR and wait until the operation is done
+///
+/// Placed in RAM, as nothing can be fetched from flash while it is busy.
+#[inline(never)]
+#[link_section = ".data.hello_wch.flash_start"]
+unsafe fn start_and_wait(ctlr: *mut u32, statr: *const u32, trigger: u32) {
+    ctlr.write_volatile(ctlr.read_volatile() | trigger);
+    while statr.read_volatile() & BSY != 0 {}
+}
+
+/// Write `value` to flash at `target`, starting a programming operation, and
+/// wait until it is done
+#[inline(never)]
+#[link_section = ".data.hello_wch.flash_write"]
+unsafe fn write_and_wait(target: *mut u16, value: u16, statr: *const u32) {
+    target.write_volatile(value);
+    while statr.read_volatile() & BSY != 0 {}
+}
+
+/// The flash controller
+pub struct Flash {
+    flash: FLASH,
+}
+
+impl Flash {
+    pub fn new(flash: FLASH) -> Self {
+        Self { flash }
+    }
+
+    pub fn release(mut self) -> FLASH {
+        self.lock();
+        self.flash
+    }
+
+    /// Allow erasing and programming, including the fast page operations
+    pub fn unlock(&mut self) {
+        if self.flash.ctlr.read().lock().bit_is_set() {
+            self.flash.keyr.write(|w| w.keyr().bits(KEY1));
+            self.flash.keyr.write(|w| w.keyr().bits(KEY2));
+        }
+        if self.flash.ctlr.read().flock().bit_is_set() {
+            self.flash.modekeyr.write(|w| w.modekeyr().bits(KEY1));
+            self.flash.modekeyr.write(|w| w.modekeyr().bits(KEY2));
+        }
+    }
+
+    /// Prevent erasing and programming until unlocked again
+    pub fn lock(&mut self) {
+        self.flash
+            .ctlr
+            .modify(|_, w| w.flock().set_bit().lock().set_bit());
+    }
+
+    pub fn is_locked(&self) -> bool {
+        let ctlr = self.flash.ctlr.read();
+        ctlr.lock().bit_is_set() || ctlr.flock().bit_is_set()
+    }
+
+    /// Wait for a running operation and make sure a new one can start
+    fn prepare(&self) -> Result<(), Error> {
+        if self.is_locked() {
+            return Err(Error::Locked);
+        }
+        while self.flash.statr.read().bsy().bit_is_set() {}
+        Ok(())
+    }
+
+    /// Clear the flags of the finished operation, failing if it was refused
+    fn finish(&self) -> Result<(), Error> {
+        let statr = self.flash.statr.read();
+        // Flags are cleared by writing 1
+        self.flash
+            .statr
+            .write(|w| w.eop().set_bit().wrprterr().set_bit());
+        if statr.wrprterr().bit_is_set() {
+            Err(Error::WriteProtected)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Run an operation selected by `mode` in CTLR, started by `trigger`
+    fn operate(&mut self, mode: u32, trigger: u32) {
+        let ctlr = self.flash.ctlr.as_ptr();
+        let statr = self.flash.statr.as_ptr();
+        critical_section::with(|_| {
+            self.flash
+                .ctlr
+                .modify(|r, w| unsafe { w.bits(r.bits() | mode) });
+            unsafe { start_and_wait(ctlr, statr, trigger) };
+            self.flash
+                .ctlr
+                .modify(|r, w| unsafe { w.bits(r.bits() & !mode) });
+        });
+    }
+
+    /// Erase the 64-byte page at `offset` to all ones
+    pub fn erase_page(&mut self, offset: u32) -> Result<(), Error> {
+        check(offset, PAGE_SIZE, PAGE_SIZE)?;
+        self.prepare()?;
+
+        self.flash
+            .addr
+            .write(|w| w.far().bits(STORAGE_BASE + offset));
+        self.operate(PAGE_ER, STRT);
+        self.finish()
+    }
+
+    /// Program the half-word at `offset`, which has to be erased
+    pub fn program_half_word(&mut self, offset: u32, value: u16) -> Result<(), Error> {
+        check(offset, 2, 2)?;
+        self.prepare()?;
+
+        let target = (STORAGE_BASE + offset) as *mut u16;
+        let statr = self.flash.statr.as_ptr();
+        critical_section::with(|_| {
+            self.flash.ctlr.modify(|_, w| w.pg().set_bit());
+            unsafe { write_and_wait(target, value, statr) };
+            self.flash.ctlr.modify(|_, w| w.pg().clear_bit());
+        });
+        self.finish()?;
+
+        if unsafe { target.read_volatile() } != value {
+            return Err(Error::Verify);
+        }
+        Ok(())
+    }
+
+    /// Program the whole 64-byte page at `offset`, which has to be erased
+    pub fn program_page(&mut self, offset: u32, data: &[u8; PAGE_SIZE]) -> Result<(), Error> {
+        check(offset, PAGE_SIZE, PAGE_SIZE)?;
+        self.prepare()?;
+
+        let page = (STORAGE_BASE + offset) as *mut u32;
+        let ctlr = self.flash.ctlr.as_ptr();
+        let statr = self.flash.statr.as_ptr();
+        critical_section::with(|_| {
+            self.flash.ctlr.modify(|_, w| w.page_pg().set_bit());
+            // Empty the page buffer, then fill it a word at a time
+            unsafe { start_and_wait(ctlr, statr, BUFRST) };
+            for (i, word) in data.chunks_exact(4).enumerate() {
+                let word = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+                unsafe {
+                    page.add(i).write_volatile(word);
+                    start_and_wait(ctlr, statr, BUFLOAD);
+                }
+            }
+            self.flash
+                .addr
+                .write(|w| w.far().bits(STORAGE_BASE + offset));
+            unsafe { start_and_wait(ctlr, statr, STRT) };
+            self.flash.ctlr.modify(|_, w| w.page_pg().clear_bit());
+        });
+        self.finish()?;
+
+        let written = unsafe { core::slice::from_raw_parts(page as *const u8, PAGE_SIZE) };
+        if written != data {
+            return Err(Error::Verify);
+        }
+        Ok(())
+    }
+}
+
+/// Check that `len` bytes at `offset` are inside the storage and aligned
+fn check(offset: u32, len: usize, align: usize) -> Result<(), Error> {
+    if !(offset as usize).is_multiple_of(align) || !len.is_multiple_of(align) {
+        Err(Error::NotAligned)
+    } else if offset as usize + len > chip::STORAGE_SIZE as usize {
+        Err(Error::OutOfBounds)
+    } else {
+        Ok(())
+    }
+}
+
+impl ErrorType for Flash {
+    type Error = Error;
+}
+
+impl ReadNorFlash for Flash {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Error> {
+        nor_flash::check_read(self, offset, bytes.len())?;
+        let start = (STORAGE_BASE + offset) as *const u8;
+        let flash = unsafe { core::slice::from_raw_parts(start, bytes.len()) };
+        bytes.copy_from_slice(flash);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        chip::STORAGE_SIZE as usize
+    }
+}
+
+impl NorFlash for Flash {
+    const WRITE_SIZE: usize = 2;
+    const ERASE_SIZE: usize = PAGE_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Error> {
+        nor_flash::check_erase(self, from, to)?;
+        for offset in (from..to).step_by(PAGE_SIZE) {
+            self.erase_page(offset)?;
+        }
+        Ok(())
+    }
+
+    /// Whole aligned pages are programmed at once, the rest a half-word at a
+    /// time
+    fn write(&mut self, mut offset: u32, mut bytes: &[u8]) -> Result<(), Error> {
+        nor_flash::check_write(self, offset, bytes.len())?;
+        while !bytes.is_empty() {
+            if let (0, Some(page)) = (offset as usize % PAGE_SIZE, bytes.first_chunk()) {
+                self.program_page(offset, page)?;
+                offset += PAGE_SIZE as u32;
+                bytes = &bytes[PAGE_SIZE..];
+            } else {
+                self.program_half_word(offset, u16::from_le_bytes([bytes[0], bytes[1]]))?;
+                offset += 2;
+                bytes = &bytes[2..];
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,527 @@
+//! General purpose I/O
+//!
+//! Each pin is its own type, with its configuration tracked in the type:
+//!
+//! ```ignore
+//! let gpioc = p.GPIOC.split();
+//! let mut led = gpioc.pc1.into_push_pull_output();
+//! led.set_high();
+//! ```
+//!
+//! Configuring a pin only touches its own 4 bits of `CFGLR`, and the output
+//! is driven through the atomic `BSHR`/`BCR` registers.
+//!
+//! Pins that are not bonded out in the package of the selected chip are
+//! missing from the port's `Parts`, so using one fails to compile. PD1 starts
+//! out as the debug pin, see [`Afio::disable_swd`](crate::afio::Afio::disable_swd).
+
+use core::convert::Infallible;
+use core::marker::PhantomData;
+
+use embedded_hal::digital::{ErrorType, InputPin, OutputPin, StatefulOutputPin};
+
+use crate::pac::{self, gpio::RegisterBlock};
+
+/// Input mode (type state)
+pub struct Input<MODE> {
+    _mode: PhantomData<MODE>,
+}
+
+/// Floating input (type state)
+pub struct Floating;
+/// Pulled up input (type state)
+pub struct PullUp;
+/// Pulled down input (type state)
+pub struct PullDown;
+
+/// Output mode (type state)
+pub struct Output<MODE> {
+    _mode: PhantomData<MODE>,
+}
+
+/// Push-pull output (type state)
+pub struct PushPull;
+/// Open-drain output (type state)
+pub struct OpenDrain;
+
+/// Driven by a peripheral (type state)
+pub struct Alternate<MODE> {
+    _mode: PhantomData<MODE>,
+}
+
+/// Analog input (type state)
+pub struct Analog;
+
+/// Taken over by the serial wire debug interface (type state)
+///
+/// See [`Afio::disable_swd`](crate::afio::Afio::disable_swd).
+pub struct Debugger;
+
+/// Modes in which a pin can be reconfigured, everything but [`Debugger`]
+pub trait Active {}
+
+impl<MODE> Active for Input<MODE> {}
+impl<MODE> Active for Output<MODE> {}
+impl<MODE> Active for Alternate<MODE> {}
+impl Active for Analog {}
+
+/// Maximum output switching speed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Speed {
+    Mhz2 = 0b10,
+    Mhz10 = 0b01,
+    Mhz30 = 0b11,
+}
+
+/// Extension trait to split a GPIO peripheral into individual pins
+pub trait GpioExt {
+    type Parts;
+
+    /// Enable and reset the port, then split it into pins
+    fn split(self) -> Self::Parts;
+}
+
+/// A single pin `P{N}`, for example `Pin<'C', 1, MODE>` is PC1
+pub struct Pin<const P: char, const N: u8, MODE> {
+    _mode: PhantomData<MODE>,
+}
+
+const fn port(p: char) -> &'static RegisterBlock {
+    unsafe {
+        match p {
+            'A' => &*pac::GPIOA::PTR,
+            'C' => &*pac::GPIOC::PTR,
+            _ => &*pac::GPIOD::PTR,
+        }
+    }
+}
+
+impl<const P: char, const N: u8, MODE> Pin<P, N, MODE> {
+    const fn new() -> Self {
+        Self { _mode: PhantomData }
+    }
+}
+
+impl<const P: char, const N: u8> Pin<P, N, Debugger> {
+    /// The pin once released by the debugger, as configured after reset
+    pub(crate) fn into_active(self) -> Pin<P, N, Input<Floating>> {
+        Pin::new()
+    }
+}
+
+impl<const P: char, const N: u8, MODE: Active> Pin<P, N, MODE> {
+    /// Rewrite the 4 configuration bits of this pin
+    fn configure(&mut self, cfg: u32) {
+        critical_section::with(|_| {
+            port(P)
+                .cfglr
+                .modify(|r, w| unsafe { w.bits(r.bits() & !(0b1111 << (N * 4)) | cfg << (N * 4)) });
+        });
+    }
+
+    fn into_mode<M>(mut self, cfg: u32) -> Pin<P, N, M> {
+        self.configure(cfg);
+        Pin::new()
+    }
+
+    fn set_odr(&mut self, high: bool) {
+        if high {
+            port(P).bshr.write(|w| unsafe { w.bits(1 << N) });
+        } else {
+            port(P).bcr.write(|w| unsafe { w.bits(1 << N) });
+        }
+    }
+
+    fn input_high(&self) -> bool {
+        port(P).indr.read().bits() & (1 << N) != 0
+    }
+
+    fn output_high(&self) -> bool {
+        port(P).outdr.read().bits() & (1 << N) != 0
+    }
+
+    pub fn into_floating_input(self) -> Pin<P, N, Input<Floating>> {
+        self.into_mode(0b0100)
+    }
+
+    pub fn into_pull_up_input(mut self) -> Pin<P, N, Input<PullUp>> {
+        self.set_odr(true);
+        self.into_mode(0b1000)
+    }
+
+    pub fn into_pull_down_input(mut self) -> Pin<P, N, Input<PullDown>> {
+        self.set_odr(false);
+        self.into_mode(0b1000)
+    }
+
+    pub fn into_analog(self) -> Pin<P, N, Analog> {
+        self.into_mode(0b0000)
+    }
+
+    /// Push-pull output at 10 MHz, initially low
+    pub fn into_push_pull_output(self) -> Pin<P, N, Output<PushPull>> {
+        self.into_push_pull_output_with_speed(Speed::Mhz10)
+    }
+
+    /// Push-pull output, initially low
+    pub fn into_push_pull_output_with_speed(mut self, speed: Speed) -> Pin<P, N, Output<PushPull>> {
+        self.set_odr(false);
+        self.into_mode(speed as u32)
+    }
+
+    /// Open-drain output at 10 MHz, initially released
+    pub fn into_open_drain_output(self) -> Pin<P, N, Output<OpenDrain>> {
+        self.into_open_drain_output_with_speed(Speed::Mhz10)
+    }
+
+    /// Open-drain output, initially released
+    pub fn into_open_drain_output_with_speed(
+        mut self,
+        speed: Speed,
+    ) -> Pin<P, N, Output<OpenDrain>> {
+        self.set_odr(true);
+        self.into_mode(0b0100 | speed as u32)
+    }
+
+    /// Push-pull peripheral output at 30 MHz
+    pub fn into_alternate_push_pull(self) -> Pin<P, N, Alternate<PushPull>> {
+        self.into_mode(0b1000 | Speed::Mhz30 as u32)
+    }
+
+    /// Open-drain peripheral output at 30 MHz
+    pub fn into_alternate_open_drain(self) -> Pin<P, N, Alternate<OpenDrain>> {
+        self.into_mode(0b1100 | Speed::Mhz30 as u32)
+    }
+
+    /// Use the pin as an open-drain output within `f`, then restore its
+    /// configuration
+    pub fn with_open_drain_output<T>(
+        &mut self,
+        f: impl FnOnce(&mut Pin<P, N, Output<OpenDrain>>) -> T,
+    ) -> T {
+        let cfg = port(P).cfglr.read().bits() >> (N * 4) & 0b1111;
+        let odr = self.output_high();
+
+        let mut pin = Pin::<P, N, Output<OpenDrain>>::new();
+        pin.set_odr(true);
+        pin.configure(0b0100 | Speed::Mhz10 as u32);
+        let result = f(&mut pin);
+
+        self.set_odr(odr);
+        self.configure(cfg);
+        result
+    }
+
+    /// Reset the configuration before handing the pin to the debugger
+    pub(crate) fn into_debugger(self) -> Pin<P, N, Debugger> {
+        self.into_mode(0b0100)
+    }
+
+    /// Forget the pin number and port at the type level
+    pub fn erase(self) -> ErasedPin<MODE> {
+        ErasedPin {
+            port: P,
+            pin: N,
+            _mode: PhantomData,
+        }
+    }
+}
+
+impl<const P: char, const N: u8, MODE> Pin<P, N, Output<MODE>> {
+    /// Change the output switching speed
+    pub fn set_speed(&mut self, speed: Speed) {
+        critical_section::with(|_| {
+            port(P).cfglr.modify(|r, w| unsafe {
+                w.bits(r.bits() & !(0b11 << (N * 4)) | (speed as u32) << (N * 4))
+            });
+        });
+    }
+
+    pub fn set_high(&mut self) {
+        self.set_odr(true)
+    }
+
+    pub fn set_low(&mut self) {
+        self.set_odr(false)
+    }
+
+    pub fn toggle(&mut self) {
+        self.set_odr(!self.output_high())
+    }
+
+    /// Whether the pin is being driven high
+    pub fn is_set_high(&self) -> bool {
+        self.output_high()
+    }
+
+    /// Whether the pin is being driven low
+    pub fn is_set_low(&self) -> bool {
+        !self.output_high()
+    }
+}
+
+impl<const P: char, const N: u8, MODE> Pin<P, N, Input<MODE>> {
+    pub fn is_high(&self) -> bool {
+        self.input_high()
+    }
+
+    pub fn is_low(&self) -> bool {
+        !self.input_high()
+    }
+}
+
+impl<const P: char, const N: u8> Pin<P, N, Output<OpenDrain>> {
+    /// Level of the line, which may be pulled low by another device
+    pub fn is_high(&self) -> bool {
+        self.input_high()
+    }
+
+    pub fn is_low(&self) -> bool {
+        !self.input_high()
+    }
+}
+
+/// A pin whose port and number are only known at runtime
+pub struct ErasedPin<MODE> {
+    port: char,
+    pin: u8,
+    _mode: PhantomData<MODE>,
+}
+
+impl<MODE> ErasedPin<MODE> {
+    fn set_odr(&mut self, high: bool) {
+        if high {
+            port(self.port)
+                .bshr
+                .write(|w| unsafe { w.bits(1 << self.pin) });
+        } else {
+            port(self.port)
+                .bcr
+                .write(|w| unsafe { w.bits(1 << self.pin) });
+        }
+    }
+
+    fn input_high(&self) -> bool {
+        port(self.port).indr.read().bits() & (1 << self.pin) != 0
+    }
+
+    fn output_high(&self) -> bool {
+        port(self.port).outdr.read().bits() & (1 << self.pin) != 0
+    }
+}
+
+impl ErasedPin<Output<PushPull>> {
+    /// Take over a pin as a 10 MHz push-pull output, enabling its port
+    ///
+    /// # Safety
+    /// The previous owner of the pin must never use it again
+    #[cfg(feature = "panic-blink")]
+    pub(crate) unsafe fn steal(p: char, pin: u8) -> Self {
+        use crate::rcc::Enable;
+
+        match p {
+            'A' => pac::GPIOA::enable(),
+            'C' => pac::GPIOC::enable(),
+            _ => pac::GPIOD::enable(),
+        }
+        critical_section::with(|_| {
+            port(p).cfglr.modify(|r, w| {
+                w.bits(r.bits() & !(0b1111 << (pin * 4)) | (Speed::Mhz10 as u32) << (pin * 4))
+            });
+        });
+
+        Self {
+            port: p,
+            pin,
+            _mode: PhantomData,
+        }
+    }
+}
+
+impl<MODE> ErasedPin<Output<MODE>> {
+    pub fn set_high(&mut self) {
+        self.set_odr(true)
+    }
+
+    pub fn set_low(&mut self) {
+        self.set_odr(false)
+    }
+
+    pub fn toggle(&mut self) {
+        self.set_odr(!self.output_high())
+    }
+
+    pub fn is_set_high(&self) -> bool {
+        self.output_high()
+    }
+
+    pub fn is_set_low(&self) -> bool {
+        !self.output_high()
+    }
+}
+
+impl<MODE> ErasedPin<Input<MODE>> {
+    pub fn is_high(&self) -> bool {
+        self.input_high()
+    }
+
+    pub fn is_low(&self) -> bool {
+        !self.input_high()
+    }
+}
+
+impl<const P: char, const N: u8, MODE> ErrorType for Pin<P, N, MODE> {
+    type Error = Infallible;
+}
+
+impl<const P: char, const N: u8, MODE> OutputPin for Pin<P, N, Output<MODE>> {
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        Pin::set_high(self);
+        Ok(())
+    }
+
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        Pin::set_low(self);
+        Ok(())
+    }
+}
+
+impl<const P: char, const N: u8, MODE> StatefulOutputPin for Pin<P, N, Output<MODE>> {
+    fn is_set_high(&mut self) -> Result<bool, Infallible> {
+        Ok(Pin::is_set_high(self))
+    }
+
+    fn is_set_low(&mut self) -> Result<bool, Infallible> {
+        Ok(Pin::is_set_low(self))
+    }
+
+    fn toggle(&mut self) -> Result<(), Infallible> {
+        Pin::toggle(self);
+        Ok(())
+    }
+}
+
+impl<const P: char, const N: u8, MODE> InputPin for Pin<P, N, Input<MODE>> {
+    fn is_high(&mut self) -> Result<bool, Infallible> {
+        Ok(self.input_high())
+    }
+
+    fn is_low(&mut self) -> Result<bool, Infallible> {
+        Ok(!self.input_high())
+    }
+}
+
+impl<const P: char, const N: u8> InputPin for Pin<P, N, Output<OpenDrain>> {
+    fn is_high(&mut self) -> Result<bool, Infallible> {
+        Ok(self.input_high())
+    }
+
+    fn is_low(&mut self) -> Result<bool, Infallible> {
+        Ok(!self.input_high())
+    }
+}
+
+impl<MODE> ErrorType for ErasedPin<MODE> {
+    type Error = Infallible;
+}
+
+impl<MODE> OutputPin for ErasedPin<Output<MODE>> {
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        ErasedPin::set_high(self);
+        Ok(())
+    }
+
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        ErasedPin::set_low(self);
+        Ok(())
+    }
+}
+
+impl<MODE> StatefulOutputPin for ErasedPin<Output<MODE>> {
+    fn is_set_high(&mut self) -> Result<bool, Infallible> {
+        Ok(ErasedPin::is_set_high(self))
+    }
+
+    fn is_set_low(&mut self) -> Result<bool, Infallible> {
+        Ok(ErasedPin::is_set_low(self))
+    }
+
+    fn toggle(&mut self) -> Result<(), Infallible> {
+        ErasedPin::toggle(self);
+        Ok(())
+    }
+}
+
+impl<MODE> InputPin for ErasedPin<Input<MODE>> {
+    fn is_high(&mut self) -> Result<bool, Infallible> {
+        Ok(ErasedPin::is_high(self))
+    }
+
+    fn is_low(&mut self) -> Result<bool, Infallible> {
+        Ok(ErasedPin::is_low(self))
+    }
+}
+
+macro_rules! gpio {
+    ($GPIOX:ident, $port:literal, $gpiox:ident, [$($PXi:ident: ($pxi:ident, $i:literal, $name:literal, $MODE:ty),)+]) => {
+        pub mod $gpiox {
+            use super::{Floating, GpioExt, Input, Pin};
+            use crate::pac;
+            use crate::rcc::Enable;
+
+            /// Pins of the port in the package, all floating inputs after reset
+            pub struct Parts {
+                $(
+                    #[cfg(pin = $name)]
+                    pub $pxi: $PXi<$MODE>,
+                )+
+            }
+
+            impl GpioExt for pac::$GPIOX {
+                type Parts = Parts;
+
+                fn split(self) -> Parts {
+                    pac::$GPIOX::enable_and_reset();
+
+                    Parts {
+                        $(
+                            #[cfg(pin = $name)]
+                            $pxi: Pin::new(),
+                        )+
+                    }
+                }
+            }
+
+            $(pub type $PXi<MODE = Input<Floating>> = Pin<$port, $i, MODE>;)+
+        }
+
+        pub use $gpiox::{$($PXi,)+};
+    };
+}
+
+gpio!(GPIOA, 'A', gpioa, [
+    PA1: (pa1, 1, "pa1", Input<Floating>),
+    PA2: (pa2, 2, "pa2", Input<Floating>),
+]);
+
+gpio!(GPIOC, 'C', gpioc, [
+    PC0: (pc0, 0, "pc0", Input<Floating>),
+    PC1: (pc1, 1, "pc1", Input<Floating>),
+    PC2: (pc2, 2, "pc2", Input<Floating>),
+    PC3: (pc3, 3, "pc3", Input<Floating>),
+    PC4: (pc4, 4, "pc4", Input<Floating>),
+    PC5: (pc5, 5, "pc5", Input<Floating>),
+    PC6: (pc6, 6, "pc6", Input<Floating>),
+    PC7: (pc7, 7, "pc7", Input<Floating>),
+]);
+
+gpio!(GPIOD, 'D', gpiod, [
+    PD0: (pd0, 0, "pd0", Input<Floating>),
+    PD1: (pd1, 1, "pd1", super::Debugger),
+    PD2: (pd2, 2, "pd2", Input<Floating>),
+    PD3: (pd3, 3, "pd3", Input<Floating>),
+    PD4: (pd4, 4, "pd4", Input<Floating>),
+    PD5: (pd5, 5, "pd5", Input<Floating>),
+    PD6: (pd6, 6, "pd6", Input<Floating>),
+    PD7: (pd7, 7, "pd7", Input<Floating>),
+]);
@@ -0,0 +1,434 @@
+//! I2C1 master and slave
+//!
+//! Uses SCL and SDA of any of the [`i2c1`] pin sets. The master implements
+//! the embedded-hal traits for both 7 and 10-bit addresses, including
+//! `write_read` with a repeated start:
+//!
+//! ```ignore
+//! let scl = gpioc.pc2.into_alternate_open_drain();
+//! let sda = gpioc.pc1.into_alternate_open_drain();
+//! let mut i2c = I2c::new(p.I2C1, (scl, sda), Mode::FAST, &clocks);
+//! let mut id = [0; 1];
+//! if let Err(Error::Timeout) = i2c.write_read(0x68, &[0x75], &mut id) {
+//!     i2c.recover_bus(&mut delay)?;
+//! }
+//! ```
+//!
+//! An empty write only sends the address, probing whether a device answers:
+//!
+//! ```ignore
+//! let present = (0x08..0x78).filter(|&address| i2c.write(address, &[]).is_ok());
+//! ```
+//!
+//! A device that was reset in the middle of a read can keep SDA low forever,
+//! which [`I2c::recover_bus`] resolves by clocking it out by hand. For
+//! answering another master, see [`slave`].
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::{
+    self, ErrorKind, ErrorType, NoAcknowledgeSource, Operation, SevenBitAddress, TenBitAddress,
+};
+
+use crate::afio::{self, i2c1, Remap};
+use crate::gpio::{Alternate, OpenDrain, Pin};
+use crate::pac::{self, i2c::STAR1, R};
+use crate::rcc::{Clocks, Enable};
+
+pub mod slave;
+
+pub use slave::{Handler, I2cSlave, OwnAddress};
+
+/// Pins that can be used for I2C1, as `(SCL, SDA)` of the same [`i2c1`] pin
+/// set `R`
+pub trait Pins<R> {}
+
+impl<R, SCL: i2c1::Scl<R>, SDA: i2c1::Sda<R>> Pins<R> for (SCL, SDA) {}
+
+/// Bus speed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// Up to 100 kHz
+    Standard { frequency: u32 },
+    /// Up to 400 kHz
+    Fast { frequency: u32 },
+}
+
+impl Mode {
+    /// Standard mode at 100 kHz
+    pub const STANDARD: Mode = Mode::Standard { frequency: 100_000 };
+    /// Fast mode at 400 kHz
+    pub const FAST: Mode = Mode::Fast { frequency: 400_000 };
+}
+
+/// I2C errors
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// Misplaced start or stop condition
+    Bus,
+    /// Another master took over the bus
+    ArbitrationLoss,
+    /// The device did not acknowledge
+    NoAcknowledge(NoAcknowledgeSource),
+    /// Received data was not read in time
+    Overrun,
+    /// The bus stopped moving, for example a device holding SCL or SDA low
+    Timeout,
+}
+
+impl i2c::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match *self {
+            Error::Bus => ErrorKind::Bus,
+            Error::ArbitrationLoss => ErrorKind::ArbitrationLoss,
+            Error::NoAcknowledge(source) => ErrorKind::NoAcknowledge(source),
+            Error::Overrun => ErrorKind::Overrun,
+            Error::Timeout => ErrorKind::Other,
+        }
+    }
+}
+
+/// Set the bus timing from PCLK, the peripheral has to be disabled
+fn set_timing(i2c: &pac::i2c::RegisterBlock, mode: Mode, pclk: u32) {
+    i2c.ctlr2.write(|w| w.freq().bits(pclk / 1_000_000));
+    match mode {
+        Mode::Standard { frequency } => {
+            // Equal high and low times
+            let ccr = (pclk / (frequency.min(100_000) * 2)).max(4);
+            i2c.ckcfgr.write(|w| w.ccr().bits(ccr));
+        }
+        Mode::Fast { frequency } => {
+            // Low time twice the high time
+            let ccr = (pclk / (frequency.min(400_000) * 3)).max(1);
+            i2c.ckcfgr.write(|w| w.ccr().bits(ccr).fs().set_bit());
+        }
+    }
+}
+
+/// Core clock cycles a single poll of a status flag takes at least
+const POLL_CYCLES: u32 = 10;
+
+/// How long a flag is waited on by default, the SMBus clock low limit
+const DEFAULT_TIMEOUT_US: u32 = 25_000;
+
+#[derive(Clone, Copy)]
+enum Address {
+    Seven(u8),
+    Ten(u16),
+}
+
+/// I2C1 in master mode
+pub struct I2c<PINS> {
+    i2c: pac::I2C1,
+    pins: PINS,
+    /// Status polls per microsecond
+    polls_per_us: u32,
+    /// Status polls before giving up on a flag
+    timeout: u32,
+}
+
+impl<PINS> I2c<PINS> {
+    /// Set up I2C1 on the pin set the pins belong to
+    pub fn new<R: Remap>(i2c: pac::I2C1, pins: PINS, mode: Mode, clocks: &Clocks) -> Self
+    where
+        PINS: Pins<R>,
+    {
+        pac::I2C1::enable_and_reset();
+        afio::remap::<R>();
+
+        set_timing(&i2c, mode, clocks.pclk());
+        i2c.ctlr1.write(|w| w.pe().set_bit());
+
+        let polls_per_us = (clocks.hclk() / 1_000_000 / POLL_CYCLES).max(1);
+        Self {
+            i2c,
+            pins,
+            polls_per_us,
+            timeout: DEFAULT_TIMEOUT_US * polls_per_us,
+        }
+    }
+
+    /// Give back the peripheral and pins
+    pub fn release(self) -> (pac::I2C1, PINS) {
+        self.i2c.ctlr1.reset();
+        (self.i2c, self.pins)
+    }
+
+    /// Fail with [`Error::Timeout`] when the bus does not move for at least
+    /// `us` microseconds, 25 ms by default
+    pub fn set_timeout(&mut self, us: u32) {
+        self.timeout = us.saturating_mul(self.polls_per_us);
+    }
+
+    /// Check for errors, releasing the bus if one occurred
+    fn check_errors(&self, source: NoAcknowledgeSource) -> Result<(), Error> {
+        let star1 = self.i2c.star1.read();
+        let error = if star1.af().bit_is_set() {
+            Error::NoAcknowledge(source)
+        } else if star1.arlo().bit_is_set() {
+            // The peripheral already dropped to slave mode
+            self.i2c.star1.write(|w| w.arlo().clear_bit());
+            return Err(Error::ArbitrationLoss);
+        } else if star1.berr().bit_is_set() {
+            Error::Bus
+        } else if star1.ovr().bit_is_set() {
+            Error::Overrun
+        } else {
+            return Ok(());
+        };
+
+        // Error flags are cleared by writing 0
+        self.i2c.star1.write(|w| unsafe { w.bits(0) });
+        self.i2c.ctlr1.modify(|_, w| w.stop().set_bit());
+        Err(error)
+    }
+
+    /// Wait until `done` holds for STAR1, checking for errors meanwhile
+    fn wait(
+        &self,
+        source: NoAcknowledgeSource,
+        done: impl Fn(&R<STAR1>) -> bool,
+    ) -> Result<(), Error> {
+        for _ in 0..self.timeout {
+            if done(&self.i2c.star1.read()) {
+                return Ok(());
+            }
+            self.check_errors(source)?;
+        }
+        self.i2c.ctlr1.modify(|_, w| w.stop().set_bit());
+        Err(Error::Timeout)
+    }
+
+    /// Send a start condition
+    fn send_start(&mut self) -> Result<(), Error> {
+        self.i2c
+            .ctlr1
+            .modify(|_, w| w.start().set_bit().ack().set_bit());
+        self.wait(NoAcknowledgeSource::Unknown, |r| r.sb().bit_is_set())
+    }
+
+    /// Send a start condition and the address, leaving ADDR set
+    fn start(&mut self, address: Address, read: bool) -> Result<(), Error> {
+        self.send_start()?;
+        match address {
+            Address::Seven(address) => {
+                self.i2c
+                    .datar
+                    .write(|w| w.dr().bits((address as u32) << 1 | read as u32));
+            }
+            Address::Ten(address) => {
+                // 0b11110 followed by the top two bits, then the low byte
+                let header = 0xF0 | (address as u32 >> 7 & 0b110);
+                self.i2c.datar.write(|w| w.dr().bits(header));
+                self.wait(NoAcknowledgeSource::Address, |r| r.add10().bit_is_set())?;
+                self.i2c.datar.write(|w| w.dr().bits(address as u32 & 0xFF));
+
+                if read {
+                    // Reads repeat the header alone after a restart
+                    self.wait(NoAcknowledgeSource::Address, |r| r.addr().bit_is_set())?;
+                    self.clear_addr();
+                    self.send_start()?;
+                    self.i2c.datar.write(|w| w.dr().bits(header | 1));
+                }
+            }
+        }
+        self.wait(NoAcknowledgeSource::Address, |r| r.addr().bit_is_set())
+    }
+
+    /// Clear ADDR by reading STAR1 followed by STAR2
+    fn clear_addr(&self) {
+        self.i2c.star1.read();
+        self.i2c.star2.read();
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        // BTF only sets after a data byte, an empty write is done once the
+        // address was acknowledged
+        if bytes.is_empty() {
+            return Ok(());
+        }
+        for &byte in bytes {
+            self.wait(NoAcknowledgeSource::Data, |r| r.txe().bit_is_set())?;
+            self.i2c.datar.write(|w| w.dr().bits(byte as u32));
+        }
+
+        self.wait(NoAcknowledgeSource::Data, |r| r.btf().bit_is_set())
+    }
+
+    /// Receive bytes, not acknowledging the final one if `nack` is set and
+    /// ending the transfer with a stop condition if `stop` is set
+    fn read_bytes(
+        &mut self,
+        buf: &mut [u8],
+        addressed: bool,
+        nack: bool,
+        stop: bool,
+    ) -> Result<(), Error> {
+        let len = buf.len();
+        if len == 0 {
+            if nack {
+                self.i2c.ctlr1.modify(|_, w| w.ack().clear_bit());
+            }
+            if addressed {
+                self.clear_addr();
+            }
+            if stop {
+                self.i2c.ctlr1.modify(|_, w| w.stop().set_bit());
+            }
+            return Ok(());
+        }
+
+        for (i, byte) in buf.iter_mut().enumerate() {
+            let final_byte = i == len - 1;
+            // ACK and STOP apply to the byte currently being received, so
+            // they have to be set before waiting for it
+            if final_byte && nack {
+                self.i2c.ctlr1.modify(|_, w| w.ack().clear_bit());
+            }
+            if addressed && i == 0 {
+                self.clear_addr();
+            }
+            if final_byte && stop {
+                self.i2c.ctlr1.modify(|_, w| w.stop().set_bit());
+            }
+
+            self.wait(NoAcknowledgeSource::Data, |r| r.rxne().bit_is_set())?;
+            *byte = self.i2c.datar.read().bits() as u8;
+        }
+        Ok(())
+    }
+
+    fn wait_stop(&self) -> Result<(), Error> {
+        for _ in 0..self.timeout {
+            if self.i2c.ctlr1.read().stop().bit_is_clear() {
+                return Ok(());
+            }
+        }
+        Err(Error::Timeout)
+    }
+
+    fn run(&mut self, address: Address, operations: &mut [Operation<'_>]) -> Result<(), Error> {
+        let count = operations.len();
+        // Direction of the previous operation, adjacent operations in the
+        // same direction share one start condition
+        let mut previous = None;
+
+        for i in 0..count {
+            let next_is_read = matches!(operations.get(i + 1), Some(Operation::Read(_)));
+            let last = i == count - 1;
+
+            match &mut operations[i] {
+                Operation::Write(bytes) => {
+                    let addressed = previous != Some(false);
+                    if addressed {
+                        self.start(address, false)?;
+                        self.clear_addr();
+                    }
+                    self.write_bytes(bytes)?;
+                    if last {
+                        self.i2c.ctlr1.modify(|_, w| w.stop().set_bit());
+                    }
+                    previous = Some(false);
+                }
+                Operation::Read(buf) => {
+                    let addressed = previous != Some(true);
+                    if addressed {
+                        self.start(address, true)?;
+                    }
+                    // A NACK tells the device the read is over, so only the
+                    // end of a run of reads gets one
+                    self.read_bytes(buf, addressed, !next_is_read, last)?;
+                    previous = Some(true);
+                }
+            }
+        }
+
+        self.wait_stop()
+    }
+}
+
+impl<const CP: char, const CN: u8, const DP: char, const DN: u8>
+    I2c<(
+        Pin<CP, CN, Alternate<OpenDrain>>,
+        Pin<DP, DN, Alternate<OpenDrain>>,
+    )>
+{
+    /// Free a bus held low by a device stuck in the middle of a transfer
+    ///
+    /// SCL is toggled by hand until the device lets go of SDA, up to the 9
+    /// clocks of a byte and its acknowledge, followed by a stop condition.
+    /// I2C1 is reset afterwards, keeping its timing. Fails with
+    /// [`Error::Timeout`] if SCL stays low, or [`Error::Bus`] if SDA does.
+    pub fn recover_bus(&mut self, delay: &mut impl DelayNs) -> Result<(), Error> {
+        let i2c = &self.i2c;
+        let timeout = self.timeout;
+        let (scl, sda) = &mut self.pins;
+
+        i2c.ctlr1.modify(|_, w| w.pe().clear_bit());
+        let result = scl.with_open_drain_output(|scl| {
+            sda.with_open_drain_output(|sda| {
+                // Devices may stretch the clock even here
+                let release_scl = |scl: &mut Pin<CP, CN, _>| {
+                    scl.set_high();
+                    (0..timeout).any(|_| scl.is_high())
+                };
+
+                for _ in 0..9 {
+                    if sda.is_high() {
+                        break;
+                    }
+                    scl.set_low();
+                    delay.delay_us(5);
+                    if !release_scl(scl) {
+                        return Err(Error::Timeout);
+                    }
+                    delay.delay_us(5);
+                }
+
+                // Stop condition, SDA rising while SCL is high
+                scl.set_low();
+                sda.set_low();
+                delay.delay_us(5);
+                if !release_scl(scl) {
+                    return Err(Error::Timeout);
+                }
+                delay.delay_us(5);
+                sda.set_high();
+                delay.delay_us(5);
+
+                if sda.is_high() {
+                    Ok(())
+                } else {
+                    Err(Error::Bus)
+                }
+            })
+        });
+
+        // The peripheral may still think the bus is busy
+        let ctlr2 = i2c.ctlr2.read().bits();
+        let ckcfgr = i2c.ckcfgr.read().bits();
+        i2c.ctlr1.write(|w| w.swrst().set_bit());
+        i2c.ctlr1.reset();
+        i2c.ctlr2.write(|w| unsafe { w.bits(ctlr2) });
+        i2c.ckcfgr.write(|w| unsafe { w.bits(ckcfgr) });
+        i2c.ctlr1.write(|w| w.pe().set_bit());
+
+        result
+    }
+}
+
+impl<PINS> ErrorType for I2c<PINS> {
+    type Error = Error;
+}
+
+impl<PINS> i2c::I2c<SevenBitAddress> for I2c<PINS> {
+    fn transaction(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<(), Error> {
+        self.run(Address::Seven(address), operations)
+    }
+}
+
+impl<PINS> i2c::I2c<TenBitAddress> for I2c<PINS> {
+    fn transaction(&mut self, address: u16, operations: &mut [Operation<'_>]) -> Result<(), Error> {
+        self.run(Address::Ten(address), operations)
+    }
+}
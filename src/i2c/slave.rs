@@ -0,0 +1,218 @@
+//! I2C1 in slave mode
+//!
+//! The slave answers on its own address, and optionally a second 7-bit one
+//! and the general call address. Bus events are passed to a [`Handler`] by
+//! [`I2cSlave::handle`], called from the I2C1 event and error interrupts or
+//! in a polling loop:
+//!
+//! ```ignore
+//! struct Registers { index: usize, data: [u8; 4] }
+//!
+//! impl Handler for Registers {
+//!     fn received(&mut self, byte: u8) {
+//!         self.data[self.index % 4] = byte;
+//!         self.index += 1;
+//!     }
+//!
+//!     fn transmit(&mut self) -> u8 {
+//!         self.index += 1;
+//!         self.data[(self.index - 1) % 4]
+//!     }
+//! }
+//!
+//! let mut slave = I2cSlave::new(p.I2C1, (scl, sda), OwnAddress::Seven(0x42), &clocks);
+//! loop {
+//!     slave.handle(&mut registers).ok();
+//! }
+//! ```
+
+use super::{Error, Pins};
+use crate::afio::{self, Remap};
+use crate::pac;
+use crate::rcc::{Clocks, Enable};
+
+/// Address the slave answers on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OwnAddress {
+    Seven(u8),
+    Ten(u16),
+}
+
+/// Which address the master used
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Matched {
+    /// The [`OwnAddress`]
+    Primary,
+    /// The one given to [`I2cSlave::set_secondary_address`]
+    Secondary,
+    /// Address 0, if enabled
+    GeneralCall,
+}
+
+/// Which way data moves, as seen by the slave
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// The master writes
+    Receive,
+    /// The master reads
+    Transmit,
+}
+
+/// Callbacks for the events of the bus
+pub trait Handler {
+    /// The master addressed the slave at the start of a transfer
+    fn addressed(&mut self, _matched: Matched, _direction: Direction) {}
+
+    /// The master wrote a byte
+    fn received(&mut self, byte: u8);
+
+    /// The master reads a byte
+    fn transmit(&mut self) -> u8;
+
+    /// The master ended the transfer, with a stop condition after writing or
+    /// by not acknowledging a byte it read
+    fn stopped(&mut self) {}
+}
+
+/// I2C1 in slave mode
+pub struct I2cSlave<PINS> {
+    i2c: pac::I2C1,
+    pins: PINS,
+}
+
+impl<PINS> I2cSlave<PINS> {
+    /// Set up I2C1 to answer on `address` on the pin set the pins belong to
+    pub fn new<R: Remap>(i2c: pac::I2C1, pins: PINS, address: OwnAddress, clocks: &Clocks) -> Self
+    where
+        PINS: Pins<R>,
+    {
+        pac::I2C1::enable_and_reset();
+        afio::remap::<R>();
+
+        // The data setup time is counted in PCLK cycles even as a slave
+        i2c.ctlr2
+            .write(|w| w.freq().bits(clocks.pclk() / 1_000_000));
+        i2c.oaddr1.write(|w| match address {
+            OwnAddress::Seven(address) => w.add7_1().bits(address as u32),
+            OwnAddress::Ten(address) => w
+                .add0()
+                .bit(address & 1 != 0)
+                .add7_1()
+                .bits(address as u32 >> 1)
+                .add9_8()
+                .bits(address as u32 >> 8)
+                .addmode()
+                .set_bit(),
+        });
+        i2c.ctlr1.write(|w| w.pe().set_bit());
+        // ACK is cleared while the peripheral is disabled
+        i2c.ctlr1.modify(|_, w| w.ack().set_bit());
+
+        Self { i2c, pins }
+    }
+
+    /// Give back the peripheral and pins
+    pub fn release(self) -> (pac::I2C1, PINS) {
+        self.i2c.ctlr2.reset();
+        self.i2c.ctlr1.reset();
+        (self.i2c, self.pins)
+    }
+
+    /// Also answer on a second 7-bit address, or stop doing so
+    pub fn set_secondary_address(&mut self, address: Option<u8>) {
+        self.i2c.oaddr2.write(|w| {
+            w.endual()
+                .bit(address.is_some())
+                .add2()
+                .bits(address.unwrap_or(0) as u32)
+        });
+    }
+
+    /// Answer on the general call address 0
+    pub fn set_general_call(&mut self, enable: bool) {
+        self.i2c.ctlr1.modify(|_, w| w.engc().bit(enable));
+    }
+
+    /// Raise the I2C1 event and error interrupts for bus events
+    pub fn listen(&mut self) {
+        self.i2c.ctlr2.modify(|_, w| {
+            w.itevten()
+                .set_bit()
+                .itbufen()
+                .set_bit()
+                .iterren()
+                .set_bit()
+        });
+    }
+
+    pub fn unlisten(&mut self) {
+        self.i2c.ctlr2.modify(|_, w| {
+            w.itevten()
+                .clear_bit()
+                .itbufen()
+                .clear_bit()
+                .iterren()
+                .clear_bit()
+        });
+    }
+
+    /// Pass pending bus events to `handler`
+    ///
+    /// Fails on bus errors and when a received byte was overwritten before
+    /// it was handled, the transfer continues after either.
+    pub fn handle(&mut self, handler: &mut impl Handler) -> Result<(), Error> {
+        let star1 = self.i2c.star1.read();
+
+        if star1.berr().bit_is_set() {
+            self.i2c.star1.write(|w| unsafe { w.bits(!(1 << 8)) });
+            return Err(Error::Bus);
+        }
+        if star1.ovr().bit_is_set() {
+            self.i2c.star1.write(|w| unsafe { w.bits(!(1 << 11)) });
+            return Err(Error::Overrun);
+        }
+
+        if star1.addr().bit_is_set() {
+            // Reading STAR2 after STAR1 clears ADDR
+            let star2 = self.i2c.star2.read();
+            let matched = if star2.gencall().bit_is_set() {
+                Matched::GeneralCall
+            } else if star2.dualf().bit_is_set() {
+                Matched::Secondary
+            } else {
+                Matched::Primary
+            };
+            let direction = if star2.tra().bit_is_set() {
+                Direction::Transmit
+            } else {
+                Direction::Receive
+            };
+            handler.addressed(matched, direction);
+        }
+
+        if star1.af().bit_is_set() {
+            // The master does not want more bytes
+            self.i2c.star1.write(|w| unsafe { w.bits(!(1 << 10)) });
+            handler.stopped();
+            return Ok(());
+        }
+
+        if star1.rxne().bit_is_set() {
+            handler.received(self.i2c.datar.read().bits() as u8);
+        }
+
+        // TXE is only set once ADDR is cleared
+        let txe = self.i2c.star1.read().txe().bit_is_set();
+        if txe && self.i2c.star2.read().tra().bit_is_set() {
+            let byte = handler.transmit();
+            self.i2c.datar.write(|w| w.dr().bits(byte as u32));
+        }
+
+        if star1.stopf().bit_is_set() {
+            // Cleared by reading STAR1 followed by writing CTLR1
+            self.i2c.ctlr1.modify(|_, w| w);
+            handler.stopped();
+        }
+        Ok(())
+    }
+}
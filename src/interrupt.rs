@@ -0,0 +1,88 @@
+//! Global interrupt enable and critical sections
+//!
+//! The target has no atomic instructions, so state shared with interrupt
+//! handlers is protected by masking interrupts instead. This module provides
+//! the [`critical_section`] implementation for the chip, which also backs the
+//! atomics re-exported in [`atomic`]:
+//!
+//! ```ignore
+//! use core::cell::RefCell;
+//! use critical_section::Mutex;
+//! use hello_wch::interrupt::atomic::{AtomicU32, Ordering};
+//!
+//! static TICKS: AtomicU32 = AtomicU32::new(0);
+//! static LED: Mutex<RefCell<Option<PC1<Output<PushPull>>>>> = Mutex::new(RefCell::new(None));
+//!
+//! fn on_tick() {
+//!     TICKS.fetch_add(1, Ordering::Relaxed);
+//!     critical_section::with(|cs| {
+//!         if let Some(led) = LED.borrow_ref_mut(cs).as_mut() {
+//!             led.toggle();
+//!         }
+//!     });
+//! }
+//! ```
+//!
+//! The QingKe GINTENR CSR mirrors `mstatus.MIE`, so either works. `mstatus` is
+//! used here as it can be read and cleared with a single instruction.
+
+use core::arch::asm;
+
+/// Atomic types built on critical sections
+pub use portable_atomic as atomic;
+
+/// `mstatus.MIE`, the global machine interrupt enable
+const MIE: usize = 1 << 3;
+
+/// Let interrupts fire
+///
+/// # Safety
+/// Must not be called from inside a critical section
+#[inline]
+pub unsafe fn enable() {
+    asm!("csrsi mstatus, {0}", const MIE);
+}
+
+/// Prevent all interrupts from firing
+#[inline]
+pub fn disable() {
+    unsafe { asm!("csrci mstatus, {0}", const MIE) };
+}
+
+/// Whether interrupts are globally enabled
+#[inline]
+pub fn is_enabled() -> bool {
+    let mstatus: usize;
+    unsafe { asm!("csrr {0}, mstatus", out(reg) mstatus) };
+    mstatus & MIE != 0
+}
+
+/// Run `f` with interrupts masked
+#[inline]
+pub fn free<F, R>(f: F) -> R
+where
+    F: FnOnce(critical_section::CriticalSection) -> R,
+{
+    critical_section::with(f)
+}
+
+struct SingleHart;
+
+critical_section::set_impl!(SingleHart);
+
+unsafe impl critical_section::Impl for SingleHart {
+    unsafe fn acquire() -> bool {
+        // Read and clear MIE in one go, so an interrupt can not slip in
+        // between the two
+        let mstatus: usize;
+        asm!("csrrci {0}, mstatus, {1}", out(reg) mstatus, const MIE);
+        mstatus & MIE != 0
+    }
+
+    unsafe fn release(was_enabled: bool) {
+        // Nested sections leave interrupts masked until the outermost one ends
+        if was_enabled {
+            enable();
+        }
+    }
+}
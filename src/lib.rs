@@ -0,0 +1,24 @@
+#![no_std]
+
+pub mod adc;
+pub mod afio;
+pub mod chip;
+pub mod delay;
+pub mod dma;
+pub mod exti;
+pub mod flash;
+pub mod gpio;
+pub mod i2c;
+pub mod interrupt;
+pub mod pac;
+#[cfg(any(feature = "panic-blink", feature = "panic-reset"))]
+pub mod panic;
+pub mod pfic;
+pub mod pwr;
+pub mod rcc;
+pub mod serial;
+pub mod spi;
+pub mod systick;
+pub mod time;
+pub mod timer;
+pub mod watchdog;
@@ -1,29 +1,45 @@
 #![no_std]
 #![no_main]
 
-use panic_halt as _;
+use core::fmt::Write;
+
+use embedded_hal::delay::DelayNs;
+use hello_wch::{
+    gpio::GpioExt,
+    pac,
+    rcc::{self, RccExt},
+    serial::{self, Serial},
+    systick::Systick,
+};
 use riscv_rt::entry;
 
 #[entry]
 fn main() -> ! {
-    let RCC_APB2PCENR: *mut u32 = 0x4002_1018 as _;
-    let GPIOC_CFGLR: *mut u32 = 0x4001_1000 as _;
-    let GPIOC_OUTDR: *mut u32 = 0x4001_100C as _;
-
-    unsafe {
-        // Enable clocks to the GPIOC bank
-        RCC_APB2PCENR.write_volatile(0b10000);
-        // Set pin 1 to output
-        GPIOC_CFGLR.write_volatile(0b0001_0000);
-
-        loop {
-            // Set pin 1 to high
-            GPIOC_OUTDR.write_volatile(0b1_0);
-            riscv::asm::delay(1_000_000);
-
-            // Set pin 1 to low
-            GPIOC_OUTDR.write_volatile(0b0_0);
-            riscv::asm::delay(1_000_000);
-        }
+    let p = pac::Peripherals::take().unwrap();
+
+    // Run the core at the full 24 MHz of the internal oscillator
+    let clocks = p.RCC.freeze(rcc::Config::hsi()).unwrap();
+    let mut systick = Systick::new(p.SYSTICK, &clocks);
+
+    // Enable clocks to the GPIOC bank and set pin 1 to output
+    let gpioc = p.GPIOC.split();
+    let mut led = gpioc.pc1.into_push_pull_output();
+
+    // Log over USART1 on PD5 (TX) and PD6 (RX)
+    let gpiod = p.GPIOD.split();
+    let tx = gpiod.pd5.into_alternate_push_pull();
+    let rx = gpiod.pd6.into_floating_input();
+    let mut serial = Serial::new(p.USART1, (tx, rx), serial::Config::default(), &clocks);
+
+    let mut count = 0u32;
+    loop {
+        writeln!(serial, "blink {}", count).ok();
+        count = count.wrapping_add(1);
+
+        led.set_high();
+        systick.delay_ms(500);
+
+        led.set_low();
+        systick.delay_ms(500);
     }
 }
@@ -0,0 +1,425 @@
+//! Typed register access for the CH32V003
+//!
+//! Every peripheral is a `#[repr(C)]` block of [`Reg`]s laid out exactly like
+//! the reference manual. Registers are accessed through `read`, `write` and
+//! `modify` closures, with named fields instead of hand-counted bit positions:
+//!
+//! ```ignore
+//! let p = pac::Peripherals::take().unwrap();
+//! p.RCC.apb2pcenr.modify(|_, w| w.iopcen().set_bit());
+//! ```
+
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::ops::Deref;
+
+pub mod adc;
+pub mod afio;
+pub mod dma;
+pub mod exti;
+pub mod flash;
+pub mod gpio;
+pub mod i2c;
+pub mod iwdg;
+pub mod pfic;
+pub mod pwr;
+pub mod rcc;
+pub mod spi;
+pub mod systick;
+pub mod tim1;
+pub mod tim2;
+pub mod usart;
+pub mod wwdg;
+
+/// Interrupt and exception numbers, as used by the PFIC and vector table
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Interrupt {
+    /// Non-maskable interrupt
+    NMI = 2,
+    /// Exceptions
+    HardFault = 3,
+    /// System timer
+    SysTick = 12,
+    /// Software interrupt
+    Software = 14,
+    /// Window watchdog
+    WWDG = 16,
+    /// Supply voltage detector, through EXTI line 8
+    PVD = 17,
+    /// Flash global
+    FLASH = 18,
+    /// RCC global
+    RCC = 19,
+    /// EXTI lines 0 to 7
+    EXTI7_0 = 20,
+    /// Auto-wakeup, through EXTI line 9
+    AWU = 21,
+    DMA1_CHANNEL1 = 22,
+    DMA1_CHANNEL2 = 23,
+    DMA1_CHANNEL3 = 24,
+    DMA1_CHANNEL4 = 25,
+    DMA1_CHANNEL5 = 26,
+    DMA1_CHANNEL6 = 27,
+    DMA1_CHANNEL7 = 28,
+    /// ADC global
+    ADC = 29,
+    /// I2C1 event
+    I2C1_EV = 30,
+    /// I2C1 error
+    I2C1_ER = 31,
+    /// USART1 global
+    USART1 = 32,
+    /// SPI1 global
+    SPI1 = 33,
+    /// TIM1 break
+    TIM1_BRK = 34,
+    /// TIM1 update
+    TIM1_UP = 35,
+    /// TIM1 trigger and commutation
+    TIM1_TRG_COM = 36,
+    /// TIM1 capture/compare
+    TIM1_CC = 37,
+    /// TIM2 global
+    TIM2 = 38,
+}
+
+impl Interrupt {
+    /// Number of vector table entries
+    pub const COUNT: usize = 39;
+
+    #[inline(always)]
+    pub fn number(self) -> usize {
+        self as usize
+    }
+}
+
+/// Describes a single 32-bit register
+pub trait RegisterSpec {
+    /// Value of the register after a system reset
+    const RESET: u32;
+}
+
+/// A memory mapped register
+#[repr(transparent)]
+pub struct Reg<REG> {
+    value: UnsafeCell<u32>,
+    _reg: PhantomData<REG>,
+}
+
+impl<REG: RegisterSpec> Reg<REG> {
+    /// Read the current value of the register
+    #[inline(always)]
+    pub fn read(&self) -> R<REG> {
+        R {
+            bits: unsafe { self.value.get().read_volatile() },
+            _reg: PhantomData,
+        }
+    }
+
+    /// Write the register, starting from its reset value
+    #[inline(always)]
+    pub fn write<F>(&self, f: F)
+    where
+        F: FnOnce(&mut W<REG>) -> &mut W<REG>,
+    {
+        let mut w = W {
+            bits: REG::RESET,
+            _reg: PhantomData,
+        };
+        f(&mut w);
+        unsafe { self.value.get().write_volatile(w.bits) }
+    }
+
+    /// Read, modify and write back the register
+    #[inline(always)]
+    pub fn modify<F>(&self, f: F)
+    where
+        for<'w> F: FnOnce(&R<REG>, &'w mut W<REG>) -> &'w mut W<REG>,
+    {
+        let r = self.read();
+        let mut w = W {
+            bits: r.bits,
+            _reg: PhantomData,
+        };
+        f(&r, &mut w);
+        unsafe { self.value.get().write_volatile(w.bits) }
+    }
+
+    /// Write the reset value to the register
+    #[inline(always)]
+    pub fn reset(&self) {
+        unsafe { self.value.get().write_volatile(REG::RESET) }
+    }
+
+    /// Address of the register, for handing to DMA
+    #[inline(always)]
+    pub fn as_ptr(&self) -> *mut u32 {
+        self.value.get()
+    }
+}
+
+/// Value read from a register
+pub struct R<REG> {
+    bits: u32,
+    _reg: PhantomData<REG>,
+}
+
+impl<REG> R<REG> {
+    /// Raw value of the register
+    #[inline(always)]
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+}
+
+/// Value to be written to a register
+pub struct W<REG> {
+    bits: u32,
+    _reg: PhantomData<REG>,
+}
+
+impl<REG> W<REG> {
+    /// Overwrite the whole register value
+    ///
+    /// # Safety
+    /// Reserved bits and field encodings are not checked
+    #[inline(always)]
+    pub unsafe fn bits(&mut self, bits: u32) -> &mut Self {
+        self.bits = bits;
+        self
+    }
+}
+
+/// Value of a `WIDTH` bit field starting at bit `OFFSET`
+pub struct FieldReader<const OFFSET: u8, const WIDTH: u8> {
+    bits: u32,
+}
+
+impl<const OFFSET: u8, const WIDTH: u8> FieldReader<OFFSET, WIDTH> {
+    const MASK: u32 = u32::MAX >> (32 - WIDTH);
+
+    #[inline(always)]
+    fn new(register: u32) -> Self {
+        Self {
+            bits: (register >> OFFSET) & Self::MASK,
+        }
+    }
+
+    /// Value of the field
+    #[inline(always)]
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    /// Value of a single bit field
+    #[inline(always)]
+    pub fn bit(&self) -> bool {
+        self.bits != 0
+    }
+
+    #[inline(always)]
+    pub fn bit_is_set(&self) -> bool {
+        self.bit()
+    }
+
+    #[inline(always)]
+    pub fn bit_is_clear(&self) -> bool {
+        !self.bit()
+    }
+}
+
+/// Writer for a `WIDTH` bit field starting at bit `OFFSET`
+pub struct FieldWriter<'a, REG, const OFFSET: u8, const WIDTH: u8> {
+    w: &'a mut W<REG>,
+}
+
+impl<'a, REG, const OFFSET: u8, const WIDTH: u8> FieldWriter<'a, REG, OFFSET, WIDTH> {
+    const MASK: u32 = u32::MAX >> (32 - WIDTH);
+
+    #[inline(always)]
+    fn new(w: &'a mut W<REG>) -> Self {
+        Self { w }
+    }
+
+    /// Set the field, any bits that do not fit are discarded
+    #[inline(always)]
+    pub fn bits(self, value: u32) -> &'a mut W<REG> {
+        self.w.bits &= !(Self::MASK << OFFSET);
+        self.w.bits |= (value & Self::MASK) << OFFSET;
+        self.w
+    }
+
+    #[inline(always)]
+    pub fn bit(self, value: bool) -> &'a mut W<REG> {
+        self.bits(value as u32)
+    }
+
+    #[inline(always)]
+    pub fn set_bit(self) -> &'a mut W<REG> {
+        self.bit(true)
+    }
+
+    #[inline(always)]
+    pub fn clear_bit(self) -> &'a mut W<REG> {
+        self.bit(false)
+    }
+}
+
+/// Declares a register type together with its named fields
+///
+/// Fields are written as `name @ offset` for single bits, or
+/// `name @ offset: width` for wider fields.
+macro_rules! register {
+    (
+        $(#[$attr:meta])*
+        $name:ident = $reset:literal {
+            $(
+                $(#[$fattr:meta])*
+                $field:ident @ $offset:literal $(: $width:literal)?
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        #[allow(non_camel_case_types)]
+        pub struct $name;
+
+        impl $crate::pac::RegisterSpec for $name {
+            const RESET: u32 = $reset;
+        }
+
+        #[allow(dead_code)]
+        impl $crate::pac::R<$name> {
+            $(
+                $(#[$fattr])*
+                #[inline(always)]
+                pub fn $field(&self) -> $crate::pac::FieldReader<$offset, { register!(@width $($width)?) }> {
+                    $crate::pac::FieldReader::new(self.bits)
+                }
+            )*
+        }
+
+        #[allow(dead_code)]
+        impl $crate::pac::W<$name> {
+            $(
+                $(#[$fattr])*
+                #[inline(always)]
+                pub fn $field(&mut self) -> $crate::pac::FieldWriter<'_, $name, $offset, { register!(@width $($width)?) }> {
+                    $crate::pac::FieldWriter::new(self)
+                }
+            )*
+        }
+    };
+    (@width) => { 1 };
+    (@width $width:literal) => { $width };
+}
+pub(crate) use register;
+
+/// Declares a peripheral singleton pointing at a register block
+macro_rules! peripherals {
+    ($(
+        $(#[$attr:meta])*
+        $name:ident: $module:ident @ $address:literal,
+    )*) => {
+        $(
+            $(#[$attr])*
+            pub struct $name {
+                _marker: PhantomData<*const ()>,
+            }
+
+            impl $name {
+                /// Address of the register block
+                pub const PTR: *const $module::RegisterBlock = $address as *const _;
+
+                /// Pointer to the register block
+                #[inline(always)]
+                pub const fn ptr() -> *const $module::RegisterBlock {
+                    Self::PTR
+                }
+            }
+
+            impl Deref for $name {
+                type Target = $module::RegisterBlock;
+
+                #[inline(always)]
+                fn deref(&self) -> &Self::Target {
+                    unsafe { &*Self::PTR }
+                }
+            }
+        )*
+
+        /// All peripherals of the chip
+        #[allow(non_snake_case)]
+        pub struct Peripherals {
+            $(pub $name: $name,)*
+        }
+
+        impl Peripherals {
+            /// Take the peripherals, only succeeds once
+            pub fn take() -> Option<Self> {
+                static mut TAKEN: bool = false;
+
+                critical_section::with(|_| unsafe {
+                    if TAKEN {
+                        None
+                    } else {
+                        TAKEN = true;
+                        Some(Self::steal())
+                    }
+                })
+            }
+
+            /// Create the peripherals regardless of whether they were already taken
+            ///
+            /// # Safety
+            /// Only one owner should access a given peripheral at a time
+            pub unsafe fn steal() -> Self {
+                Self {
+                    $($name: $name { _marker: PhantomData },)*
+                }
+            }
+        }
+    };
+}
+
+peripherals! {
+    /// General purpose timer 2
+    TIM2: tim2 @ 0x4000_0000,
+    /// Window watchdog
+    WWDG: wwdg @ 0x4000_2C00,
+    /// Independent watchdog
+    IWDG: iwdg @ 0x4000_3000,
+    /// I2C bus 1
+    I2C1: i2c @ 0x4000_5400,
+    /// Power control
+    PWR: pwr @ 0x4000_7000,
+    /// Alternate function I/O
+    AFIO: afio @ 0x4001_0000,
+    /// External interrupt and event controller
+    EXTI: exti @ 0x4001_0400,
+    /// GPIO port A
+    GPIOA: gpio @ 0x4001_0800,
+    /// GPIO port C
+    GPIOC: gpio @ 0x4001_1000,
+    /// GPIO port D
+    GPIOD: gpio @ 0x4001_1400,
+    /// Analog to digital converter
+    ADC1: adc @ 0x4001_2400,
+    /// Advanced control timer 1
+    TIM1: tim1 @ 0x4001_2C00,
+    /// SPI bus 1
+    SPI1: spi @ 0x4001_3000,
+    /// USART 1
+    USART1: usart @ 0x4001_3800,
+    /// DMA controller 1
+    DMA1: dma @ 0x4002_0000,
+    /// Reset and clock control
+    RCC: rcc @ 0x4002_1000,
+    /// Flash memory interface
+    FLASH: flash @ 0x4002_2000,
+    /// Programmable fast interrupt controller
+    PFIC: pfic @ 0xE000_E000,
+    /// QingKe V2 system timer
+    SYSTICK: systick @ 0xE000_F000,
+}
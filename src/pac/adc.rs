@@ -0,0 +1,236 @@
+//! Analog to digital converter
+//!
+//! Channels 0 to 7 are pins, channel 8 is the internal reference voltage and
+//! channel 9 the calibration voltage.
+
+use super::{register, Reg};
+
+#[repr(C)]
+pub struct RegisterBlock {
+    /// 0x00: Status
+    pub statr: Reg<STATR>,
+    /// 0x04: Control 1
+    pub ctlr1: Reg<CTLR1>,
+    /// 0x08: Control 2
+    pub ctlr2: Reg<CTLR2>,
+    /// 0x0C: Sample time 1, channels 10 to 15
+    pub samptr1: Reg<SAMPTR1>,
+    /// 0x10: Sample time 2, channels 0 to 9
+    pub samptr2: Reg<SAMPTR2>,
+    /// 0x14: Injected channel data offsets
+    pub iofr: [Reg<IOFR>; 4],
+    /// 0x24: Watchdog high threshold
+    pub wdhtr: Reg<WDHTR>,
+    /// 0x28: Watchdog low threshold
+    pub wdltr: Reg<WDLTR>,
+    /// 0x2C: Regular sequence 1
+    pub rsqr1: Reg<RSQR1>,
+    /// 0x30: Regular sequence 2
+    pub rsqr2: Reg<RSQR2>,
+    /// 0x34: Regular sequence 3
+    pub rsqr3: Reg<RSQR3>,
+    /// 0x38: Injected sequence
+    pub isqr: Reg<ISQR>,
+    /// 0x3C: Injected data
+    pub idatar: [Reg<IDATAR>; 4],
+    /// 0x4C: Regular data
+    pub rdatar: Reg<RDATAR>,
+    /// 0x50: Delayed trigger
+    pub dlyr: Reg<DLYR>,
+}
+
+register! {
+    /// Status, write 0 to clear
+    STATR = 0x0000_0000 {
+        /// Analog watchdog triggered
+        awd @ 0,
+        /// Regular conversion complete
+        eoc @ 1,
+        /// Injected conversion complete
+        jeoc @ 2,
+        /// Injected conversion started
+        jstrt @ 3,
+        /// Regular conversion started
+        strt @ 4,
+    }
+}
+
+register! {
+    /// Control 1
+    CTLR1 = 0x0000_0000 {
+        /// Analog watchdog channel
+        awdch @ 0: 5,
+        eocie @ 5,
+        awdie @ 6,
+        jeocie @ 7,
+        /// Scan mode
+        scan @ 8,
+        /// Watch a single channel
+        awdsgl @ 9,
+        /// Automatic injected conversion
+        jauto @ 10,
+        /// Discontinuous regular conversion
+        discen @ 11,
+        /// Discontinuous injected conversion
+        jdiscen @ 12,
+        /// Channels per discontinuous conversion, minus one
+        discnum @ 13: 3,
+        /// Watchdog on injected channels
+        jawden @ 22,
+        /// Watchdog on regular channels
+        awden @ 23,
+        /// Calibration voltage selection
+        calvol @ 25: 2,
+    }
+}
+
+register! {
+    /// Control 2
+    CTLR2 = 0x0000_0000 {
+        /// Power on and start
+        adon @ 0,
+        /// Continuous conversion
+        cont @ 1,
+        /// Start calibration
+        cal @ 2,
+        /// Reset calibration
+        rstcal @ 3,
+        /// DMA requests for regular conversions
+        dma @ 8,
+        /// Left aligned data
+        align @ 11,
+        /// Injected trigger selection
+        jextsel @ 12: 3,
+        /// Injected external trigger enable
+        jexttrig @ 15,
+        /// Regular trigger selection
+        extsel @ 17: 3,
+        /// Regular external trigger enable
+        exttrig @ 20,
+        /// Start injected conversion
+        jswstart @ 21,
+        /// Start regular conversion
+        swstart @ 22,
+    }
+}
+
+register! {
+    /// Sample time 1, 3 bits per channel
+    SAMPTR1 = 0x0000_0000 {
+        smp10 @ 0: 3,
+        smp11 @ 3: 3,
+        smp12 @ 6: 3,
+        smp13 @ 9: 3,
+        smp14 @ 12: 3,
+        smp15 @ 15: 3,
+    }
+}
+
+register! {
+    /// Sample time 2, 3 bits per channel
+    SAMPTR2 = 0x0000_0000 {
+        smp0 @ 0: 3,
+        smp1 @ 3: 3,
+        smp2 @ 6: 3,
+        smp3 @ 9: 3,
+        smp4 @ 12: 3,
+        smp5 @ 15: 3,
+        smp6 @ 18: 3,
+        smp7 @ 21: 3,
+        smp8 @ 24: 3,
+        smp9 @ 27: 3,
+    }
+}
+
+register! {
+    /// Injected channel data offset
+    IOFR = 0x0000_0000 {
+        joffset @ 0: 10,
+    }
+}
+
+register! {
+    /// Watchdog high threshold
+    WDHTR = 0x0000_03FF {
+        ht @ 0: 10,
+    }
+}
+
+register! {
+    /// Watchdog low threshold
+    WDLTR = 0x0000_0000 {
+        lt @ 0: 10,
+    }
+}
+
+register! {
+    /// Regular sequence 1
+    RSQR1 = 0x0000_0000 {
+        sq13 @ 0: 5,
+        sq14 @ 5: 5,
+        sq15 @ 10: 5,
+        sq16 @ 15: 5,
+        /// Sequence length, minus one
+        l @ 20: 4,
+    }
+}
+
+register! {
+    /// Regular sequence 2
+    RSQR2 = 0x0000_0000 {
+        sq7 @ 0: 5,
+        sq8 @ 5: 5,
+        sq9 @ 10: 5,
+        sq10 @ 15: 5,
+        sq11 @ 20: 5,
+        sq12 @ 25: 5,
+    }
+}
+
+register! {
+    /// Regular sequence 3
+    RSQR3 = 0x0000_0000 {
+        sq1 @ 0: 5,
+        sq2 @ 5: 5,
+        sq3 @ 10: 5,
+        sq4 @ 15: 5,
+        sq5 @ 20: 5,
+        sq6 @ 25: 5,
+    }
+}
+
+register! {
+    /// Injected sequence
+    ISQR = 0x0000_0000 {
+        jsq1 @ 0: 5,
+        jsq2 @ 5: 5,
+        jsq3 @ 10: 5,
+        jsq4 @ 15: 5,
+        /// Sequence length, minus one
+        jl @ 20: 2,
+    }
+}
+
+register! {
+    /// Injected data
+    IDATAR = 0x0000_0000 {
+        jdata @ 0: 16,
+    }
+}
+
+register! {
+    /// Regular data
+    RDATAR = 0x0000_0000 {
+        data @ 0: 16,
+    }
+}
+
+register! {
+    /// Delayed trigger
+    DLYR = 0x0000_0000 {
+        /// Delay in ADC clock cycles
+        dlyvlu @ 0: 9,
+        /// Delay the injected rather than the regular trigger
+        dlysrc @ 9,
+    }
+}
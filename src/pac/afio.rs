@@ -0,0 +1,58 @@
+//! Alternate function I/O
+
+use super::{register, Reg};
+
+#[repr(C)]
+pub struct RegisterBlock {
+    _reserved0: u32,
+    /// 0x04: Remap register 1
+    pub pcfr1: Reg<PCFR1>,
+    /// 0x08: External interrupt configuration
+    pub exticr: Reg<EXTICR>,
+}
+
+register! {
+    /// Remap register 1
+    PCFR1 = 0x0000_0000 {
+        /// SPI1 remapping
+        spi1_rm @ 0,
+        /// I2C1 remapping, low bit
+        i2c1_rm @ 1,
+        /// USART1 remapping, low bit
+        usart1_rm @ 2,
+        /// TIM1 remapping
+        tim1_rm @ 6: 2,
+        /// TIM2 remapping
+        tim2_rm @ 8: 2,
+        /// PA1 and PA2 used as GPIO instead of the external oscillator
+        pa12_rm @ 15,
+        /// ADC injected conversion trigger remapping
+        adc_etrginj_rm @ 17,
+        /// ADC regular conversion trigger remapping
+        adc_etrgreg_rm @ 18,
+        /// USART1 remapping, high bit
+        usart1_rm1 @ 21,
+        /// I2C1 remapping, high bit
+        i2c1_rm1 @ 22,
+        /// TIM1 channel 1 input from the LSI oscillator
+        tim1_iremap @ 23,
+        /// Serial wire debug configuration, `0b100` disables SWD
+        swcfg @ 24: 3,
+    }
+}
+
+register! {
+    /// External interrupt configuration
+    ///
+    /// Each line selects port A (`0b00`), C (`0b10`) or D (`0b11`)
+    EXTICR = 0x0000_0000 {
+        exti0 @ 0: 2,
+        exti1 @ 2: 2,
+        exti2 @ 4: 2,
+        exti3 @ 6: 2,
+        exti4 @ 8: 2,
+        exti5 @ 10: 2,
+        exti6 @ 12: 2,
+        exti7 @ 14: 2,
+    }
+}
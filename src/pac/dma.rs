@@ -0,0 +1,146 @@
+//! DMA controller with 7 channels
+
+use super::{register, Reg};
+
+#[repr(C)]
+pub struct RegisterBlock {
+    /// 0x00: Interrupt status
+    pub intfr: Reg<INTFR>,
+    /// 0x04: Interrupt flag clear
+    pub intfcr: Reg<INTFCR>,
+    /// 0x08: Channels 1 to 7
+    pub ch: [CH; 7],
+}
+
+/// Registers of a single channel
+#[repr(C)]
+pub struct CH {
+    /// Configuration
+    pub cfgr: Reg<CFGR>,
+    /// Number of data items left to transfer
+    pub cntr: Reg<CNTR>,
+    /// Peripheral address
+    pub paddr: Reg<PADDR>,
+    /// Memory address
+    pub maddr: Reg<MADDR>,
+    _reserved0: u32,
+}
+
+register! {
+    /// Interrupt status
+    INTFR = 0x0000_0000 {
+        gif1 @ 0,
+        tcif1 @ 1,
+        htif1 @ 2,
+        teif1 @ 3,
+        gif2 @ 4,
+        tcif2 @ 5,
+        htif2 @ 6,
+        teif2 @ 7,
+        gif3 @ 8,
+        tcif3 @ 9,
+        htif3 @ 10,
+        teif3 @ 11,
+        gif4 @ 12,
+        tcif4 @ 13,
+        htif4 @ 14,
+        teif4 @ 15,
+        gif5 @ 16,
+        tcif5 @ 17,
+        htif5 @ 18,
+        teif5 @ 19,
+        gif6 @ 20,
+        tcif6 @ 21,
+        htif6 @ 22,
+        teif6 @ 23,
+        gif7 @ 24,
+        tcif7 @ 25,
+        htif7 @ 26,
+        teif7 @ 27,
+    }
+}
+
+register! {
+    /// Interrupt flag clear, writing zeroes has no effect
+    INTFCR = 0x0000_0000 {
+        cgif1 @ 0,
+        ctcif1 @ 1,
+        chtif1 @ 2,
+        cteif1 @ 3,
+        cgif2 @ 4,
+        ctcif2 @ 5,
+        chtif2 @ 6,
+        cteif2 @ 7,
+        cgif3 @ 8,
+        ctcif3 @ 9,
+        chtif3 @ 10,
+        cteif3 @ 11,
+        cgif4 @ 12,
+        ctcif4 @ 13,
+        chtif4 @ 14,
+        cteif4 @ 15,
+        cgif5 @ 16,
+        ctcif5 @ 17,
+        chtif5 @ 18,
+        cteif5 @ 19,
+        cgif6 @ 20,
+        ctcif6 @ 21,
+        chtif6 @ 22,
+        cteif6 @ 23,
+        cgif7 @ 24,
+        ctcif7 @ 25,
+        chtif7 @ 26,
+        cteif7 @ 27,
+    }
+}
+
+register! {
+    /// Channel configuration
+    CFGR = 0x0000_0000 {
+        /// Channel enable
+        en @ 0,
+        /// Transfer complete interrupt enable
+        tcie @ 1,
+        /// Half transfer interrupt enable
+        htie @ 2,
+        /// Transfer error interrupt enable
+        teie @ 3,
+        /// Read from memory
+        dir @ 4,
+        /// Circular mode
+        circ @ 5,
+        /// Peripheral address increment
+        pinc @ 6,
+        /// Memory address increment
+        minc @ 7,
+        /// Peripheral data size, 8, 16 or 32 bits
+        psize @ 8: 2,
+        /// Memory data size, 8, 16 or 32 bits
+        msize @ 10: 2,
+        /// Channel priority
+        pl @ 12: 2,
+        /// Memory to memory mode
+        mem2mem @ 14,
+    }
+}
+
+register! {
+    /// Number of data items to transfer
+    CNTR = 0x0000_0000 {
+        ndt @ 0: 16,
+    }
+}
+
+register! {
+    /// Peripheral address
+    PADDR = 0x0000_0000 {
+        pa @ 0: 32,
+    }
+}
+
+register! {
+    /// Memory address
+    MADDR = 0x0000_0000 {
+        ma @ 0: 32,
+    }
+}
@@ -0,0 +1,117 @@
+//! External interrupt and event controller
+//!
+//! Lines 0 to 7 are GPIO pins, line 8 is the PVD and line 9 the auto-wakeup.
+
+use super::{register, Reg};
+
+#[repr(C)]
+pub struct RegisterBlock {
+    /// 0x00: Interrupt enable
+    pub intenr: Reg<INTENR>,
+    /// 0x04: Event enable
+    pub evenr: Reg<EVENR>,
+    /// 0x08: Rising edge trigger enable
+    pub rtenr: Reg<RTENR>,
+    /// 0x0C: Falling edge trigger enable
+    pub ftenr: Reg<FTENR>,
+    /// 0x10: Software interrupt event
+    pub swievr: Reg<SWIEVR>,
+    /// 0x14: Interrupt flag, write 1 to clear
+    pub intfr: Reg<INTFR>,
+}
+
+register! {
+    /// Interrupt enable
+    INTENR = 0x0000_0000 {
+        mr0 @ 0,
+        mr1 @ 1,
+        mr2 @ 2,
+        mr3 @ 3,
+        mr4 @ 4,
+        mr5 @ 5,
+        mr6 @ 6,
+        mr7 @ 7,
+        mr8 @ 8,
+        mr9 @ 9,
+    }
+}
+
+register! {
+    /// Event enable
+    EVENR = 0x0000_0000 {
+        mr0 @ 0,
+        mr1 @ 1,
+        mr2 @ 2,
+        mr3 @ 3,
+        mr4 @ 4,
+        mr5 @ 5,
+        mr6 @ 6,
+        mr7 @ 7,
+        mr8 @ 8,
+        mr9 @ 9,
+    }
+}
+
+register! {
+    /// Rising edge trigger enable
+    RTENR = 0x0000_0000 {
+        tr0 @ 0,
+        tr1 @ 1,
+        tr2 @ 2,
+        tr3 @ 3,
+        tr4 @ 4,
+        tr5 @ 5,
+        tr6 @ 6,
+        tr7 @ 7,
+        tr8 @ 8,
+        tr9 @ 9,
+    }
+}
+
+register! {
+    /// Falling edge trigger enable
+    FTENR = 0x0000_0000 {
+        tr0 @ 0,
+        tr1 @ 1,
+        tr2 @ 2,
+        tr3 @ 3,
+        tr4 @ 4,
+        tr5 @ 5,
+        tr6 @ 6,
+        tr7 @ 7,
+        tr8 @ 8,
+        tr9 @ 9,
+    }
+}
+
+register! {
+    /// Software interrupt event
+    SWIEVR = 0x0000_0000 {
+        swier0 @ 0,
+        swier1 @ 1,
+        swier2 @ 2,
+        swier3 @ 3,
+        swier4 @ 4,
+        swier5 @ 5,
+        swier6 @ 6,
+        swier7 @ 7,
+        swier8 @ 8,
+        swier9 @ 9,
+    }
+}
+
+register! {
+    /// Interrupt flag
+    INTFR = 0x0000_0000 {
+        if0 @ 0,
+        if1 @ 1,
+        if2 @ 2,
+        if3 @ 3,
+        if4 @ 4,
+        if5 @ 5,
+        if6 @ 6,
+        if7 @ 7,
+        if8 @ 8,
+        if9 @ 9,
+    }
+}
@@ -0,0 +1,146 @@
+//! Flash memory interface
+
+use super::{register, Reg};
+
+#[repr(C)]
+pub struct RegisterBlock {
+    /// 0x00: Access control
+    pub actlr: Reg<ACTLR>,
+    /// 0x04: Key
+    pub keyr: Reg<KEYR>,
+    /// 0x08: Option byte key
+    pub obkeyr: Reg<OBKEYR>,
+    /// 0x0C: Status
+    pub statr: Reg<STATR>,
+    /// 0x10: Control
+    pub ctlr: Reg<CTLR>,
+    /// 0x14: Address
+    pub addr: Reg<ADDR>,
+    _reserved0: u32,
+    /// 0x1C: Option byte
+    pub obr: Reg<OBR>,
+    /// 0x20: Write protection
+    pub wpr: Reg<WPR>,
+    /// 0x24: Extended key
+    pub modekeyr: Reg<MODEKEYR>,
+    /// 0x28: Boot area key
+    pub boot_modekeyr: Reg<BOOT_MODEKEYR>,
+}
+
+register! {
+    /// Access control
+    ACTLR = 0x0000_0000 {
+        /// Wait states, 0 up to 24 MHz and 1 up to 48 MHz
+        latency @ 0: 2,
+    }
+}
+
+register! {
+    /// Key
+    KEYR = 0x0000_0000 {
+        keyr @ 0: 32,
+    }
+}
+
+register! {
+    /// Option byte key
+    OBKEYR = 0x0000_0000 {
+        optkey @ 0: 32,
+    }
+}
+
+register! {
+    /// Status
+    STATR = 0x0000_0000 {
+        /// Busy
+        bsy @ 0,
+        /// Write protection error
+        wrprterr @ 4,
+        /// End of operation
+        eop @ 5,
+        /// Boot from the bootloader area after a software reset
+        mode @ 14,
+        /// Boot area locked
+        boot_lock @ 15,
+    }
+}
+
+register! {
+    /// Control
+    CTLR = 0x0000_8080 {
+        /// Half-word programming
+        pg @ 0,
+        /// Sector erase
+        per @ 1,
+        /// Mass erase
+        mer @ 2,
+        /// Option byte programming
+        obpg @ 4,
+        /// Option byte erase
+        ober @ 5,
+        /// Start the erase
+        strt @ 6,
+        /// Locked
+        lock @ 7,
+        /// Option byte write enable
+        optwre @ 9,
+        errie @ 10,
+        eopie @ 12,
+        /// Fast programming locked
+        flock @ 15,
+        /// Fast page programming
+        page_pg @ 16,
+        /// Fast page erase
+        page_er @ 17,
+        /// Load the page buffer
+        bufload @ 18,
+        /// Reset the page buffer
+        bufrst @ 19,
+    }
+}
+
+register! {
+    /// Address
+    ADDR = 0x0000_0000 {
+        far @ 0: 32,
+    }
+}
+
+register! {
+    /// Option byte
+    OBR = 0x0000_0000 {
+        /// Option byte error
+        opterr @ 0,
+        /// Read protection
+        rdprt @ 1,
+        /// Software watchdog
+        iwdg_sw @ 2,
+        stop_rst @ 3,
+        standby_rst @ 4,
+        /// External reset pin configuration
+        rst_mode @ 5: 2,
+        data0 @ 10: 8,
+        data1 @ 18: 8,
+    }
+}
+
+register! {
+    /// Write protection
+    WPR = 0x0000_0000 {
+        wrp @ 0: 32,
+    }
+}
+
+register! {
+    /// Extended key
+    MODEKEYR = 0x0000_0000 {
+        modekeyr @ 0: 32,
+    }
+}
+
+register! {
+    /// Boot area key
+    BOOT_MODEKEYR = 0x0000_0000 {
+        modekeyr @ 0: 32,
+    }
+}
@@ -0,0 +1,125 @@
+//! General purpose I/O, shared by GPIOA, GPIOC and GPIOD
+
+use super::{register, Reg};
+
+#[repr(C)]
+pub struct RegisterBlock {
+    /// 0x00: Port configuration, 4 bits per pin
+    pub cfglr: Reg<CFGLR>,
+    _reserved0: u32,
+    /// 0x08: Port input data
+    pub indr: Reg<INDR>,
+    /// 0x0C: Port output data
+    pub outdr: Reg<OUTDR>,
+    /// 0x10: Port set/reset
+    pub bshr: Reg<BSHR>,
+    /// 0x14: Port reset
+    pub bcr: Reg<BCR>,
+    /// 0x18: Port configuration lock
+    pub lckr: Reg<LCKR>,
+}
+
+register! {
+    /// Port configuration
+    ///
+    /// `mode` is 0 for input, 1 for 10 MHz, 2 for 2 MHz and 3 for 30 MHz
+    /// output. The meaning of `cnf` depends on the mode.
+    CFGLR = 0x4444_4444 {
+        mode0 @ 0: 2,
+        cnf0 @ 2: 2,
+        mode1 @ 4: 2,
+        cnf1 @ 6: 2,
+        mode2 @ 8: 2,
+        cnf2 @ 10: 2,
+        mode3 @ 12: 2,
+        cnf3 @ 14: 2,
+        mode4 @ 16: 2,
+        cnf4 @ 18: 2,
+        mode5 @ 20: 2,
+        cnf5 @ 22: 2,
+        mode6 @ 24: 2,
+        cnf6 @ 26: 2,
+        mode7 @ 28: 2,
+        cnf7 @ 30: 2,
+    }
+}
+
+register! {
+    /// Port input data
+    INDR = 0x0000_0000 {
+        idr0 @ 0,
+        idr1 @ 1,
+        idr2 @ 2,
+        idr3 @ 3,
+        idr4 @ 4,
+        idr5 @ 5,
+        idr6 @ 6,
+        idr7 @ 7,
+    }
+}
+
+register! {
+    /// Port output data, also selects pull-up (1) or pull-down (0) for inputs
+    OUTDR = 0x0000_0000 {
+        odr0 @ 0,
+        odr1 @ 1,
+        odr2 @ 2,
+        odr3 @ 3,
+        odr4 @ 4,
+        odr5 @ 5,
+        odr6 @ 6,
+        odr7 @ 7,
+    }
+}
+
+register! {
+    /// Port set/reset, writing zeroes has no effect
+    BSHR = 0x0000_0000 {
+        bs0 @ 0,
+        bs1 @ 1,
+        bs2 @ 2,
+        bs3 @ 3,
+        bs4 @ 4,
+        bs5 @ 5,
+        bs6 @ 6,
+        bs7 @ 7,
+        br0 @ 16,
+        br1 @ 17,
+        br2 @ 18,
+        br3 @ 19,
+        br4 @ 20,
+        br5 @ 21,
+        br6 @ 22,
+        br7 @ 23,
+    }
+}
+
+register! {
+    /// Port reset, writing zeroes has no effect
+    BCR = 0x0000_0000 {
+        br0 @ 0,
+        br1 @ 1,
+        br2 @ 2,
+        br3 @ 3,
+        br4 @ 4,
+        br5 @ 5,
+        br6 @ 6,
+        br7 @ 7,
+    }
+}
+
+register! {
+    /// Port configuration lock
+    LCKR = 0x0000_0000 {
+        lck0 @ 0,
+        lck1 @ 1,
+        lck2 @ 2,
+        lck3 @ 3,
+        lck4 @ 4,
+        lck5 @ 5,
+        lck6 @ 6,
+        lck7 @ 7,
+        /// Lock key
+        lckk @ 8,
+    }
+}
@@ -0,0 +1,157 @@
+//! Inter-integrated circuit interface
+
+use super::{register, Reg};
+
+#[repr(C)]
+pub struct RegisterBlock {
+    /// 0x00: Control 1
+    pub ctlr1: Reg<CTLR1>,
+    /// 0x04: Control 2
+    pub ctlr2: Reg<CTLR2>,
+    /// 0x08: Own address 1
+    pub oaddr1: Reg<OADDR1>,
+    /// 0x0C: Own address 2
+    pub oaddr2: Reg<OADDR2>,
+    /// 0x10: Data
+    pub datar: Reg<DATAR>,
+    /// 0x14: Status 1
+    pub star1: Reg<STAR1>,
+    /// 0x18: Status 2
+    pub star2: Reg<STAR2>,
+    /// 0x1C: Clock configuration
+    pub ckcfgr: Reg<CKCFGR>,
+}
+
+register! {
+    /// Control 1
+    CTLR1 = 0x0000_0000 {
+        /// Peripheral enable
+        pe @ 0,
+        smbus @ 1,
+        smbtype @ 3,
+        enarp @ 4,
+        enpec @ 5,
+        /// General call enable
+        engc @ 6,
+        /// Clock stretching disable
+        nostretch @ 7,
+        /// Generate a start condition
+        start @ 8,
+        /// Generate a stop condition
+        stop @ 9,
+        /// Acknowledge enable
+        ack @ 10,
+        /// Acknowledge the next byte
+        pos @ 11,
+        pec @ 12,
+        alert @ 13,
+        /// Software reset
+        swrst @ 15,
+    }
+}
+
+register! {
+    /// Control 2
+    CTLR2 = 0x0000_0000 {
+        /// Bus clock frequency in MHz
+        freq @ 0: 6,
+        iterren @ 8,
+        itevten @ 9,
+        itbufen @ 10,
+        dmaen @ 11,
+        /// Next DMA transfer is the last
+        last @ 12,
+    }
+}
+
+register! {
+    /// Own address 1
+    OADDR1 = 0x0000_0000 {
+        /// Bit 0 of a 10-bit address
+        add0 @ 0,
+        /// 7-bit address
+        add7_1 @ 1: 7,
+        /// Bits 9..8 of a 10-bit address
+        add9_8 @ 8: 2,
+        /// 10-bit addressing
+        addmode @ 15,
+    }
+}
+
+register! {
+    /// Own address 2
+    OADDR2 = 0x0000_0000 {
+        /// Dual addressing enable
+        endual @ 0,
+        add2 @ 1: 7,
+    }
+}
+
+register! {
+    /// Data
+    DATAR = 0x0000_0000 {
+        dr @ 0: 8,
+    }
+}
+
+register! {
+    /// Status 1
+    STAR1 = 0x0000_0000 {
+        /// Start condition generated
+        sb @ 0,
+        /// Address sent or matched
+        addr @ 1,
+        /// Byte transfer finished
+        btf @ 2,
+        /// 10-bit header sent
+        add10 @ 3,
+        /// Stop condition detected
+        stopf @ 4,
+        /// Receive buffer not empty
+        rxne @ 6,
+        /// Transmit buffer empty
+        txe @ 7,
+        /// Bus error
+        berr @ 8,
+        /// Arbitration lost
+        arlo @ 9,
+        /// Acknowledge failure
+        af @ 10,
+        /// Overrun or underrun
+        ovr @ 11,
+        pecerr @ 12,
+        timeout @ 14,
+        smbalert @ 15,
+    }
+}
+
+register! {
+    /// Status 2
+    STAR2 = 0x0000_0000 {
+        /// Master mode
+        msl @ 0,
+        /// Bus busy
+        busy @ 1,
+        /// Transmitter
+        tra @ 2,
+        /// General call address received
+        gencall @ 4,
+        smbdefault @ 5,
+        smbhost @ 6,
+        /// Matched the second own address
+        dualf @ 7,
+        pec @ 8: 8,
+    }
+}
+
+register! {
+    /// Clock configuration
+    CKCFGR = 0x0000_0000 {
+        /// Clock control value
+        ccr @ 0: 12,
+        /// Fast mode duty cycle, 16/9 when set and 2 otherwise
+        duty @ 14,
+        /// Fast mode
+        fs @ 15,
+    }
+}
@@ -0,0 +1,46 @@
+//! Independent watchdog, clocked by the 128 kHz LSI oscillator
+
+use super::{register, Reg};
+
+#[repr(C)]
+pub struct RegisterBlock {
+    /// 0x00: Key
+    pub ctlr: Reg<CTLR>,
+    /// 0x04: Prescaler
+    pub pscr: Reg<PSCR>,
+    /// 0x08: Reload value
+    pub rldr: Reg<RLDR>,
+    /// 0x0C: Status
+    pub statr: Reg<STATR>,
+}
+
+register! {
+    /// Key, `0xAAAA` reloads, `0x5555` unlocks and `0xCCCC` starts
+    CTLR = 0x0000_0000 {
+        key @ 0: 16,
+    }
+}
+
+register! {
+    /// Prescaler, the LSI clock is divided by `4 << pr`
+    PSCR = 0x0000_0000 {
+        pr @ 0: 3,
+    }
+}
+
+register! {
+    /// Reload value
+    RLDR = 0x0000_0FFF {
+        rl @ 0: 12,
+    }
+}
+
+register! {
+    /// Status
+    STATR = 0x0000_0000 {
+        /// Prescaler update in progress
+        pvu @ 0,
+        /// Reload value update in progress
+        rvu @ 1,
+    }
+}
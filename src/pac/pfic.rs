@@ -0,0 +1,174 @@
+//! Programmable fast interrupt controller
+//!
+//! Interrupt numbers below 16 are core exceptions, peripheral interrupts
+//! start at 16. Bit `n` of word `n / 32` in the enable, pending and active
+//! registers belongs to interrupt `n`.
+
+use super::{register, Reg};
+
+#[repr(C)]
+pub struct RegisterBlock {
+    /// 0x000: Interrupt enable status
+    pub isr: [Reg<ISR>; 2],
+    _reserved0: [u32; 6],
+    /// 0x020: Interrupt pending status
+    pub ipr: [Reg<IPR>; 2],
+    _reserved1: [u32; 6],
+    /// 0x040: Interrupt priority threshold
+    pub ithresdr: Reg<ITHRESDR>,
+    _reserved2: u32,
+    /// 0x048: Interrupt configuration
+    pub cfgr: Reg<CFGR>,
+    /// 0x04C: Global interrupt status
+    pub gisr: Reg<GISR>,
+    /// 0x050: Vector table free interrupt IDs
+    pub vtfidr: Reg<VTFIDR>,
+    _reserved3: [u32; 3],
+    /// 0x060: Vector table free interrupt addresses
+    pub vtfaddrr: [Reg<VTFADDRR>; 2],
+    _reserved4: [u32; 38],
+    /// 0x100: Interrupt enable set
+    pub ienr: [Reg<IENR>; 2],
+    _reserved5: [u32; 30],
+    /// 0x180: Interrupt enable clear
+    pub irer: [Reg<IRER>; 2],
+    _reserved6: [u32; 30],
+    /// 0x200: Interrupt pending set
+    pub ipsr: [Reg<IPSR>; 2],
+    _reserved7: [u32; 30],
+    /// 0x280: Interrupt pending clear
+    pub iprr: [Reg<IPRR>; 2],
+    _reserved8: [u32; 30],
+    /// 0x300: Interrupt active status
+    pub iactr: [Reg<IACTR>; 2],
+    _reserved9: [u32; 62],
+    /// 0x400: Interrupt priority, one byte per interrupt
+    pub iprior: [Reg<IPRIOR>; 16],
+    _reserved10: [u32; 564],
+    /// 0xD10: System control
+    pub sctlr: Reg<SCTLR>,
+}
+
+register! {
+    /// Interrupt enable status
+    ISR = 0x0000_0000 {
+        intensta @ 0: 32,
+    }
+}
+
+register! {
+    /// Interrupt pending status
+    IPR = 0x0000_0000 {
+        pendsta @ 0: 32,
+    }
+}
+
+register! {
+    /// Interrupt enable set, writing zeroes has no effect
+    IENR = 0x0000_0000 {
+        inten @ 0: 32,
+    }
+}
+
+register! {
+    /// Interrupt enable clear, writing zeroes has no effect
+    IRER = 0x0000_0000 {
+        interset @ 0: 32,
+    }
+}
+
+register! {
+    /// Interrupt pending set, writing zeroes has no effect
+    IPSR = 0x0000_0000 {
+        pendset @ 0: 32,
+    }
+}
+
+register! {
+    /// Interrupt pending clear, writing zeroes has no effect
+    IPRR = 0x0000_0000 {
+        pendreset @ 0: 32,
+    }
+}
+
+register! {
+    /// Interrupt active status
+    IACTR = 0x0000_0000 {
+        iacts @ 0: 32,
+    }
+}
+
+register! {
+    /// Interrupt priority threshold
+    ITHRESDR = 0x0000_0000 {
+        threshold @ 0: 8,
+    }
+}
+
+register! {
+    /// Interrupt configuration
+    CFGR = 0x0000_0000 {
+        /// System reset request, needs the key
+        resetsys @ 7,
+        /// Write protection key, `0xBEEF`
+        keycode @ 16: 16,
+    }
+}
+
+register! {
+    /// Global interrupt status
+    GISR = 0x0000_0000 {
+        /// Current nesting level
+        neststa @ 0: 8,
+        /// An interrupt is being handled
+        gactsta @ 8,
+        /// An interrupt is pending
+        gpendsta @ 9,
+    }
+}
+
+register! {
+    /// Vector table free interrupt IDs
+    VTFIDR = 0x0000_0000 {
+        vtfid0 @ 0: 8,
+        vtfid1 @ 8: 8,
+    }
+}
+
+register! {
+    /// Vector table free interrupt address
+    VTFADDRR = 0x0000_0000 {
+        /// Enable fast handling of this interrupt
+        vtfen @ 0,
+        /// Handler address, bit 0 is implied zero
+        addr @ 1: 31,
+    }
+}
+
+register! {
+    /// Interrupt priority, bits 7..6 of each byte are implemented
+    IPRIOR = 0x0000_0000 {
+        iprior0 @ 0: 8,
+        iprior1 @ 8: 8,
+        iprior2 @ 16: 8,
+        iprior3 @ 24: 8,
+    }
+}
+
+register! {
+    /// System control
+    SCTLR = 0x0000_0000 {
+        /// Enter sleep again when returning from an interrupt
+        sleeponexit @ 1,
+        /// Deep sleep on `wfi`
+        sleepdeep @ 2,
+        /// Treat `wfi` as wait for event
+        wfitowfe @ 3,
+        /// Pending interrupts generate events
+        sevonpend @ 4,
+        /// Set the event flag
+        setevent @ 5,
+        /// System reset
+        sysreset @ 31,
+    }
+}
@@ -0,0 +1,58 @@
+//! Power control
+
+use super::{register, Reg};
+
+#[repr(C)]
+pub struct RegisterBlock {
+    /// 0x00: Control
+    pub ctlr: Reg<CTLR>,
+    /// 0x04: Status
+    pub csr: Reg<CSR>,
+    /// 0x08: Auto-wakeup control
+    pub awucsr: Reg<AWUCSR>,
+    /// 0x0C: Auto-wakeup window
+    pub awuwr: Reg<AWUWR>,
+    /// 0x10: Auto-wakeup prescaler
+    pub awupsc: Reg<AWUPSC>,
+}
+
+register! {
+    /// Control
+    CTLR = 0x0000_0000 {
+        /// Standby rather than sleep on deep sleep
+        pdds @ 1,
+        /// Voltage detector enable
+        pvde @ 4,
+        /// Voltage detector threshold
+        pls @ 5: 3,
+    }
+}
+
+register! {
+    /// Status
+    CSR = 0x0000_0000 {
+        /// Supply is below the voltage detector threshold
+        pvdo @ 2,
+    }
+}
+
+register! {
+    /// Auto-wakeup control
+    AWUCSR = 0x0000_0000 {
+        awuen @ 1,
+    }
+}
+
+register! {
+    /// Auto-wakeup window
+    AWUWR = 0x0000_003F {
+        awuwr @ 0: 6,
+    }
+}
+
+register! {
+    /// Auto-wakeup prescaler
+    AWUPSC = 0x0000_0000 {
+        awupsc @ 0: 4,
+    }
+}
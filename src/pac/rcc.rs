@@ -0,0 +1,170 @@
+//! Reset and clock control
+
+use super::{register, Reg};
+
+#[repr(C)]
+pub struct RegisterBlock {
+    /// 0x00: Clock control
+    pub ctlr: Reg<CTLR>,
+    /// 0x04: Clock configuration 0
+    pub cfgr0: Reg<CFGR0>,
+    /// 0x08: Clock interrupt
+    pub intr: Reg<INTR>,
+    /// 0x0C: APB2 peripheral reset
+    pub apb2prstr: Reg<APB2PRSTR>,
+    /// 0x10: APB1 peripheral reset
+    pub apb1prstr: Reg<APB1PRSTR>,
+    /// 0x14: AHB peripheral clock enable
+    pub ahbpcenr: Reg<AHBPCENR>,
+    /// 0x18: APB2 peripheral clock enable
+    pub apb2pcenr: Reg<APB2PCENR>,
+    /// 0x1C: APB1 peripheral clock enable
+    pub apb1pcenr: Reg<APB1PCENR>,
+    _reserved0: u32,
+    /// 0x24: Control/status
+    pub rstsckr: Reg<RSTSCKR>,
+}
+
+register! {
+    /// Clock control
+    CTLR = 0x0000_0083 {
+        /// Internal 24 MHz RC oscillator enable
+        hsion @ 0,
+        /// Internal 24 MHz RC oscillator ready
+        hsirdy @ 1,
+        /// Internal oscillator trim
+        hsitrim @ 3: 5,
+        /// Internal oscillator factory calibration
+        hsical @ 8: 8,
+        /// External oscillator enable
+        hseon @ 16,
+        /// External oscillator ready
+        hserdy @ 17,
+        /// External oscillator bypass
+        hsebyp @ 18,
+        /// Clock security system enable
+        csson @ 19,
+        /// PLL enable
+        pllon @ 24,
+        /// PLL ready
+        pllrdy @ 25,
+    }
+}
+
+register! {
+    /// Clock configuration 0
+    CFGR0 = 0x0000_0020 {
+        /// System clock switch
+        sw @ 0: 2,
+        /// System clock switch status
+        sws @ 2: 2,
+        /// AHB prescaler
+        hpre @ 4: 4,
+        /// ADC prescaler
+        adcpre @ 11: 5,
+        /// PLL entry clock source
+        pllsrc @ 16,
+        /// Microcontroller clock output
+        mco @ 24: 3,
+    }
+}
+
+register! {
+    /// Clock interrupt
+    INTR = 0x0000_0000 {
+        lsirdyf @ 0,
+        hsirdyf @ 2,
+        hserdyf @ 3,
+        pllrdyf @ 4,
+        cssf @ 7,
+        lsirdyie @ 8,
+        hsirdyie @ 10,
+        hserdyie @ 11,
+        pllrdyie @ 12,
+        lsirdyc @ 16,
+        hsirdyc @ 18,
+        hserdyc @ 19,
+        pllrdyc @ 20,
+        cssc @ 23,
+    }
+}
+
+register! {
+    /// APB2 peripheral reset
+    APB2PRSTR = 0x0000_0000 {
+        afiorst @ 0,
+        ioparst @ 2,
+        iopcrst @ 4,
+        iopdrst @ 5,
+        adc1rst @ 9,
+        tim1rst @ 11,
+        spi1rst @ 12,
+        usart1rst @ 14,
+    }
+}
+
+register! {
+    /// APB1 peripheral reset
+    APB1PRSTR = 0x0000_0000 {
+        tim2rst @ 0,
+        wwdgrst @ 11,
+        i2c1rst @ 21,
+        pwrrst @ 28,
+    }
+}
+
+register! {
+    /// AHB peripheral clock enable
+    AHBPCENR = 0x0000_0014 {
+        dma1en @ 0,
+        sramen @ 2,
+    }
+}
+
+register! {
+    /// APB2 peripheral clock enable
+    APB2PCENR = 0x0000_0000 {
+        afioen @ 0,
+        iopaen @ 2,
+        iopcen @ 4,
+        iopden @ 5,
+        adc1en @ 9,
+        tim1en @ 11,
+        spi1en @ 12,
+        usart1en @ 14,
+    }
+}
+
+register! {
+    /// APB1 peripheral clock enable
+    APB1PCENR = 0x0000_0000 {
+        tim2en @ 0,
+        wwdgen @ 11,
+        i2c1en @ 21,
+        pwren @ 28,
+    }
+}
+
+register! {
+    /// Control/status
+    RSTSCKR = 0x0C00_0000 {
+        /// Internal 128 kHz oscillator enable
+        lsion @ 0,
+        /// Internal 128 kHz oscillator ready
+        lsirdy @ 1,
+        /// Clear reset flags
+        rmvf @ 24,
+        /// External reset pin
+        pinrstf @ 26,
+        /// Power on reset
+        porrstf @ 27,
+        /// Software reset
+        sftrstf @ 28,
+        /// Independent watchdog reset
+        iwdgrstf @ 29,
+        /// Window watchdog reset
+        wwdgrstf @ 30,
+        /// Low power reset
+        lpwrrstf @ 31,
+    }
+}
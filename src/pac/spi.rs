@@ -0,0 +1,128 @@
+//! Serial peripheral interface
+
+use super::{register, Reg};
+
+#[repr(C)]
+pub struct RegisterBlock {
+    /// 0x00: Control 1
+    pub ctlr1: Reg<CTLR1>,
+    /// 0x04: Control 2
+    pub ctlr2: Reg<CTLR2>,
+    /// 0x08: Status
+    pub statr: Reg<STATR>,
+    /// 0x0C: Data
+    pub datar: Reg<DATAR>,
+    /// 0x10: CRC polynomial
+    pub crcr: Reg<CRCR>,
+    /// 0x14: Receive CRC
+    pub rcrcr: Reg<RCRCR>,
+    /// 0x18: Transmit CRC
+    pub tcrcr: Reg<TCRCR>,
+    _reserved0: [u32; 2],
+    /// 0x24: High speed control
+    pub hscr: Reg<HSCR>,
+}
+
+register! {
+    /// Control 1
+    CTLR1 = 0x0000_0000 {
+        /// Clock phase, sample on the second edge
+        cpha @ 0,
+        /// Clock polarity, idle high
+        cpol @ 1,
+        /// Master mode
+        mstr @ 2,
+        /// Baud rate, the bus clock divided by `2 << br`
+        br @ 3: 3,
+        /// SPI enable
+        spe @ 6,
+        /// Least significant bit first
+        lsbfirst @ 7,
+        /// Internal slave select level
+        ssi @ 8,
+        /// Software slave management
+        ssm @ 9,
+        /// Receive only
+        rxonly @ 10,
+        /// 16-bit data frames
+        dff @ 11,
+        /// Transmit the CRC next
+        crcnext @ 12,
+        /// CRC enable
+        crcen @ 13,
+        /// Bidirectional output enable
+        bidioe @ 14,
+        /// Bidirectional single wire mode
+        bidimode @ 15,
+    }
+}
+
+register! {
+    /// Control 2
+    CTLR2 = 0x0000_0000 {
+        rxdmaen @ 0,
+        txdmaen @ 1,
+        /// Drive NSS as an output in master mode
+        ssoe @ 2,
+        errie @ 5,
+        rxneie @ 6,
+        txeie @ 7,
+    }
+}
+
+register! {
+    /// Status
+    STATR = 0x0000_0002 {
+        /// Receive buffer not empty
+        rxne @ 0,
+        /// Transmit buffer empty
+        txe @ 1,
+        chside @ 2,
+        /// Underrun
+        udr @ 3,
+        /// CRC mismatch
+        crcerr @ 4,
+        /// Mode fault
+        modf @ 5,
+        /// Overrun
+        ovr @ 6,
+        /// Busy
+        bsy @ 7,
+    }
+}
+
+register! {
+    /// Data
+    DATAR = 0x0000_0000 {
+        dr @ 0: 16,
+    }
+}
+
+register! {
+    /// CRC polynomial
+    CRCR = 0x0000_0007 {
+        polynomial @ 0: 16,
+    }
+}
+
+register! {
+    /// Receive CRC
+    RCRCR = 0x0000_0000 {
+        rxcrc @ 0: 16,
+    }
+}
+
+register! {
+    /// Transmit CRC
+    TCRCR = 0x0000_0000 {
+        txcrc @ 0: 16,
+    }
+}
+
+register! {
+    /// High speed control
+    HSCR = 0x0000_0000 {
+        /// High speed read mode enable
+        hsrxen @ 0,
+    }
+}
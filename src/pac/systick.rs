@@ -0,0 +1,54 @@
+//! QingKe V2 system timer
+
+use super::{register, Reg};
+
+#[repr(C)]
+pub struct RegisterBlock {
+    /// 0x00: Control
+    pub ctlr: Reg<CTLR>,
+    /// 0x04: Status
+    pub sr: Reg<SR>,
+    /// 0x08: Counter
+    pub cnt: Reg<CNT>,
+    _reserved0: u32,
+    /// 0x10: Compare value
+    pub cmp: Reg<CMP>,
+}
+
+register! {
+    /// Control
+    CTLR = 0x0000_0000 {
+        /// Counter enable
+        ste @ 0,
+        /// Interrupt on compare match enable
+        stie @ 1,
+        /// Clock source, 0 for HCLK / 8 and 1 for HCLK
+        stclk @ 2,
+        /// Restart from zero on compare match
+        stre @ 3,
+        /// Software interrupt trigger
+        swie @ 31,
+    }
+}
+
+register! {
+    /// Status
+    SR = 0x0000_0000 {
+        /// Compare match flag
+        cntif @ 0,
+    }
+}
+
+register! {
+    /// Counter, counts upwards
+    CNT = 0x0000_0000 {
+        cnt @ 0: 32,
+    }
+}
+
+register! {
+    /// Compare value
+    CMP = 0x0000_0000 {
+        cmp @ 0: 32,
+    }
+}
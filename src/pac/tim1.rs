@@ -0,0 +1,300 @@
+//! Advanced control timer
+
+use super::{register, Reg};
+
+#[repr(C)]
+pub struct RegisterBlock {
+    /// 0x00: Control 1
+    pub ctlr1: Reg<CTLR1>,
+    /// 0x04: Control 2
+    pub ctlr2: Reg<CTLR2>,
+    /// 0x08: Slave mode control
+    pub smcfgr: Reg<SMCFGR>,
+    /// 0x0C: DMA and interrupt enable
+    pub dmaintenr: Reg<DMAINTENR>,
+    /// 0x10: Interrupt status, write 0 to clear
+    pub intfr: Reg<INTFR>,
+    /// 0x14: Event generation
+    pub swevgr: Reg<SWEVGR>,
+    /// 0x18: Compare/capture control 1
+    pub chctlr1: Reg<CHCTLR1>,
+    /// 0x1C: Compare/capture control 2
+    pub chctlr2: Reg<CHCTLR2>,
+    /// 0x20: Compare/capture enable
+    pub ccer: Reg<CCER>,
+    /// 0x24: Counter
+    pub cnt: Reg<CNT>,
+    /// 0x28: Prescaler
+    pub psc: Reg<PSC>,
+    /// 0x2C: Auto-reload
+    pub atrlr: Reg<ATRLR>,
+    /// 0x30: Repetition counter
+    pub rptcr: Reg<RPTCR>,
+    /// 0x34: Compare/capture value for channels 1 to 4
+    pub chcvr: [Reg<CHCVR>; 4],
+    /// 0x44: Break and dead-time
+    pub bdtr: Reg<BDTR>,
+    /// 0x48: DMA control
+    pub dmacfgr: Reg<DMACFGR>,
+    /// 0x4C: DMA burst address
+    pub dmaadr: Reg<DMAADR>,
+}
+
+register! {
+    /// Control 1
+    CTLR1 = 0x0000_0000 {
+        /// Counter enable
+        cen @ 0,
+        /// Update disable
+        udis @ 1,
+        /// Update request source
+        urs @ 2,
+        /// One pulse mode
+        opm @ 3,
+        /// Count downwards
+        dir @ 4,
+        /// Center aligned mode
+        cms @ 5: 2,
+        /// Auto-reload preload enable
+        arpe @ 7,
+        /// Clock division for dead-time and filters
+        ckd @ 8: 2,
+        /// Capture value overflow indication enable
+        capov @ 14,
+        /// Capture level indication enable
+        caplvl @ 15,
+    }
+}
+
+register! {
+    /// Control 2
+    CTLR2 = 0x0000_0000 {
+        /// Compare/capture preload control
+        ccpc @ 0,
+        /// Compare/capture control update selection
+        ccus @ 2,
+        /// Compare/capture DMA selection
+        ccds @ 3,
+        /// Master mode selection
+        mms @ 4: 3,
+        /// TI1 selection
+        ti1s @ 7,
+        /// Idle output states
+        ois1 @ 8,
+        ois1n @ 9,
+        ois2 @ 10,
+        ois2n @ 11,
+        ois3 @ 12,
+        ois3n @ 13,
+        ois4 @ 14,
+    }
+}
+
+register! {
+    /// Slave mode control
+    SMCFGR = 0x0000_0000 {
+        /// Slave mode selection
+        sms @ 0: 3,
+        /// Trigger selection
+        ts @ 4: 3,
+        /// Master/slave mode
+        msm @ 7,
+        /// External trigger filter
+        etf @ 8: 4,
+        /// External trigger prescaler
+        etps @ 12: 2,
+        /// External clock mode 2 enable
+        ece @ 14,
+        /// External trigger polarity
+        etp @ 15,
+    }
+}
+
+register! {
+    /// DMA and interrupt enable
+    DMAINTENR = 0x0000_0000 {
+        uie @ 0,
+        cc1ie @ 1,
+        cc2ie @ 2,
+        cc3ie @ 3,
+        cc4ie @ 4,
+        comie @ 5,
+        tie @ 6,
+        bie @ 7,
+        ude @ 8,
+        cc1de @ 9,
+        cc2de @ 10,
+        cc3de @ 11,
+        cc4de @ 12,
+        comde @ 13,
+        tde @ 14,
+    }
+}
+
+register! {
+    /// Interrupt status
+    INTFR = 0x0000_0000 {
+        uif @ 0,
+        cc1if @ 1,
+        cc2if @ 2,
+        cc3if @ 3,
+        cc4if @ 4,
+        comif @ 5,
+        tif @ 6,
+        bif @ 7,
+        cc1of @ 9,
+        cc2of @ 10,
+        cc3of @ 11,
+        cc4of @ 12,
+    }
+}
+
+register! {
+    /// Event generation
+    SWEVGR = 0x0000_0000 {
+        ug @ 0,
+        cc1g @ 1,
+        cc2g @ 2,
+        cc3g @ 3,
+        cc4g @ 4,
+        comg @ 5,
+        tg @ 6,
+        bg @ 7,
+    }
+}
+
+register! {
+    /// Compare/capture control 1, the `ic` fields apply in input mode
+    CHCTLR1 = 0x0000_0000 {
+        cc1s @ 0: 2,
+        oc1fe @ 2,
+        oc1pe @ 3,
+        oc1m @ 4: 3,
+        oc1ce @ 7,
+        cc2s @ 8: 2,
+        oc2fe @ 10,
+        oc2pe @ 11,
+        oc2m @ 12: 3,
+        oc2ce @ 15,
+        ic1psc @ 2: 2,
+        ic1f @ 4: 4,
+        ic2psc @ 10: 2,
+        ic2f @ 12: 4,
+    }
+}
+
+register! {
+    /// Compare/capture control 2, the `ic` fields apply in input mode
+    CHCTLR2 = 0x0000_0000 {
+        cc3s @ 0: 2,
+        oc3fe @ 2,
+        oc3pe @ 3,
+        oc3m @ 4: 3,
+        oc3ce @ 7,
+        cc4s @ 8: 2,
+        oc4fe @ 10,
+        oc4pe @ 11,
+        oc4m @ 12: 3,
+        oc4ce @ 15,
+        ic3psc @ 2: 2,
+        ic3f @ 4: 4,
+        ic4psc @ 10: 2,
+        ic4f @ 12: 4,
+    }
+}
+
+register! {
+    /// Compare/capture enable
+    CCER = 0x0000_0000 {
+        cc1e @ 0,
+        cc1p @ 1,
+        cc1ne @ 2,
+        cc1np @ 3,
+        cc2e @ 4,
+        cc2p @ 5,
+        cc2ne @ 6,
+        cc2np @ 7,
+        cc3e @ 8,
+        cc3p @ 9,
+        cc3ne @ 10,
+        cc3np @ 11,
+        cc4e @ 12,
+        cc4p @ 13,
+    }
+}
+
+register! {
+    /// Counter
+    CNT = 0x0000_0000 {
+        cnt @ 0: 16,
+    }
+}
+
+register! {
+    /// Prescaler, the counter clock is divided by `psc + 1`
+    PSC = 0x0000_0000 {
+        psc @ 0: 16,
+    }
+}
+
+register! {
+    /// Auto-reload
+    ATRLR = 0x0000_FFFF {
+        atrlr @ 0: 16,
+    }
+}
+
+register! {
+    /// Repetition counter
+    RPTCR = 0x0000_0000 {
+        rptcr @ 0: 8,
+    }
+}
+
+register! {
+    /// Compare/capture value
+    CHCVR = 0x0000_0000 {
+        chcvr @ 0: 16,
+        /// Input level at the time of capture
+        level @ 16,
+    }
+}
+
+register! {
+    /// Break and dead-time
+    BDTR = 0x0000_0000 {
+        /// Dead-time generator setup
+        dtg @ 0: 8,
+        /// Lock configuration
+        lock @ 8: 2,
+        /// Off state selection in idle mode
+        ossi @ 10,
+        /// Off state selection in run mode
+        ossr @ 11,
+        /// Break enable
+        bke @ 12,
+        /// Break polarity
+        bkp @ 13,
+        /// Automatic output enable
+        aoe @ 14,
+        /// Main output enable
+        moe @ 15,
+    }
+}
+
+register! {
+    /// DMA control
+    DMACFGR = 0x0000_0000 {
+        /// DMA base address
+        dba @ 0: 5,
+        /// DMA burst length
+        dbl @ 8: 5,
+    }
+}
+
+register! {
+    /// DMA burst address
+    DMAADR = 0x0000_0000 {
+        dmaadr @ 0: 16,
+    }
+}
@@ -0,0 +1,47 @@
+//! General purpose timer
+//!
+//! Shares its register layout with TIM1, minus the repetition counter,
+//! break and complementary output features.
+
+use super::Reg;
+
+pub use super::tim1::{
+    ATRLR, CCER, CHCTLR1, CHCTLR2, CHCVR, CNT, CTLR1, CTLR2, DMAADR, DMACFGR, DMAINTENR, INTFR,
+    PSC, SMCFGR, SWEVGR,
+};
+
+#[repr(C)]
+pub struct RegisterBlock {
+    /// 0x00: Control 1
+    pub ctlr1: Reg<CTLR1>,
+    /// 0x04: Control 2
+    pub ctlr2: Reg<CTLR2>,
+    /// 0x08: Slave mode control
+    pub smcfgr: Reg<SMCFGR>,
+    /// 0x0C: DMA and interrupt enable
+    pub dmaintenr: Reg<DMAINTENR>,
+    /// 0x10: Interrupt status, write 0 to clear
+    pub intfr: Reg<INTFR>,
+    /// 0x14: Event generation
+    pub swevgr: Reg<SWEVGR>,
+    /// 0x18: Compare/capture control 1
+    pub chctlr1: Reg<CHCTLR1>,
+    /// 0x1C: Compare/capture control 2
+    pub chctlr2: Reg<CHCTLR2>,
+    /// 0x20: Compare/capture enable
+    pub ccer: Reg<CCER>,
+    /// 0x24: Counter
+    pub cnt: Reg<CNT>,
+    /// 0x28: Prescaler
+    pub psc: Reg<PSC>,
+    /// 0x2C: Auto-reload
+    pub atrlr: Reg<ATRLR>,
+    _reserved0: u32,
+    /// 0x34: Compare/capture value for channels 1 to 4
+    pub chcvr: [Reg<CHCVR>; 4],
+    _reserved1: u32,
+    /// 0x48: DMA control
+    pub dmacfgr: Reg<DMACFGR>,
+    /// 0x4C: DMA burst address
+    pub dmaadr: Reg<DMAADR>,
+}
@@ -0,0 +1,145 @@
+//! Universal synchronous/asynchronous receiver transmitter
+
+use super::{register, Reg};
+
+#[repr(C)]
+pub struct RegisterBlock {
+    /// 0x00: Status
+    pub statr: Reg<STATR>,
+    /// 0x04: Data
+    pub datar: Reg<DATAR>,
+    /// 0x08: Baud rate
+    pub brr: Reg<BRR>,
+    /// 0x0C: Control 1
+    pub ctlr1: Reg<CTLR1>,
+    /// 0x10: Control 2
+    pub ctlr2: Reg<CTLR2>,
+    /// 0x14: Control 3
+    pub ctlr3: Reg<CTLR3>,
+    /// 0x18: Guard time and prescaler
+    pub gpr: Reg<GPR>,
+}
+
+register! {
+    /// Status
+    STATR = 0x0000_00C0 {
+        /// Parity error
+        pe @ 0,
+        /// Framing error
+        fe @ 1,
+        /// Noise error
+        ne @ 2,
+        /// Overrun error
+        ore @ 3,
+        /// Idle line detected
+        idle @ 4,
+        /// Receive buffer not empty
+        rxne @ 5,
+        /// Transmission complete
+        tc @ 6,
+        /// Transmit buffer empty
+        txe @ 7,
+        /// LIN break detected
+        lbd @ 8,
+        /// CTS changed
+        cts @ 9,
+    }
+}
+
+register! {
+    /// Data
+    DATAR = 0x0000_0000 {
+        dr @ 0: 9,
+    }
+}
+
+register! {
+    /// Baud rate, the bus clock divided by 16 in 12.4 fixed point
+    BRR = 0x0000_0000 {
+        div_fraction @ 0: 4,
+        div_mantissa @ 4: 12,
+    }
+}
+
+register! {
+    /// Control 1
+    CTLR1 = 0x0000_0000 {
+        /// Send break
+        sbk @ 0,
+        /// Receiver wakeup
+        rwu @ 1,
+        /// Receiver enable
+        re @ 2,
+        /// Transmitter enable
+        te @ 3,
+        idleie @ 4,
+        rxneie @ 5,
+        tcie @ 6,
+        txeie @ 7,
+        peie @ 8,
+        /// Odd parity
+        ps @ 9,
+        /// Parity control enable
+        pce @ 10,
+        /// Wakeup method
+        wake @ 11,
+        /// 9 data bits
+        m @ 12,
+        /// USART enable
+        ue @ 13,
+    }
+}
+
+register! {
+    /// Control 2
+    CTLR2 = 0x0000_0000 {
+        /// Node address
+        add @ 0: 4,
+        /// LIN break detection length
+        lbdl @ 5,
+        lbdie @ 6,
+        /// Last bit clock pulse
+        lbcl @ 8,
+        cpha @ 9,
+        cpol @ 10,
+        /// Clock output enable
+        clken @ 11,
+        /// Stop bits
+        stop @ 12: 2,
+        /// LIN mode enable
+        linen @ 14,
+    }
+}
+
+register! {
+    /// Control 3
+    CTLR3 = 0x0000_0000 {
+        /// Error interrupt enable
+        eie @ 0,
+        /// IrDA mode
+        iren @ 1,
+        /// IrDA low power
+        irlp @ 2,
+        /// Half duplex
+        hdsel @ 3,
+        /// Smartcard NACK
+        nack @ 4,
+        /// Smartcard mode
+        scen @ 5,
+        /// DMA receive
+        dmar @ 6,
+        /// DMA transmit
+        dmat @ 7,
+        rtse @ 8,
+        ctse @ 9,
+        ctsie @ 10,
+    }
+}
+
+register! {
+    /// Guard time and prescaler
+    GPR = 0x0000_0000 {
+        psc @ 0: 8,
+        gt @ 8: 8,
+    }
+}
@@ -0,0 +1,43 @@
+//! Window watchdog
+
+use super::{register, Reg};
+
+#[repr(C)]
+pub struct RegisterBlock {
+    /// 0x00: Control
+    pub ctlr: Reg<CTLR>,
+    /// 0x04: Configuration
+    pub cfgr: Reg<CFGR>,
+    /// 0x08: Status
+    pub statr: Reg<STATR>,
+}
+
+register! {
+    /// Control
+    CTLR = 0x0000_007F {
+        /// Counter, the chip resets when bit 6 clears
+        t @ 0: 7,
+        /// Watchdog enable
+        wdga @ 7,
+    }
+}
+
+register! {
+    /// Configuration
+    CFGR = 0x0000_007F {
+        /// Window value
+        w @ 0: 7,
+        /// Timer base, the bus clock divided by `4096 << wdgtb`
+        wdgtb @ 7: 2,
+        /// Early wakeup interrupt enable
+        ewi @ 9,
+    }
+}
+
+register! {
+    /// Status
+    STATR = 0x0000_0000 {
+        /// Early wakeup interrupt flag
+        ewif @ 0,
+    }
+}
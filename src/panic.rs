@@ -0,0 +1,130 @@
+//! Panic handler
+//!
+//! Prints the panic location and message over USART1, if the firmware set it
+//! up, then signals the panic in the way selected by the crate features:
+//!
+//! - `panic-blink` (default) blinks an error pattern on PC1, or the pin set
+//!   with [`set_blink`]
+//! - `panic-reset` resets the chip
+//!
+//! Disable both to provide a panic handler of your own.
+
+use core::fmt::Write;
+use core::panic::PanicInfo;
+
+use crate::pac;
+use crate::serial::Tx;
+
+#[cfg(all(feature = "panic-blink", feature = "panic-reset"))]
+compile_error!("features `panic-blink` and `panic-reset` are mutually exclusive");
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    crate::interrupt::disable();
+    print(info);
+
+    #[cfg(feature = "panic-reset")]
+    crate::pfic::Pfic::system_reset();
+
+    #[cfg(feature = "panic-blink")]
+    blink::run();
+}
+
+fn print(info: &PanicInfo) {
+    let rcc = unsafe { &*pac::RCC::PTR };
+    let usart = unsafe { &*pac::USART1::PTR };
+
+    // Without a configured transmitter there is nobody listening
+    let ctlr1 = usart.ctlr1.read();
+    if rcc.apb2pcenr.read().usart1en().bit_is_clear()
+        || ctlr1.ue().bit_is_clear()
+        || ctlr1.te().bit_is_clear()
+    {
+        return;
+    }
+
+    // A DMA transfer in flight would interleave with the message
+    usart.ctlr3.modify(|_, w| w.dmat().clear_bit());
+
+    let mut tx = unsafe { Tx::steal() };
+    writeln!(tx, "\r\n{}", info).ok();
+    embedded_io::Write::flush(&mut tx).ok();
+}
+
+#[cfg(feature = "panic-blink")]
+pub use blink::{set_blink, Blink};
+
+#[cfg(feature = "panic-blink")]
+mod blink {
+    use core::cell::Cell;
+
+    use critical_section::Mutex;
+    use embedded_hal::delay::DelayNs;
+
+    use crate::delay::CycleDelay;
+    use crate::gpio::{ErasedPin, Pin};
+    use crate::rcc::Clocks;
+
+    /// Pin and pattern blinked after a panic
+    #[derive(Clone, Copy, Debug)]
+    pub struct Blink {
+        port: char,
+        pin: u8,
+        /// Durations in milliseconds, alternating between on and off
+        pub pattern: &'static [u32],
+    }
+
+    impl Blink {
+        /// Blink `pattern` on `pin`
+        ///
+        /// The pin is only borrowed to prove it exists on the selected chip,
+        /// the panic handler takes it over from whoever owns it then.
+        pub fn new<const P: char, const N: u8, MODE>(
+            _pin: &Pin<P, N, MODE>,
+            pattern: &'static [u32],
+        ) -> Self {
+            Self {
+                port: P,
+                pin: N,
+                pattern,
+            }
+        }
+    }
+
+    impl Default for Blink {
+        /// Three short flashes and a pause on the PC1 LED
+        fn default() -> Self {
+            DEFAULT
+        }
+    }
+
+    const DEFAULT: Blink = Blink {
+        port: 'C',
+        pin: 1,
+        pattern: &[100, 100, 100, 100, 100, 700],
+    };
+
+    static BLINK: Mutex<Cell<Blink>> = Mutex::new(Cell::new(DEFAULT));
+
+    /// Change the pin and pattern used to signal a panic
+    pub fn set_blink(blink: Blink) {
+        critical_section::with(|cs| BLINK.borrow(cs).set(blink));
+    }
+
+    pub(super) fn run() -> ! {
+        let blink = critical_section::with(|cs| BLINK.borrow(cs).get());
+        let mut led = unsafe { ErasedPin::steal(blink.port, blink.pin) };
+        let mut delay = CycleDelay::new(&Clocks::current());
+
+        loop {
+            for (i, &ms) in blink.pattern.iter().enumerate() {
+                if i % 2 == 0 {
+                    led.set_high();
+                } else {
+                    led.set_low();
+                }
+                delay.delay_ms(ms);
+            }
+        }
+    }
+}
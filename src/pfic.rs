@@ -0,0 +1,328 @@
+//! Programmable fast interrupt controller
+//!
+//! The PFIC runs in vectored mode: every interrupt jumps straight to its own
+//! entry in the vector table. Handlers are installed with [`interrupt!`],
+//! and unmasked through [`Pfic`]:
+//!
+//! ```ignore
+//! hello_wch::interrupt!(EXTI7_0, on_button);
+//!
+//! fn on_button() {
+//!     // ...
+//! }
+//!
+//! let pfic = Pfic::new(p.PFIC, pfic::Config::default())?;
+//! unsafe { Pfic::unmask(Interrupt::EXTI7_0) };
+//! ```
+//!
+//! Interrupts without a handler run `DefaultHandler`.
+
+use core::arch::{asm, global_asm};
+
+use crate::pac::{self, Interrupt};
+
+/// Install a function as the handler of an interrupt
+///
+/// The handler is an ordinary function, it is called from a trampoline that
+/// saves the caller-saved registers and returns with `mret`.
+#[macro_export]
+macro_rules! interrupt {
+    ($NAME:ident, $handler:path) => {
+        #[allow(non_snake_case)]
+        #[no_mangle]
+        pub extern "C" fn $NAME() {
+            // Only names from the vector table are accepted
+            let _ = $crate::pac::Interrupt::$NAME;
+            let handler: fn() = $handler;
+            handler()
+        }
+    };
+}
+
+/// Entry of the vector table
+#[doc(hidden)]
+#[repr(C)]
+pub union Vector {
+    handler: unsafe extern "C" fn(),
+    reserved: usize,
+}
+
+macro_rules! vectors {
+    ($($index:literal: $name:ident,)*) => {
+        trampolines!($($name)*);
+
+        #[allow(non_snake_case)]
+        extern "C" {
+            $(
+                #[link_name = concat!("__", stringify!($name), "_trampoline")]
+                fn $name();
+            )*
+        }
+
+        /// Vector table, each entry is the address of a handler
+        #[doc(hidden)]
+        #[used]
+        pub static __VECTOR_TABLE: [Vector; Interrupt::COUNT] = {
+            let mut table = [const { Vector { reserved: 0 } }; Interrupt::COUNT];
+            $(table[$index] = Vector { handler: $name };)*
+            table
+        };
+    };
+}
+
+/// One small stub per interrupt that saves `t0`, loads the handler address
+/// into it and jumps to the shared part
+macro_rules! trampolines {
+    ($($name:ident)*) => {
+        $(
+            global_asm!(concat!(
+                ".section .text.__", stringify!($name), "_trampoline, \"ax\"\n",
+                ".global __", stringify!($name), "_trampoline\n",
+                ".align 2\n",
+                "__", stringify!($name), "_trampoline:\n",
+                "    addi sp, sp, -40\n",
+                "    sw t0, 4(sp)\n",
+                "    la t0, ", stringify!($name), "\n",
+                "    j __interrupt_trampoline\n",
+            ));
+        )*
+    };
+}
+
+// Saves the remaining caller-saved registers of the ilp32e ABI, calls the
+// handler in t0 and returns from the interrupt
+global_asm!(
+    ".section .text.__interrupt_trampoline, \"ax\"",
+    ".global __interrupt_trampoline",
+    ".align 2",
+    "__interrupt_trampoline:",
+    "    sw ra, 0(sp)",
+    "    sw t1, 8(sp)",
+    "    sw t2, 12(sp)",
+    "    sw a0, 16(sp)",
+    "    sw a1, 20(sp)",
+    "    sw a2, 24(sp)",
+    "    sw a3, 28(sp)",
+    "    sw a4, 32(sp)",
+    "    sw a5, 36(sp)",
+    "    jalr ra, t0, 0",
+    "    lw ra, 0(sp)",
+    "    lw t0, 4(sp)",
+    "    lw t1, 8(sp)",
+    "    lw t2, 12(sp)",
+    "    lw a0, 16(sp)",
+    "    lw a1, 20(sp)",
+    "    lw a2, 24(sp)",
+    "    lw a3, 28(sp)",
+    "    lw a4, 32(sp)",
+    "    lw a5, 36(sp)",
+    "    addi sp, sp, 40",
+    "    mret",
+);
+
+vectors! {
+    2: NMI,
+    3: HardFault,
+    12: SysTick,
+    14: Software,
+    16: WWDG,
+    17: PVD,
+    18: FLASH,
+    19: RCC,
+    20: EXTI7_0,
+    21: AWU,
+    22: DMA1_CHANNEL1,
+    23: DMA1_CHANNEL2,
+    24: DMA1_CHANNEL3,
+    25: DMA1_CHANNEL4,
+    26: DMA1_CHANNEL5,
+    27: DMA1_CHANNEL6,
+    28: DMA1_CHANNEL7,
+    29: ADC,
+    30: I2C1_EV,
+    31: I2C1_ER,
+    32: USART1,
+    33: SPI1,
+    34: TIM1_BRK,
+    35: TIM1_UP,
+    36: TIM1_TRG_COM,
+    37: TIM1_CC,
+    38: TIM2,
+}
+
+/// Interrupt system configuration
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// Hardware prologue/epilogue, the core stacks the caller-saved
+    /// registers itself on entry. Handlers installed with [`interrupt!`]
+    /// work either way.
+    pub hpe: bool,
+    /// Allow higher priority interrupts to preempt running handlers, two
+    /// levels deep
+    ///
+    /// Needs `hpe`, as the software trampoline does not save `mepc` and
+    /// `mstatus` before a nested interrupt can overwrite them.
+    pub nesting: bool,
+}
+
+/// Interrupt system configuration errors
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// Nesting was enabled without the hardware prologue
+    NestingWithoutHpe,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            hpe: true,
+            nesting: true,
+        }
+    }
+}
+
+/// Interrupt system configuration CSR
+const INTSYSCR: usize = 0x804;
+
+/// Key that has to accompany writes to `CFGR`
+const KEY3: u32 = 0xBEEF;
+
+/// Highest priority, lower values preempt higher ones
+pub const PRIORITY_HIGHEST: u8 = 0x00;
+/// Lowest and default priority
+pub const PRIORITY_LOWEST: u8 = 0xC0;
+
+/// The interrupt controller
+///
+/// Creating it installs the vector table, which has to happen before any
+/// interrupt is unmasked.
+pub struct Pfic {
+    pfic: pac::PFIC,
+}
+
+impl Pfic {
+    pub fn new(pfic: pac::PFIC, config: Config) -> Result<Self, Error> {
+        let mut pfic = Self { pfic };
+        unsafe {
+            pfic.set_vector_table(__VECTOR_TABLE.as_ptr() as usize);
+        }
+        pfic.configure(config)?;
+        Ok(pfic)
+    }
+
+    /// Change the HPE and nesting configuration
+    pub fn configure(&mut self, config: Config) -> Result<(), Error> {
+        if config.nesting && !config.hpe {
+            return Err(Error::NestingWithoutHpe);
+        }
+        let mask: usize = 0b11;
+        let bits = config.hpe as usize | (config.nesting as usize) << 1;
+        unsafe {
+            asm!("csrc {csr}, {0}", in(reg) mask, csr = const INTSYSCR);
+            asm!("csrs {csr}, {0}", in(reg) bits, csr = const INTSYSCR);
+        }
+        Ok(())
+    }
+
+    /// Move the vector table
+    ///
+    /// # Safety
+    /// `address` has to point to a 4-byte aligned table of handler addresses
+    /// covering every interrupt that can fire
+    pub unsafe fn set_vector_table(&mut self, address: usize) {
+        // Vectored mode, entries are absolute addresses
+        asm!("csrw mtvec, {0}", in(reg) address | 0b11);
+    }
+
+    /// Allow an interrupt to fire
+    ///
+    /// # Safety
+    /// Can break critical sections that rely on the interrupt being masked.
+    /// The vector table has to be installed first, by creating a [`Pfic`].
+    pub unsafe fn unmask(irq: Interrupt) {
+        let n = irq.number();
+        let pfic = &*pac::PFIC::PTR;
+        pfic.ienr[n / 32].write(|w| w.bits(1 << (n % 32)));
+    }
+
+    /// Prevent an interrupt from firing
+    pub fn mask(irq: Interrupt) {
+        let n = irq.number();
+        let pfic = unsafe { &*pac::PFIC::PTR };
+        pfic.irer[n / 32].write(|w| unsafe { w.bits(1 << (n % 32)) });
+    }
+
+    pub fn is_enabled(irq: Interrupt) -> bool {
+        let n = irq.number();
+        let pfic = unsafe { &*pac::PFIC::PTR };
+        pfic.isr[n / 32].read().bits() & (1 << (n % 32)) != 0
+    }
+
+    /// Request an interrupt from software
+    pub fn pend(irq: Interrupt) {
+        let n = irq.number();
+        let pfic = unsafe { &*pac::PFIC::PTR };
+        pfic.ipsr[n / 32].write(|w| unsafe { w.bits(1 << (n % 32)) });
+    }
+
+    /// Withdraw a pending interrupt
+    pub fn unpend(irq: Interrupt) {
+        let n = irq.number();
+        let pfic = unsafe { &*pac::PFIC::PTR };
+        pfic.iprr[n / 32].write(|w| unsafe { w.bits(1 << (n % 32)) });
+    }
+
+    pub fn is_pending(irq: Interrupt) -> bool {
+        let n = irq.number();
+        let pfic = unsafe { &*pac::PFIC::PTR };
+        pfic.ipr[n / 32].read().bits() & (1 << (n % 32)) != 0
+    }
+
+    /// Whether the handler of the interrupt is currently running
+    pub fn is_active(irq: Interrupt) -> bool {
+        let n = irq.number();
+        let pfic = unsafe { &*pac::PFIC::PTR };
+        pfic.iactr[n / 32].read().bits() & (1 << (n % 32)) != 0
+    }
+
+    /// Set the priority of an interrupt
+    ///
+    /// Only bits 7..6 are implemented. With nesting enabled bit 7 selects the
+    /// preemption level and bit 6 the priority within it, lower values win.
+    pub fn set_priority(&mut self, irq: Interrupt, priority: u8) {
+        let n = irq.number();
+        // Priorities are byte addressable, so no read-modify-write is needed
+        unsafe {
+            let iprior = self.pfic.iprior.as_ptr() as *mut u8;
+            iprior.add(n).write_volatile(priority);
+        }
+    }
+
+    pub fn get_priority(irq: Interrupt) -> u8 {
+        let n = irq.number();
+        let pfic = unsafe { &*pac::PFIC::PTR };
+        unsafe {
+            let iprior = pfic.iprior.as_ptr() as *const u8;
+            iprior.add(n).read_volatile()
+        }
+    }
+
+    /// Only interrupts with a priority value below `threshold` can fire,
+    /// zero disables the threshold
+    pub fn set_threshold(&mut self, threshold: u8) {
+        self.pfic
+            .ithresdr
+            .write(|w| w.threshold().bits(threshold as u32));
+    }
+
+    /// Reset the whole chip
+    pub fn system_reset() -> ! {
+        let pfic = unsafe { &*pac::PFIC::PTR };
+        pfic.cfgr
+            .write(|w| w.keycode().bits(KEY3).resetsys().set_bit());
+        loop {
+            riscv::asm::nop();
+        }
+    }
+}
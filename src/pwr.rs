@@ -0,0 +1,167 @@
+//! Power control and low-power modes
+//!
+//! In sleep only the core stops, and any interrupt or event wakes it. In
+//! standby all clocks stop except LSI, and only EXTI lines wake the chip,
+//! which then continues where it left off. The auto-wakeup timer (AWU) is
+//! one of those lines, so a blink can sleep through most of its period:
+//!
+//! ```ignore
+//! let mut exti = Exti::new(p.EXTI);
+//! let mut pwr = Pwr::new(p.PWR);
+//! // 128 kHz / 10240 = 12.5 Hz, 6 ticks for 480 ms
+//! pwr.enable_awu(&mut exti, AwuPrescaler::Div10240, 6);
+//! loop {
+//!     led.toggle();
+//!     pwr.standby(Entry::Event);
+//! }
+//! ```
+//!
+//! Waking from standby switches the system clock back to HSI, so the
+//! configuration of the last [`RccExt::freeze`](crate::rcc::RccExt::freeze)
+//! is applied again before [`Pwr::standby`] returns.
+
+use core::arch::asm;
+
+use crate::exti::{self, Edge, Exti};
+use crate::pac::{self, PWR};
+use crate::rcc::{self, Enable};
+
+/// How the core waits in a low-power mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Entry {
+    /// `wfi`, woken by an interrupt that then runs its handler
+    Interrupt,
+    /// `wfe`, woken by an event or by an interrupt without running its
+    /// handler
+    Event,
+}
+
+/// Division of LSI for the auto-wakeup counter
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AwuPrescaler {
+    Div1 = 0x0,
+    Div2 = 0x2,
+    Div4 = 0x3,
+    Div8 = 0x4,
+    Div16 = 0x5,
+    Div32 = 0x6,
+    Div64 = 0x7,
+    Div128 = 0x8,
+    Div256 = 0x9,
+    Div512 = 0xA,
+    Div1024 = 0xB,
+    Div2048 = 0xC,
+    Div4096 = 0xD,
+    Div10240 = 0xE,
+    Div61440 = 0xF,
+}
+
+impl AwuPrescaler {
+    pub fn divisor(self) -> u32 {
+        match self {
+            AwuPrescaler::Div1 => 1,
+            AwuPrescaler::Div10240 => 10240,
+            AwuPrescaler::Div61440 => 61440,
+            _ => 1 << (self as u32 - 1),
+        }
+    }
+}
+
+/// Power control
+pub struct Pwr {
+    pwr: PWR,
+}
+
+impl Pwr {
+    pub fn new(pwr: PWR) -> Self {
+        PWR::enable();
+        Self { pwr }
+    }
+
+    pub fn release(self) -> PWR {
+        self.pwr
+    }
+
+    /// Wake the chip every `window` ticks of LSI divided by `prescaler`
+    ///
+    /// The wakeup is a rising edge on EXTI line [`exti::AWU`], set up here as
+    /// an event. Enable its interrupt on `exti` as well to have a handler run
+    /// on every wakeup.
+    pub fn enable_awu(&mut self, exti: &mut Exti, prescaler: AwuPrescaler, window: u8) {
+        rcc::enable_lsi();
+        self.enable_wakeup_line(exti, exti::AWU, Edge::Rising);
+
+        self.pwr.awupsc.write(|w| w.awupsc().bits(prescaler as u32));
+        self.pwr
+            .awuwr
+            .write(|w| w.awuwr().bits(window.clamp(1, 0x3F) as u32));
+        self.pwr.awucsr.write(|w| w.awuen().set_bit());
+    }
+
+    pub fn disable_awu(&mut self, exti: &mut Exti) {
+        self.pwr.awucsr.reset();
+        exti.disable_event(exti::AWU);
+    }
+
+    /// Let `line` wake the chip from standby and `wfe` on `edge`
+    ///
+    /// For pin lines the port has to be selected first, see
+    /// [`ExtiPin::make_interrupt_source`](crate::exti::ExtiPin::make_interrupt_source).
+    /// Panics unless `line` is below [`exti::LINES`].
+    pub fn enable_wakeup_line(&mut self, exti: &mut Exti, line: u8, edge: Edge) {
+        assert!(line < exti::LINES, "EXTI line {line} does not exist");
+        exti.set_edge(line, edge);
+        exti.enable_event(line);
+    }
+
+    pub fn disable_wakeup_line(&mut self, exti: &mut Exti, line: u8) {
+        exti.disable_event(line);
+    }
+
+    /// Stop the core until it is woken, leaving the clocks running
+    pub fn sleep(&mut self, entry: Entry) {
+        set_sleep_deep(false);
+        wait(entry);
+    }
+
+    /// Stop all clocks but LSI until an EXTI line wakes the chip, then
+    /// restore the clock configuration
+    pub fn standby(&mut self, entry: Entry) {
+        self.pwr.ctlr.modify(|_, w| w.pdds().set_bit());
+        set_sleep_deep(true);
+        wait(entry);
+        set_sleep_deep(false);
+        self.pwr.ctlr.modify(|_, w| w.pdds().clear_bit());
+
+        rcc::restore();
+    }
+}
+
+fn set_sleep_deep(deep: bool) {
+    let pfic = unsafe { &*pac::PFIC::PTR };
+    critical_section::with(|_| pfic.sctlr.modify(|_, w| w.sleepdeep().bit(deep)));
+}
+
+/// Execute `wfi`, or `wfe` which the core implements as `wfi` with
+/// WFITOWFE set
+fn wait(entry: Entry) {
+    match entry {
+        Entry::Interrupt => unsafe { asm!("wfi") },
+        Entry::Event => {
+            let pfic = unsafe { &*pac::PFIC::PTR };
+            critical_section::with(|_| {
+                // As in the vendor SDK, raise an event so the first wait
+                // returns at once and clears any stale one, then wait again
+                let previous = pfic.sctlr.read().setevent().bit();
+                pfic.sctlr
+                    .modify(|_, w| w.wfitowfe().set_bit().setevent().set_bit());
+                pfic.sctlr.modify(|_, w| w.setevent().bit(previous));
+            });
+            unsafe {
+                asm!("wfi");
+                asm!("wfi");
+            }
+            critical_section::with(|_| pfic.sctlr.modify(|_, w| w.wfitowfe().clear_bit()));
+        }
+    }
+}
@@ -0,0 +1,440 @@
+//! Reset and clock control
+//!
+//! The clock tree is configured once and then frozen into [`Clocks`], which
+//! drivers use to derive their baud rates and prescalers:
+//!
+//! ```ignore
+//! let clocks = p.RCC.freeze(rcc::Config::hsi().pll()).unwrap();
+//! assert_eq!(clocks.hclk(), 48_000_000);
+//! ```
+//!
+//! A configuration the chip can not run is refused by
+//! [`RccExt::freeze`]. As [`Config`] is built with `const fn`s, it can also
+//! be checked at compile time:
+//!
+//! ```ignore
+//! const CLOCKS: rcc::Config = rcc::Config::hse(8_000_000).pll();
+//! const _: () = assert!(CLOCKS.validate().is_ok());
+//! ```
+
+use core::cell::Cell;
+
+use critical_section::Mutex;
+
+use crate::pac::{self, RCC};
+
+/// Frequency of the internal RC oscillator
+pub const HSI_FREQ: u32 = 24_000_000;
+
+/// Highest supported system clock
+pub const SYSCLK_MAX: u32 = 48_000_000;
+
+/// Range of external clocks the oscillator accepts
+pub const HSE_MIN: u32 = 4_000_000;
+pub const HSE_MAX: u32 = 25_000_000;
+
+/// Reasons a clock configuration can not be applied
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ClockError {
+    /// The external clock is outside [`HSE_MIN`] to [`HSE_MAX`]
+    HseOutOfRange,
+    /// The system clock would be above [`SYSCLK_MAX`]
+    SysclkTooHigh,
+    /// The flash latency is too short for the system clock
+    LatencyTooLow,
+}
+
+/// System clock source
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    /// Internal 24 MHz RC oscillator
+    Hsi,
+    /// External oscillator on PA1 and PA2, from 4 to 25 MHz
+    Hse { freq: u32, mode: HseMode },
+}
+
+/// How the external clock is provided
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HseMode {
+    /// Crystal or resonator between PA1 and PA2
+    Crystal,
+    /// External clock signal on PA1
+    Bypass,
+}
+
+/// AHB prescaler, dividing the system clock down to the core and bus clock
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AhbDiv {
+    Div1 = 0b0000,
+    Div2 = 0b0001,
+    Div3 = 0b0010,
+    Div4 = 0b0011,
+    Div5 = 0b0100,
+    Div6 = 0b0101,
+    Div7 = 0b0110,
+    Div8 = 0b0111,
+    Div16 = 0b1011,
+    Div32 = 0b1100,
+    Div64 = 0b1101,
+    Div128 = 0b1110,
+    Div256 = 0b1111,
+}
+
+impl AhbDiv {
+    pub fn divisor(self) -> u32 {
+        match self {
+            AhbDiv::Div1 => 1,
+            AhbDiv::Div2 => 2,
+            AhbDiv::Div3 => 3,
+            AhbDiv::Div4 => 4,
+            AhbDiv::Div5 => 5,
+            AhbDiv::Div6 => 6,
+            AhbDiv::Div7 => 7,
+            AhbDiv::Div8 => 8,
+            AhbDiv::Div16 => 16,
+            AhbDiv::Div32 => 32,
+            AhbDiv::Div64 => 64,
+            AhbDiv::Div128 => 128,
+            AhbDiv::Div256 => 256,
+        }
+    }
+}
+
+/// Flash wait states
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Latency {
+    /// System clock up to 24 MHz
+    Ws0 = 0,
+    /// System clock up to 48 MHz
+    Ws1 = 1,
+}
+
+impl Latency {
+    /// Fewest wait states that work at `sysclk`
+    pub const fn for_sysclk(sysclk: u32) -> Self {
+        if sysclk <= 24_000_000 {
+            Latency::Ws0
+        } else {
+            Latency::Ws1
+        }
+    }
+}
+
+/// Clock tree configuration
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    source: Source,
+    pll: bool,
+    ahb_div: AhbDiv,
+    latency: Option<Latency>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::hsi()
+    }
+}
+
+impl Config {
+    /// Run from the internal 24 MHz oscillator
+    pub const fn hsi() -> Self {
+        Self {
+            source: Source::Hsi,
+            pll: false,
+            ahb_div: AhbDiv::Div1,
+            latency: None,
+        }
+    }
+
+    /// Run from an external crystal of `freq` Hz
+    pub const fn hse(freq: u32) -> Self {
+        Self {
+            source: Source::Hse {
+                freq,
+                mode: HseMode::Crystal,
+            },
+            ..Self::hsi()
+        }
+    }
+
+    /// Run from an external clock signal of `freq` Hz
+    pub const fn hse_bypass(freq: u32) -> Self {
+        Self {
+            source: Source::Hse {
+                freq,
+                mode: HseMode::Bypass,
+            },
+            ..Self::hsi()
+        }
+    }
+
+    /// Double the source clock with the PLL
+    pub const fn pll(mut self) -> Self {
+        self.pll = true;
+        self
+    }
+
+    /// Divide the system clock before feeding it to the core and buses
+    pub const fn ahb_div(mut self, div: AhbDiv) -> Self {
+        self.ahb_div = div;
+        self
+    }
+
+    /// Override the flash wait states, by default the fewest that work
+    pub const fn flash_latency(mut self, latency: Latency) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+
+    /// Check that the chip can run this configuration
+    pub const fn validate(&self) -> Result<(), ClockError> {
+        if let Source::Hse { freq, .. } = self.source {
+            if freq < HSE_MIN || freq > HSE_MAX {
+                return Err(ClockError::HseOutOfRange);
+            }
+        }
+        let sysclk = self.sysclk();
+        if sysclk > SYSCLK_MAX {
+            return Err(ClockError::SysclkTooHigh);
+        }
+        if let Some(latency) = self.latency {
+            if (latency as u32) < Latency::for_sysclk(sysclk) as u32 {
+                return Err(ClockError::LatencyTooLow);
+            }
+        }
+        Ok(())
+    }
+
+    const fn source_freq(&self) -> u32 {
+        match self.source {
+            Source::Hsi => HSI_FREQ,
+            Source::Hse { freq, .. } => freq,
+        }
+    }
+
+    const fn sysclk(&self) -> u32 {
+        self.source_freq() * if self.pll { 2 } else { 1 }
+    }
+}
+
+/// Clocks applied by the last [`RccExt::freeze`], starting with the reset
+/// configuration of HSI divided by 3
+static CURRENT: Mutex<Cell<Clocks>> = Mutex::new(Cell::new(Clocks {
+    sysclk: HSI_FREQ,
+    hclk: HSI_FREQ / 3,
+}));
+
+/// Frozen clock frequencies, in Hz
+///
+/// Can only be created by configuring the clock tree, so holding one
+/// guarantees the frequencies match the hardware.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Clocks {
+    sysclk: u32,
+    hclk: u32,
+}
+
+impl Clocks {
+    /// System clock
+    pub fn sysclk(&self) -> u32 {
+        self.sysclk
+    }
+
+    /// Core and AHB clock
+    pub fn hclk(&self) -> u32 {
+        self.hclk
+    }
+
+    /// Peripheral bus clock, the same as HCLK on this chip
+    pub fn pclk(&self) -> u32 {
+        self.hclk
+    }
+
+    /// Frequencies the hardware currently runs at, for code that has no
+    /// `Clocks` passed to it
+    #[cfg(feature = "panic-blink")]
+    pub(crate) fn current() -> Self {
+        critical_section::with(|cs| CURRENT.borrow(cs).get())
+    }
+}
+
+/// Extension trait to configure the clock tree
+pub trait RccExt {
+    /// Apply the configuration, blocking until the clocks are stable
+    ///
+    /// Fails without touching the hardware if the configuration is invalid.
+    fn freeze(self, config: Config) -> Result<Clocks, ClockError>;
+}
+
+impl RccExt for RCC {
+    fn freeze(self, config: Config) -> Result<Clocks, ClockError> {
+        config.validate()?;
+        let clocks = apply(config);
+        critical_section::with(|cs| FROZEN.borrow(cs).set(Some(config)));
+        Ok(clocks)
+    }
+}
+
+/// Configuration of the last [`RccExt::freeze`]
+static FROZEN: Mutex<Cell<Option<Config>>> = Mutex::new(Cell::new(None));
+
+/// Set up the clock tree again as it was frozen, after waking from standby
+/// switched it back to HSI
+pub(crate) fn restore() {
+    if let Some(config) = critical_section::with(|cs| FROZEN.borrow(cs).get()) {
+        apply(config);
+    }
+}
+
+/// Turn on the 128 kHz LSI oscillator, for the watchdog and auto-wakeup
+pub(crate) fn enable_lsi() {
+    let rcc = unsafe { &*RCC::PTR };
+    critical_section::with(|_| rcc.rstsckr.modify(|_, w| w.lsion().set_bit()));
+    while rcc.rstsckr.read().lsirdy().bit_is_clear() {}
+}
+
+/// Set up a validated configuration
+fn apply(config: Config) -> Clocks {
+    let rcc = unsafe { &*RCC::PTR };
+    let sysclk = config.sysclk();
+    let latency = config.latency.unwrap_or(Latency::for_sysclk(sysclk));
+
+    // Run from HSI while the rest of the tree is reconfigured
+    rcc.ctlr.modify(|_, w| w.hsion().set_bit());
+    while rcc.ctlr.read().hsirdy().bit_is_clear() {}
+    rcc.cfgr0.modify(|_, w| w.sw().bits(0b00));
+    while rcc.cfgr0.read().sws().bits() != 0b00 {}
+
+    // At 24 MHz any latency works, set the final one before speeding up
+    let flash = unsafe { &*pac::FLASH::PTR };
+    flash.actlr.modify(|_, w| w.latency().bits(latency as u32));
+
+    rcc.ctlr.modify(|_, w| w.pllon().clear_bit());
+    if let Source::Hse { mode, .. } = config.source {
+        rcc.ctlr.modify(|_, w| w.hseon().clear_bit());
+        rcc.ctlr
+            .modify(|_, w| w.hsebyp().bit(mode == HseMode::Bypass));
+        rcc.ctlr.modify(|_, w| w.hseon().set_bit());
+        while rcc.ctlr.read().hserdy().bit_is_clear() {}
+    }
+
+    rcc.cfgr0
+        .modify(|_, w| w.hpre().bits(config.ahb_div as u32));
+
+    let sw = if config.pll {
+        let hse = matches!(config.source, Source::Hse { .. });
+        rcc.cfgr0.modify(|_, w| w.pllsrc().bit(hse));
+        rcc.ctlr.modify(|_, w| w.pllon().set_bit());
+        while rcc.ctlr.read().pllrdy().bit_is_clear() {}
+        0b10
+    } else if config.source == Source::Hsi {
+        0b00
+    } else {
+        0b01
+    };
+    rcc.cfgr0.modify(|_, w| w.sw().bits(sw));
+    while rcc.cfgr0.read().sws().bits() != sw {}
+
+    if config.source == Source::Hsi {
+        rcc.ctlr.modify(|_, w| w.hseon().clear_bit());
+    }
+
+    let clocks = Clocks {
+        sysclk,
+        hclk: sysclk / config.ahb_div.divisor(),
+    };
+    critical_section::with(|cs| CURRENT.borrow(cs).set(clocks));
+    clocks
+}
+
+/// What caused the last reset
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResetCause {
+    /// Power was applied
+    PowerOn,
+    /// The NRST pin was pulled low
+    Pin,
+    /// Software requested it through PFIC
+    Software,
+    IndependentWatchdog,
+    WindowWatchdog,
+    /// Entering standby or stop without it being enabled
+    LowPower,
+    /// The flags were already cleared
+    Unknown,
+}
+
+/// Read and clear the reset flags
+///
+/// The flags add up over resets, so this only gives the right answer the
+/// first time it is called after one. Power-on also sets the pin flag, and
+/// watchdogs reset through the pin, so the most specific flag is reported.
+pub fn reset_cause() -> ResetCause {
+    let rcc = unsafe { &*RCC::PTR };
+    let flags = rcc.rstsckr.read();
+    let cause = if flags.lpwrrstf().bit_is_set() {
+        ResetCause::LowPower
+    } else if flags.iwdgrstf().bit_is_set() {
+        ResetCause::IndependentWatchdog
+    } else if flags.wwdgrstf().bit_is_set() {
+        ResetCause::WindowWatchdog
+    } else if flags.sftrstf().bit_is_set() {
+        ResetCause::Software
+    } else if flags.porrstf().bit_is_set() {
+        ResetCause::PowerOn
+    } else if flags.pinrstf().bit_is_set() {
+        ResetCause::Pin
+    } else {
+        ResetCause::Unknown
+    };
+
+    critical_section::with(|_| rcc.rstsckr.modify(|_, w| w.rmvf().set_bit()));
+    cause
+}
+
+/// Peripherals with a clock enable and reset bit in RCC
+pub(crate) trait Enable {
+    /// Turn on the clock, keeping the current configuration
+    fn enable();
+
+    /// Turn on the clock and reset the peripheral
+    fn enable_and_reset();
+}
+
+macro_rules! bus {
+    ($($PER:ident: $enr:ident.$en:ident, $rstr:ident.$rst:ident;)*) => {
+        $(
+            impl Enable for pac::$PER {
+                fn enable() {
+                    let rcc = unsafe { &*RCC::PTR };
+                    critical_section::with(|_| rcc.$enr.modify(|_, w| w.$en().set_bit()));
+                }
+
+                fn enable_and_reset() {
+                    let rcc = unsafe { &*RCC::PTR };
+                    critical_section::with(|_| {
+                        rcc.$enr.modify(|_, w| w.$en().set_bit());
+                        rcc.$rstr.modify(|_, w| w.$rst().set_bit());
+                        rcc.$rstr.modify(|_, w| w.$rst().clear_bit());
+                    });
+                }
+            }
+        )*
+    };
+}
+
+bus! {
+    AFIO: apb2pcenr.afioen, apb2prstr.afiorst;
+    GPIOA: apb2pcenr.iopaen, apb2prstr.ioparst;
+    GPIOC: apb2pcenr.iopcen, apb2prstr.iopcrst;
+    GPIOD: apb2pcenr.iopden, apb2prstr.iopdrst;
+    ADC1: apb2pcenr.adc1en, apb2prstr.adc1rst;
+    TIM1: apb2pcenr.tim1en, apb2prstr.tim1rst;
+    SPI1: apb2pcenr.spi1en, apb2prstr.spi1rst;
+    USART1: apb2pcenr.usart1en, apb2prstr.usart1rst;
+    TIM2: apb1pcenr.tim2en, apb1prstr.tim2rst;
+    WWDG: apb1pcenr.wwdgen, apb1prstr.wwdgrst;
+    I2C1: apb1pcenr.i2c1en, apb1prstr.i2c1rst;
+    PWR: apb1pcenr.pwren, apb1prstr.pwrrst;
+}
@@ -0,0 +1,653 @@
+//! USART1 serial port
+//!
+//! Supports 8 or 9 data bits, parity and all stop bit lengths on any of the
+//! four pin mappings. Data can be moved with blocking calls, through ring
+//! buffers serviced by the USART1 interrupt, or by DMA:
+//!
+//! ```ignore
+//! let tx = gpiod.pd5.into_alternate_push_pull();
+//! let rx = gpiod.pd6.into_floating_input();
+//! let mut serial = Serial::new(p.USART1, (tx, rx), serial::Config::default(), &clocks);
+//! writeln!(serial, "hello from {}", "USART1").ok();
+//! ```
+
+use core::cell::{Cell, RefCell};
+use core::fmt;
+
+use critical_section::Mutex;
+use embedded_io::{ErrorKind, ErrorType, Read, ReadReady, Write, WriteReady};
+
+use crate::afio::{self, usart1, Remap};
+use crate::dma::{self, CircBuffer, Direction, ReadBuffer, Transfer, WriteBuffer};
+use crate::pac::{self, usart::RegisterBlock};
+use crate::rcc::{Clocks, Enable};
+
+/// Pins that can be used for USART1, as `(TX, RX)` of the same
+/// [`usart1`] pin set `R`
+pub trait Pins<R> {}
+
+impl<R, TX: usart1::Tx<R>, RX: usart1::Rx<R>> Pins<R> for (TX, RX) {}
+
+/// Number of data bits in a frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WordLength {
+    DataBits8,
+    /// Can not be combined with parity
+    DataBits9,
+}
+
+/// Parity bit sent after the data bits
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Parity {
+    None,
+    Even,
+    Odd,
+}
+
+/// Length of the stop condition
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopBits {
+    Stop1 = 0b00,
+    Stop0p5 = 0b01,
+    Stop2 = 0b10,
+    Stop1p5 = 0b11,
+}
+
+/// Serial configuration
+#[derive(Clone, Copy, Debug)]
+pub struct Config {
+    pub baudrate: u32,
+    pub word_length: WordLength,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+}
+
+impl Default for Config {
+    /// 115200 baud 8N1
+    fn default() -> Self {
+        Self {
+            baudrate: 115_200,
+            word_length: WordLength::DataBits8,
+            parity: Parity::None,
+            stop_bits: StopBits::Stop1,
+        }
+    }
+}
+
+/// Receive errors
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// A byte was received before the previous one was read
+    Overrun,
+    /// No stop bit was detected
+    Framing,
+    /// Noise was detected on the line
+    Noise,
+    /// The parity bit did not match
+    Parity,
+}
+
+impl embedded_io::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            // Received data was lost, by the hardware or a full queue
+            Error::Overrun => ErrorKind::Other,
+            _ => ErrorKind::InvalidData,
+        }
+    }
+}
+
+fn regs() -> &'static RegisterBlock {
+    unsafe { &*pac::USART1::PTR }
+}
+
+/// USART1 in asynchronous mode
+pub struct Serial<PINS> {
+    usart: pac::USART1,
+    pins: PINS,
+    tx: Tx,
+    rx: Rx,
+}
+
+impl<PINS> Serial<PINS> {
+    /// Set up USART1 on the pin set the pins belong to
+    pub fn new<R: Remap>(usart: pac::USART1, pins: PINS, config: Config, clocks: &Clocks) -> Self
+    where
+        PINS: Pins<R>,
+    {
+        assert!(config.word_length == WordLength::DataBits8 || config.parity == Parity::None);
+
+        pac::USART1::enable_and_reset();
+
+        afio::remap::<R>();
+
+        // 12.4 fixed point divider, rounded to nearest
+        let brr = (clocks.pclk() + config.baudrate / 2) / config.baudrate;
+        usart.brr.write(|w| unsafe { w.bits(brr) });
+        usart
+            .ctlr2
+            .write(|w| w.stop().bits(config.stop_bits as u32));
+        usart.ctlr1.write(|w| {
+            // The parity bit takes the place of the most significant data bit
+            w.m()
+                .bit(config.word_length == WordLength::DataBits9 || config.parity != Parity::None)
+                .pce()
+                .bit(config.parity != Parity::None)
+                .ps()
+                .bit(config.parity == Parity::Odd)
+                .ue()
+                .set_bit()
+                .te()
+                .set_bit()
+                .re()
+                .set_bit()
+        });
+
+        Self {
+            usart,
+            pins,
+            tx: Tx { _0: () },
+            rx: Rx { _0: () },
+        }
+    }
+
+    /// Give back the peripheral and pins
+    pub fn release(self) -> (pac::USART1, PINS) {
+        self.usart.ctlr1.reset();
+        (self.usart, self.pins)
+    }
+
+    /// Separate the transmitter and receiver
+    pub fn split(self) -> (Tx, Rx) {
+        (self.tx, self.rx)
+    }
+
+    /// Move data through ring buffers serviced by the USART1 interrupt
+    ///
+    /// [`Buffers::on_interrupt`] has to be called from the USART1 handler,
+    /// and the interrupt unmasked in the PFIC.
+    pub fn into_buffered<const RX: usize, const TX: usize>(
+        self,
+        buffers: &'static Buffers<RX, TX>,
+    ) -> Buffered<PINS, RX, TX> {
+        critical_section::with(|_| self.usart.ctlr1.modify(|_, w| w.rxneie().set_bit()));
+        Buffered {
+            serial: self,
+            buffers,
+        }
+    }
+
+    /// Receive a byte if one is available
+    pub fn read_byte(&mut self) -> nb::Result<u8, Error> {
+        self.rx.read_byte()
+    }
+
+    /// Receive a 9-bit word if one is available
+    pub fn read_word(&mut self) -> nb::Result<u16, Error> {
+        self.rx.read_word()
+    }
+
+    /// Queue a byte for transmission if there is room
+    pub fn write_byte(&mut self, byte: u8) -> nb::Result<(), Error> {
+        self.tx.write_byte(byte)
+    }
+
+    /// Queue a 9-bit word for transmission if there is room
+    pub fn write_word(&mut self, word: u16) -> nb::Result<(), Error> {
+        self.tx.write_word(word)
+    }
+
+    /// Whether all queued bytes have left the shift register
+    pub fn is_tx_complete(&self) -> bool {
+        self.tx.is_complete()
+    }
+}
+
+/// Transmitting half of USART1
+pub struct Tx {
+    _0: (),
+}
+
+impl Tx {
+    /// Create a transmitter regardless of who owns USART1
+    ///
+    /// # Safety
+    /// Nothing else may transmit at the same time
+    #[cfg(any(feature = "panic-blink", feature = "panic-reset"))]
+    pub(crate) unsafe fn steal() -> Self {
+        Self { _0: () }
+    }
+
+    /// Queue a byte for transmission if there is room
+    pub fn write_byte(&mut self, byte: u8) -> nb::Result<(), Error> {
+        self.write_word(byte as u16)
+    }
+
+    /// Queue a 9-bit word for transmission if there is room
+    pub fn write_word(&mut self, word: u16) -> nb::Result<(), Error> {
+        let usart = regs();
+        if usart.statr.read().txe().bit_is_set() {
+            usart.datar.write(|w| w.dr().bits(word as u32));
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// Whether all queued bytes have left the shift register
+    pub fn is_complete(&self) -> bool {
+        regs().statr.read().tc().bit_is_set()
+    }
+
+    /// Send a buffer with DMA
+    pub fn write_dma<B>(self, mut channel: dma::C4, buffer: B) -> Transfer<4, B, Tx>
+    where
+        B: ReadBuffer<Word = u8>,
+    {
+        let usart = regs();
+        let (ptr, len) = buffer.read_buffer();
+        channel.configure(
+            usart.datar.as_ptr() as u32,
+            ptr,
+            len,
+            Direction::MemoryToPeripheral,
+        );
+        critical_section::with(|_| usart.ctlr3.modify(|_, w| w.dmat().set_bit()));
+        Transfer::start(channel, buffer, self)
+    }
+}
+
+/// Receiving half of USART1
+pub struct Rx {
+    _0: (),
+}
+
+impl Rx {
+    /// Receive a byte if one is available
+    pub fn read_byte(&mut self) -> nb::Result<u8, Error> {
+        self.read_word().map(|word| word as u8)
+    }
+
+    /// Receive a 9-bit word if one is available
+    ///
+    /// With 8 data bits and parity enabled the top bit is the parity bit.
+    pub fn read_word(&mut self) -> nb::Result<u16, Error> {
+        let usart = regs();
+        let statr = usart.statr.read();
+
+        // Errors are cleared by reading STATR followed by DATAR
+        let error = if statr.pe().bit_is_set() {
+            Some(Error::Parity)
+        } else if statr.fe().bit_is_set() {
+            Some(Error::Framing)
+        } else if statr.ne().bit_is_set() {
+            Some(Error::Noise)
+        } else if statr.ore().bit_is_set() {
+            Some(Error::Overrun)
+        } else {
+            None
+        };
+
+        if let Some(error) = error {
+            usart.datar.read();
+            Err(nb::Error::Other(error))
+        } else if statr.rxne().bit_is_set() {
+            Ok(usart.datar.read().dr().bits() as u16)
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// Whether a byte is waiting to be read
+    pub fn is_ready(&self) -> bool {
+        regs().statr.read().rxne().bit_is_set()
+    }
+
+    /// Fill a buffer with DMA
+    pub fn read_dma<B>(self, mut channel: dma::C5, mut buffer: B) -> Transfer<5, B, Rx>
+    where
+        B: WriteBuffer<Word = u8>,
+    {
+        let usart = regs();
+        let (ptr, len) = buffer.write_buffer();
+        channel.configure(
+            usart.datar.as_ptr() as u32,
+            ptr,
+            len,
+            Direction::PeripheralToMemory,
+        );
+        critical_section::with(|_| usart.ctlr3.modify(|_, w| w.dmar().set_bit()));
+        Transfer::start(channel, buffer, self)
+    }
+
+    /// Keep receiving into the two halves of a buffer with DMA
+    pub fn read_circular<const M: usize>(
+        self,
+        mut channel: dma::C5,
+        buffer: &'static mut [[u8; M]; 2],
+    ) -> CircBuffer<5, [u8; M], Rx> {
+        let usart = regs();
+        channel.configure(
+            usart.datar.as_ptr() as u32,
+            buffer.as_ptr() as *const u8,
+            2 * M,
+            Direction::PeripheralToMemory,
+        );
+        critical_section::with(|_| usart.ctlr3.modify(|_, w| w.dmar().set_bit()));
+        CircBuffer::start(channel, buffer, self)
+    }
+}
+
+/// Fixed size byte queue
+struct Queue<const N: usize> {
+    buf: [u8; N],
+    head: usize,
+    len: usize,
+}
+
+impl<const N: usize> Queue<N> {
+    const fn new() -> Self {
+        Self {
+            buf: [0; N],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Append a byte, returning false if the queue is full
+    fn push(&mut self, byte: u8) -> bool {
+        if self.len == N {
+            return false;
+        }
+        self.buf[(self.head + self.len) % N] = byte;
+        self.len += 1;
+        true
+    }
+
+    fn pop(&mut self) -> Option<u8> {
+        if self.len == 0 {
+            return None;
+        }
+        let byte = self.buf[self.head];
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        Some(byte)
+    }
+}
+
+/// Receive and transmit queues shared with the USART1 interrupt
+///
+/// ```ignore
+/// static BUFFERS: serial::Buffers<64, 64> = serial::Buffers::new();
+///
+/// hello_wch::interrupt!(USART1, on_usart);
+///
+/// fn on_usart() {
+///     BUFFERS.on_interrupt();
+/// }
+/// ```
+pub struct Buffers<const RX: usize, const TX: usize> {
+    rx: Mutex<RefCell<Queue<RX>>>,
+    tx: Mutex<RefCell<Queue<TX>>>,
+    error: Mutex<Cell<Option<Error>>>,
+}
+
+impl<const RX: usize, const TX: usize> Default for Buffers<RX, TX> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const RX: usize, const TX: usize> Buffers<RX, TX> {
+    pub const fn new() -> Self {
+        Self {
+            rx: Mutex::new(RefCell::new(Queue::new())),
+            tx: Mutex::new(RefCell::new(Queue::new())),
+            error: Mutex::new(Cell::new(None)),
+        }
+    }
+
+    /// Service USART1, to be called from its interrupt handler
+    pub fn on_interrupt(&self) {
+        let usart = regs();
+        critical_section::with(|cs| {
+            match (Rx { _0: () }).read_byte() {
+                Ok(byte) => {
+                    if !self.rx.borrow_ref_mut(cs).push(byte) {
+                        self.error.borrow(cs).set(Some(Error::Overrun));
+                    }
+                }
+                Err(nb::Error::Other(error)) => self.error.borrow(cs).set(Some(error)),
+                Err(nb::Error::WouldBlock) => {}
+            }
+
+            if usart.ctlr1.read().txeie().bit_is_set() && usart.statr.read().txe().bit_is_set() {
+                match self.tx.borrow_ref_mut(cs).pop() {
+                    Some(byte) => usart.datar.write(|w| w.dr().bits(byte as u32)),
+                    // Nothing left to send, stop the interrupt from firing
+                    None => usart.ctlr1.modify(|_, w| w.txeie().clear_bit()),
+                }
+            }
+        });
+    }
+}
+
+/// USART1 with interrupt driven ring buffers
+pub struct Buffered<PINS, const RX: usize, const TX: usize> {
+    serial: Serial<PINS>,
+    buffers: &'static Buffers<RX, TX>,
+}
+
+impl<PINS, const RX: usize, const TX: usize> Buffered<PINS, RX, TX> {
+    /// Stop using the interrupt, dropping any queued data
+    pub fn release(self) -> Serial<PINS> {
+        critical_section::with(|cs| {
+            regs()
+                .ctlr1
+                .modify(|_, w| w.rxneie().clear_bit().txeie().clear_bit());
+            *self.buffers.rx.borrow_ref_mut(cs) = Queue::new();
+            *self.buffers.tx.borrow_ref_mut(cs) = Queue::new();
+        });
+        self.serial
+    }
+}
+
+impl ErrorType for Tx {
+    type Error = Error;
+}
+
+impl ErrorType for Rx {
+    type Error = Error;
+}
+
+impl<PINS> ErrorType for Serial<PINS> {
+    type Error = Error;
+}
+
+impl<PINS, const RX: usize, const TX: usize> ErrorType for Buffered<PINS, RX, TX> {
+    type Error = Error;
+}
+
+impl Read for Rx {
+    /// Blocks until at least one byte is received
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let mut count = 0;
+        for byte in buf.iter_mut() {
+            match self.read_byte() {
+                Ok(b) => *byte = b,
+                Err(nb::Error::WouldBlock) if count > 0 => break,
+                Err(nb::Error::WouldBlock) => {
+                    *byte = nb::block!(self.read_byte())?;
+                }
+                Err(nb::Error::Other(e)) => return Err(e),
+            }
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+impl ReadReady for Rx {
+    fn read_ready(&mut self) -> Result<bool, Error> {
+        Ok(self.is_ready())
+    }
+}
+
+impl Write for Tx {
+    /// Blocks until at least one byte is queued
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let mut count = 0;
+        for &byte in buf {
+            match self.write_byte(byte) {
+                Ok(()) => count += 1,
+                Err(nb::Error::WouldBlock) if count > 0 => break,
+                Err(nb::Error::WouldBlock) => {
+                    nb::block!(self.write_byte(byte))?;
+                    count += 1;
+                }
+                Err(nb::Error::Other(e)) => return Err(e),
+            }
+        }
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        while !self.is_complete() {}
+        Ok(())
+    }
+}
+
+impl WriteReady for Tx {
+    fn write_ready(&mut self) -> Result<bool, Error> {
+        Ok(regs().statr.read().txe().bit_is_set())
+    }
+}
+
+impl<PINS> Read for Serial<PINS> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.rx.read(buf)
+    }
+}
+
+impl<PINS> ReadReady for Serial<PINS> {
+    fn read_ready(&mut self) -> Result<bool, Error> {
+        self.rx.read_ready()
+    }
+}
+
+impl<PINS> Write for Serial<PINS> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        self.tx.write(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.tx.flush()
+    }
+}
+
+impl<PINS> WriteReady for Serial<PINS> {
+    fn write_ready(&mut self) -> Result<bool, Error> {
+        self.tx.write_ready()
+    }
+}
+
+impl<PINS, const RX: usize, const TX: usize> Read for Buffered<PINS, RX, TX> {
+    /// Blocks until at least one byte is received
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            let count = critical_section::with(|cs| {
+                if let Some(error) = self.buffers.error.borrow(cs).take() {
+                    return Err(error);
+                }
+                let mut rx = self.buffers.rx.borrow_ref_mut(cs);
+                let mut count = 0;
+                for byte in buf.iter_mut() {
+                    match rx.pop() {
+                        Some(b) => *byte = b,
+                        None => break,
+                    }
+                    count += 1;
+                }
+                Ok(count)
+            })?;
+
+            if count > 0 {
+                return Ok(count);
+            }
+        }
+    }
+}
+
+impl<PINS, const RX: usize, const TX: usize> ReadReady for Buffered<PINS, RX, TX> {
+    fn read_ready(&mut self) -> Result<bool, Error> {
+        Ok(critical_section::with(|cs| {
+            self.buffers.rx.borrow_ref(cs).len > 0
+        }))
+    }
+}
+
+impl<PINS, const RX: usize, const TX: usize> Write for Buffered<PINS, RX, TX> {
+    /// Blocks until at least one byte is queued
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            let count = critical_section::with(|cs| {
+                let mut tx = self.buffers.tx.borrow_ref_mut(cs);
+                let mut count = 0;
+                for &byte in buf {
+                    if !tx.push(byte) {
+                        break;
+                    }
+                    count += 1;
+                }
+                if count > 0 {
+                    regs().ctlr1.modify(|_, w| w.txeie().set_bit());
+                }
+                count
+            });
+
+            if count > 0 {
+                return Ok(count);
+            }
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        while critical_section::with(|cs| self.buffers.tx.borrow_ref(cs).len > 0) {}
+        self.serial.tx.flush()
+    }
+}
+
+impl<PINS, const RX: usize, const TX: usize> WriteReady for Buffered<PINS, RX, TX> {
+    fn write_ready(&mut self) -> Result<bool, Error> {
+        Ok(critical_section::with(|cs| {
+            self.buffers.tx.borrow_ref(cs).len < TX
+        }))
+    }
+}
+
+impl fmt::Write for Tx {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_all(s.as_bytes()).map_err(|_| fmt::Error)
+    }
+}
+
+impl<PINS> fmt::Write for Serial<PINS> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.tx.write_str(s)
+    }
+}
+
+impl<PINS, const RX: usize, const TX: usize> fmt::Write for Buffered<PINS, RX, TX> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_all(s.as_bytes()).map_err(|_| fmt::Error)
+    }
+}
@@ -0,0 +1,698 @@
+//! SPI1 master and slave
+//!
+//! SCK, MISO and MOSI are always on PC5, PC7 and PC6. NSS is left to
+//! software when only those three pins are given, or handled by SPI1 on PC1
+//! or PC0 when it is passed as a fourth pin, which also selects the pin set:
+//!
+//! ```ignore
+//! let sck = gpioc.pc5.into_alternate_push_pull();
+//! let miso = gpioc.pc7.into_floating_input();
+//! let mosi = gpioc.pc6.into_alternate_push_pull();
+//! let mut spi = Spi::new(p.SPI1, (sck, miso, mosi), spi::MODE_0, 1_000_000, &clocks);
+//! spi.write(&[0x9F])?;
+//! ```
+//!
+//! Frames are 8 bits wide, or 16 after [`Spi::frame_size_16bit`]. With
+//! [`Spi::with_dma`] the data is moved by DMA channels 2 and 3 instead,
+//! either blocking through [`SpiBus`] or in the background with a
+//! `'static` buffer.
+
+use core::marker::PhantomData;
+
+use embedded_hal::spi::{self, ErrorKind, ErrorType, SpiBus};
+pub use embedded_hal::spi::{Mode, Phase, Polarity, MODE_0, MODE_1, MODE_2, MODE_3};
+
+use crate::afio::{self, spi1, Remap};
+use crate::dma::{self, Direction, WriteBuffer};
+use crate::pac::{self, spi::RegisterBlock};
+use crate::rcc::{Clocks, Enable};
+
+/// Pins that can be used for SPI1 in master mode, as `(SCK, MISO, MOSI)`
+/// with NSS in software or `(SCK, MISO, MOSI, NSS)` of pin set `R`
+pub trait Pins<R> {
+    #[doc(hidden)]
+    const NSS: bool;
+}
+
+impl<SCK, MISO, MOSI> Pins<spi1::Remap0> for (SCK, MISO, MOSI)
+where
+    SCK: spi1::Sck<spi1::Remap0>,
+    MISO: spi1::Miso<spi1::Remap0>,
+    MOSI: spi1::Mosi<spi1::Remap0>,
+{
+    const NSS: bool = false;
+}
+
+impl<R, SCK, MISO, MOSI, NSS> Pins<R> for (SCK, MISO, MOSI, NSS)
+where
+    SCK: spi1::Sck<R>,
+    MISO: spi1::Miso<R>,
+    MOSI: spi1::Mosi<R>,
+    NSS: spi1::Nss<R>,
+{
+    const NSS: bool = true;
+}
+
+/// Pins that can be used for SPI1 in slave mode, as `(SCK, MISO, MOSI)`
+/// always selected or `(SCK, MISO, MOSI, NSS)` of pin set `R`
+pub trait SlavePins<R> {
+    #[doc(hidden)]
+    const NSS: bool;
+}
+
+impl<SCK, MISO, MOSI> SlavePins<spi1::Remap0> for (SCK, MISO, MOSI)
+where
+    SCK: spi1::SlaveSck<spi1::Remap0>,
+    MISO: spi1::SlaveMiso<spi1::Remap0>,
+    MOSI: spi1::SlaveMosi<spi1::Remap0>,
+{
+    const NSS: bool = false;
+}
+
+impl<R, SCK, MISO, MOSI, NSS> SlavePins<R> for (SCK, MISO, MOSI, NSS)
+where
+    SCK: spi1::SlaveSck<R>,
+    MISO: spi1::SlaveMiso<R>,
+    MOSI: spi1::SlaveMosi<R>,
+    NSS: spi1::SlaveNss<R>,
+{
+    const NSS: bool = true;
+}
+
+/// Width of a frame, `u8` or `u16`
+pub trait FrameSize: dma::Word + Copy + Default + 'static {
+    #[doc(hidden)]
+    const DFF: bool;
+
+    #[doc(hidden)]
+    fn from_bits(bits: u32) -> Self;
+
+    #[doc(hidden)]
+    fn into_bits(self) -> u32;
+}
+
+impl FrameSize for u8 {
+    const DFF: bool = false;
+
+    fn from_bits(bits: u32) -> Self {
+        bits as u8
+    }
+
+    fn into_bits(self) -> u32 {
+        self as u32
+    }
+}
+
+impl FrameSize for u16 {
+    const DFF: bool = true;
+
+    fn from_bits(bits: u32) -> Self {
+        bits as u16
+    }
+
+    fn into_bits(self) -> u32 {
+        self as u32
+    }
+}
+
+/// SPI errors
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// Received data was not read in time
+    Overrun,
+    /// Another master pulled NSS low
+    ModeFault,
+    /// The received CRC did not match
+    Crc,
+}
+
+impl spi::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Overrun => ErrorKind::Overrun,
+            Error::ModeFault => ErrorKind::ModeFault,
+            Error::Crc => ErrorKind::Other,
+        }
+    }
+}
+
+fn regs() -> &'static RegisterBlock {
+    unsafe { &*pac::SPI1::PTR }
+}
+
+/// Change settings that can only be written while SPI1 is disabled
+fn reconfigure(f: impl FnOnce(&RegisterBlock)) {
+    let spi = regs();
+    spi.ctlr1.modify(|_, w| w.spe().clear_bit());
+    f(spi);
+    spi.ctlr1.modify(|_, w| w.spe().set_bit());
+}
+
+fn check_errors() -> Result<(), Error> {
+    let spi = regs();
+    let statr = spi.statr.read();
+    if statr.ovr().bit_is_set() {
+        // Cleared by reading DATAR followed by STATR
+        spi.datar.read();
+        spi.statr.read();
+        Err(Error::Overrun)
+    } else if statr.modf().bit_is_set() {
+        // Cleared by reading STATR followed by writing CTLR1
+        spi.ctlr1.modify(|_, w| w);
+        Err(Error::ModeFault)
+    } else {
+        Ok(())
+    }
+}
+
+fn send<W: FrameSize>(word: W) -> Result<(), Error> {
+    let spi = regs();
+    while spi.statr.read().txe().bit_is_clear() {
+        check_errors()?;
+    }
+    spi.datar.write(|w| w.dr().bits(word.into_bits()));
+    Ok(())
+}
+
+fn receive<W: FrameSize>() -> Result<W, Error> {
+    let spi = regs();
+    while spi.statr.read().rxne().bit_is_clear() {
+        check_errors()?;
+    }
+    Ok(W::from_bits(spi.datar.read().bits()))
+}
+
+/// Shift out one word and return the word shifted in
+fn exchange<W: FrameSize>(word: W) -> Result<W, Error> {
+    send(word)?;
+    receive()
+}
+
+fn set_crc(polynomial: Option<u16>) {
+    reconfigure(|spi| {
+        if let Some(polynomial) = polynomial {
+            spi.crcr.write(|w| w.polynomial().bits(polynomial as u32));
+        }
+        spi.ctlr1.modify(|_, w| w.crcen().bit(polynomial.is_some()));
+    });
+}
+
+/// Exchange words in place, followed by the CRC of each side
+fn transfer_with_crc<W: FrameSize>(words: &mut [W]) -> Result<(), Error> {
+    let spi = regs();
+    // Restart both CRCs from zero
+    reconfigure(|spi| {
+        spi.ctlr1.modify(|_, w| w.crcen().clear_bit());
+        spi.ctlr1.modify(|_, w| w.crcen().set_bit());
+    });
+
+    let len = words.len();
+    for (i, word) in words.iter_mut().enumerate() {
+        send(*word)?;
+        if i + 1 == len {
+            spi.ctlr1.modify(|_, w| w.crcnext().set_bit());
+        }
+        *word = receive()?;
+    }
+    // The CRC of the other side ends up in DATAR like any other word
+    receive::<W>()?;
+    spi.ctlr1.modify(|_, w| w.crcnext().clear_bit());
+
+    if spi.statr.read().crcerr().bit_is_set() {
+        spi.statr.modify(|_, w| w.crcerr().clear_bit());
+        Err(Error::Crc)
+    } else {
+        Ok(())
+    }
+}
+
+/// SPI1 in master mode
+pub struct Spi<PINS, W = u8> {
+    spi: pac::SPI1,
+    pins: PINS,
+    _word: PhantomData<W>,
+}
+
+impl<PINS> Spi<PINS, u8> {
+    /// Set up SPI1 with 8-bit frames and a clock of at most `freq` Hz
+    pub fn new<R: Remap>(spi: pac::SPI1, pins: PINS, mode: Mode, freq: u32, clocks: &Clocks) -> Self
+    where
+        PINS: Pins<R>,
+    {
+        pac::SPI1::enable_and_reset();
+
+        afio::remap::<R>();
+
+        // Smallest divider 2 << br that does not exceed the requested clock
+        let br = (0..7)
+            .find(|&br| clocks.pclk() >> (br + 1) <= freq)
+            .unwrap_or(7);
+
+        // With NSS in hardware it is driven low while SPI1 is enabled
+        spi.ctlr2.write(|w| w.ssoe().bit(PINS::NSS));
+        spi.ctlr1.write(|w| {
+            w.cpol()
+                .bit(mode.polarity == Polarity::IdleHigh)
+                .cpha()
+                .bit(mode.phase == Phase::CaptureOnSecondTransition)
+                .mstr()
+                .set_bit()
+                .br()
+                .bits(br)
+                .ssm()
+                .bit(!PINS::NSS)
+                .ssi()
+                .set_bit()
+                .spe()
+                .set_bit()
+        });
+
+        Self {
+            spi,
+            pins,
+            _word: PhantomData,
+        }
+    }
+}
+
+impl<PINS, W: FrameSize> Spi<PINS, W> {
+    /// Give back the peripheral and pins
+    pub fn release(self) -> (pac::SPI1, PINS) {
+        self.spi.ctlr1.modify(|_, w| w.spe().clear_bit());
+        (self.spi, self.pins)
+    }
+
+    /// Switch to 8-bit frames
+    pub fn frame_size_8bit(self) -> Spi<PINS, u8> {
+        reconfigure(|spi| spi.ctlr1.modify(|_, w| w.dff().clear_bit()));
+        Spi {
+            spi: self.spi,
+            pins: self.pins,
+            _word: PhantomData,
+        }
+    }
+
+    /// Switch to 16-bit frames
+    pub fn frame_size_16bit(self) -> Spi<PINS, u16> {
+        reconfigure(|spi| spi.ctlr1.modify(|_, w| w.dff().set_bit()));
+        Spi {
+            spi: self.spi,
+            pins: self.pins,
+            _word: PhantomData,
+        }
+    }
+
+    /// Calculate CRCs with `polynomial` for [`Spi::transfer_with_crc`]
+    pub fn enable_crc(&mut self, polynomial: u16) {
+        set_crc(Some(polynomial));
+    }
+
+    pub fn disable_crc(&mut self) {
+        set_crc(None);
+    }
+
+    /// Exchange words in place and then their CRCs, failing if the CRC
+    /// from the slave does not match
+    pub fn transfer_with_crc(&mut self, words: &mut [W]) -> Result<(), Error> {
+        transfer_with_crc(words)
+    }
+
+    /// Move data with DMA channel 2 receiving and channel 3 sending
+    pub fn with_dma(self, rx: dma::C2, tx: dma::C3) -> SpiDma<Self> {
+        SpiDma { spi: self, rx, tx }
+    }
+}
+
+impl<PINS, W> ErrorType for Spi<PINS, W> {
+    type Error = Error;
+}
+
+impl<PINS, W: FrameSize> SpiBus<W> for Spi<PINS, W> {
+    fn read(&mut self, words: &mut [W]) -> Result<(), Error> {
+        for word in words {
+            *word = exchange(W::default())?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, words: &[W]) -> Result<(), Error> {
+        for &word in words {
+            exchange(word)?;
+        }
+        Ok(())
+    }
+
+    fn transfer(&mut self, read: &mut [W], write: &[W]) -> Result<(), Error> {
+        for i in 0..read.len().max(write.len()) {
+            let word = exchange(write.get(i).copied().unwrap_or_default())?;
+            if let Some(read) = read.get_mut(i) {
+                *read = word;
+            }
+        }
+        Ok(())
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [W]) -> Result<(), Error> {
+        for word in words {
+            *word = exchange(*word)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        while self.spi.statr.read().bsy().bit_is_set() {}
+        Ok(())
+    }
+}
+
+/// SPI1 in slave mode, clocked by the master
+pub struct SpiSlave<PINS, W = u8> {
+    spi: pac::SPI1,
+    pins: PINS,
+    _word: PhantomData<W>,
+}
+
+impl<PINS> SpiSlave<PINS, u8> {
+    /// Set up SPI1 as a slave with 8-bit frames
+    ///
+    /// Without an NSS pin the slave is always selected.
+    pub fn new<R: Remap>(spi: pac::SPI1, pins: PINS, mode: Mode) -> Self
+    where
+        PINS: SlavePins<R>,
+    {
+        pac::SPI1::enable_and_reset();
+
+        afio::remap::<R>();
+
+        spi.ctlr1.write(|w| {
+            w.cpol()
+                .bit(mode.polarity == Polarity::IdleHigh)
+                .cpha()
+                .bit(mode.phase == Phase::CaptureOnSecondTransition)
+                .ssm()
+                .bit(!PINS::NSS)
+                .ssi()
+                .clear_bit()
+                .spe()
+                .set_bit()
+        });
+
+        Self {
+            spi,
+            pins,
+            _word: PhantomData,
+        }
+    }
+}
+
+impl<PINS, W: FrameSize> SpiSlave<PINS, W> {
+    /// Give back the peripheral and pins
+    pub fn release(self) -> (pac::SPI1, PINS) {
+        self.spi.ctlr1.modify(|_, w| w.spe().clear_bit());
+        (self.spi, self.pins)
+    }
+
+    /// Switch to 8-bit frames
+    pub fn frame_size_8bit(self) -> SpiSlave<PINS, u8> {
+        reconfigure(|spi| spi.ctlr1.modify(|_, w| w.dff().clear_bit()));
+        SpiSlave {
+            spi: self.spi,
+            pins: self.pins,
+            _word: PhantomData,
+        }
+    }
+
+    /// Switch to 16-bit frames
+    pub fn frame_size_16bit(self) -> SpiSlave<PINS, u16> {
+        reconfigure(|spi| spi.ctlr1.modify(|_, w| w.dff().set_bit()));
+        SpiSlave {
+            spi: self.spi,
+            pins: self.pins,
+            _word: PhantomData,
+        }
+    }
+
+    /// Calculate CRCs with `polynomial` for [`SpiSlave::transfer_with_crc`]
+    pub fn enable_crc(&mut self, polynomial: u16) {
+        set_crc(Some(polynomial));
+    }
+
+    pub fn disable_crc(&mut self) {
+        set_crc(None);
+    }
+
+    /// Queue the word sent on the next frame if there is room
+    pub fn send(&mut self, word: W) -> nb::Result<(), Error> {
+        check_errors()?;
+        if self.spi.statr.read().txe().bit_is_set() {
+            self.spi.datar.write(|w| w.dr().bits(word.into_bits()));
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// Take the word received in the last frame if there is one
+    pub fn read(&mut self) -> nb::Result<W, Error> {
+        check_errors()?;
+        if self.spi.statr.read().rxne().bit_is_set() {
+            Ok(W::from_bits(self.spi.datar.read().bits()))
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// Exchange words in place, blocking until the master clocked them all
+    pub fn transfer(&mut self, words: &mut [W]) -> Result<(), Error> {
+        for word in words {
+            *word = exchange(*word)?;
+        }
+        Ok(())
+    }
+
+    /// Exchange words in place and then their CRCs, failing if the CRC
+    /// from the master does not match
+    pub fn transfer_with_crc(&mut self, words: &mut [W]) -> Result<(), Error> {
+        transfer_with_crc(words)
+    }
+
+    /// Move data with DMA channel 2 receiving and channel 3 sending
+    pub fn with_dma(self, rx: dma::C2, tx: dma::C3) -> SpiDma<Self> {
+        SpiDma { spi: self, rx, tx }
+    }
+}
+
+/// A master or slave that can be driven by DMA
+pub trait DmaSpi {
+    type Word: FrameSize;
+}
+
+impl<PINS, W: FrameSize> DmaSpi for Spi<PINS, W> {
+    type Word = W;
+}
+
+impl<PINS, W: FrameSize> DmaSpi for SpiSlave<PINS, W> {
+    type Word = W;
+}
+
+/// SPI1 with its DMA channels
+pub struct SpiDma<SPI> {
+    spi: SPI,
+    rx: dma::C2,
+    tx: dma::C3,
+}
+
+impl<SPI: DmaSpi> SpiDma<SPI> {
+    /// Give back the driver and channels
+    pub fn release(self) -> (SPI, dma::C2, dma::C3) {
+        (self.spi, self.rx, self.tx)
+    }
+
+    /// Start moving up to `len` words, stepping through the buffers, or
+    /// staying on `dummy` in place of a missing one
+    ///
+    /// A channel counts at most [`u16::MAX`] words, the number started is
+    /// returned.
+    fn start(
+        &mut self,
+        read: Option<*mut SPI::Word>,
+        write: Option<*const SPI::Word>,
+        dummy: *mut SPI::Word,
+        len: usize,
+    ) -> usize {
+        let spi = regs();
+        let len = len.min(u16::MAX as usize);
+
+        self.rx.configure(
+            spi.datar.as_ptr() as u32,
+            read.unwrap_or(dummy) as *const SPI::Word,
+            len,
+            Direction::PeripheralToMemory,
+        );
+        if read.is_none() {
+            self.rx.set_fixed_memory();
+        }
+        self.tx.configure(
+            spi.datar.as_ptr() as u32,
+            write.unwrap_or(dummy),
+            len,
+            Direction::MemoryToPeripheral,
+        );
+        if write.is_none() {
+            self.tx.set_fixed_memory();
+        }
+
+        // Receive before sending, so the first word can not be missed
+        spi.ctlr2.modify(|_, w| w.rxdmaen().set_bit());
+        self.rx.start();
+        self.tx.start();
+        spi.ctlr2.modify(|_, w| w.txdmaen().set_bit());
+        len
+    }
+
+    fn is_done(&self) -> bool {
+        // Also true for empty buffers, which never complete
+        self.rx.remaining() == 0 || self.rx.has_error()
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        let spi = regs();
+        while !self.is_done() {}
+        while spi.statr.read().bsy().bit_is_set() {}
+        spi.ctlr2
+            .modify(|_, w| w.rxdmaen().clear_bit().txdmaen().clear_bit());
+        self.rx.stop();
+        self.tx.stop();
+        check_errors()
+    }
+
+    /// Move `len` words and wait for it, in as many parts as it takes
+    fn run(
+        &mut self,
+        mut read: Option<*mut SPI::Word>,
+        mut write: Option<*const SPI::Word>,
+        dummy: *mut SPI::Word,
+        mut len: usize,
+    ) -> Result<(), Error> {
+        loop {
+            let part = self.start(read, write, dummy, len);
+            self.finish()?;
+            len -= part;
+            if len == 0 {
+                return Ok(());
+            }
+            read = read.map(|ptr| unsafe { ptr.add(part) });
+            write = write.map(|ptr| unsafe { ptr.add(part) });
+        }
+    }
+
+    /// Exchange a buffer in place in the background
+    pub fn start_transfer<B>(mut self, mut buffer: B) -> SpiDmaTransfer<SPI, B>
+    where
+        B: WriteBuffer<Word = SPI::Word>,
+    {
+        let (ptr, len) = buffer.write_buffer();
+        // Sending stays ahead of receiving, so a word is sent before it is
+        // overwritten
+        let part = self.start(Some(ptr), Some(ptr), ptr, len);
+        SpiDmaTransfer {
+            spi: self,
+            buffer,
+            next: unsafe { ptr.add(part) },
+            left: len - part,
+            result: Ok(()),
+        }
+    }
+}
+
+/// A DMA exchange running in the background
+pub struct SpiDmaTransfer<SPI: DmaSpi, B> {
+    spi: SpiDma<SPI>,
+    buffer: B,
+    /// Start of the words after the running part
+    next: *mut SPI::Word,
+    left: usize,
+    /// Errors of the parts already done
+    result: Result<(), Error>,
+}
+
+impl<SPI: DmaSpi, B> SpiDmaTransfer<SPI, B> {
+    /// Whether all words were exchanged
+    ///
+    /// Buffers longer than a channel can count are sent in parts, the next
+    /// one is started here once the last is done.
+    pub fn is_done(&mut self) -> bool {
+        if self.result.is_err() {
+            return true;
+        }
+        if !self.spi.is_done() {
+            return false;
+        }
+        if self.left == 0 {
+            return true;
+        }
+        self.start_next();
+        false
+    }
+
+    /// Block until the exchange is done, giving back the buffer and driver
+    pub fn wait(mut self) -> (Result<(), Error>, B, SpiDma<SPI>) {
+        while self.left > 0 && self.result.is_ok() {
+            while !self.spi.is_done() {}
+            self.start_next();
+        }
+        // A failed part was already finished
+        let result = self.result.and_then(|_| self.spi.finish());
+        (result, self.buffer, self.spi)
+    }
+
+    /// Finish the running part and start the next one
+    fn start_next(&mut self) {
+        self.result = self.spi.finish();
+        if self.result.is_ok() {
+            let ptr = self.next;
+            let part = self.spi.start(Some(ptr), Some(ptr), ptr, self.left);
+            self.next = unsafe { ptr.add(part) };
+            self.left -= part;
+        }
+    }
+}
+
+impl<SPI> ErrorType for SpiDma<SPI> {
+    type Error = Error;
+}
+
+// The buffers are only borrowed, so every operation waits for DMA to finish
+impl<PINS, W: FrameSize> SpiBus<W> for SpiDma<Spi<PINS, W>> {
+    fn read(&mut self, words: &mut [W]) -> Result<(), Error> {
+        let mut dummy = W::default();
+        self.run(Some(words.as_mut_ptr()), None, &mut dummy, words.len())
+    }
+
+    fn write(&mut self, words: &[W]) -> Result<(), Error> {
+        let mut dummy = W::default();
+        self.run(None, Some(words.as_ptr()), &mut dummy, words.len())
+    }
+
+    fn transfer(&mut self, read: &mut [W], write: &[W]) -> Result<(), Error> {
+        let common = read.len().min(write.len());
+        let (read, read_rest) = read.split_at_mut(common);
+        let (write, write_rest) = write.split_at(common);
+        let (rx, tx) = (read.as_mut_ptr(), write.as_ptr());
+        self.run(Some(rx), Some(tx), rx, common)?;
+        // Only one of them has anything left
+        self.read(read_rest)?;
+        self.write(write_rest)
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [W]) -> Result<(), Error> {
+        let ptr = words.as_mut_ptr();
+        self.run(Some(ptr), Some(ptr), ptr, words.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        while regs().statr.read().bsy().bit_is_set() {}
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+//! QingKe V2 system timer
+//!
+//! The timer free-runs at HCLK / 8 and provides both calibrated delays and
+//! a monotonic clock:
+//!
+//! ```ignore
+//! let mut systick = Systick::new(p.SYSTICK, &clocks);
+//! let start = systick.now();
+//! systick.delay_ms(500);
+//! let elapsed = systick.now() - start;
+//! ```
+
+use core::cell::Cell;
+
+use embedded_hal::delay::DelayNs;
+
+use crate::pac::SYSTICK;
+use crate::rcc::Clocks;
+use crate::time::{Duration, Instant};
+
+/// Longest stretch waited on the raw counter before refreshing the
+/// extended count
+const CHUNK: u32 = 1 << 30;
+
+/// SysTick as a delay provider and monotonic clock
+///
+/// The hardware counter is 32 bits wide and is extended to 64 bits in
+/// software, which requires [`now`](Self::now) or a delay to run at least
+/// once per counter wrap: every 2^32 ticks, or about 12 minutes at 48 MHz.
+pub struct Systick {
+    systick: SYSTICK,
+    /// Tick frequency in Hz
+    freq: u32,
+    /// Raw counter value at the last observation
+    last: Cell<u32>,
+    /// Upper 32 bits of the extended count
+    wraps: Cell<u32>,
+}
+
+impl Systick {
+    /// Start the counter from zero
+    pub fn new(systick: SYSTICK, clocks: &Clocks) -> Self {
+        systick.ctlr.reset();
+        systick.cnt.write(|w| w.cnt().bits(0));
+        systick.cmp.write(|w| w.cmp().bits(u32::MAX));
+        // HCLK / 8, free running
+        systick.ctlr.write(|w| w.ste().set_bit());
+
+        Self {
+            systick,
+            freq: clocks.hclk() / 8,
+            last: Cell::new(0),
+            wraps: Cell::new(0),
+        }
+    }
+
+    /// Stop the counter and give back the peripheral
+    pub fn release(self) -> SYSTICK {
+        self.systick.ctlr.reset();
+        self.systick
+    }
+
+    /// Tick frequency in Hz
+    pub fn freq(&self) -> u32 {
+        self.freq
+    }
+
+    fn raw(&self) -> u32 {
+        self.systick.cnt.read().bits()
+    }
+
+    /// Counter value extended to 64 bits
+    pub fn ticks(&self) -> u64 {
+        let raw = self.raw();
+        if raw < self.last.get() {
+            self.wraps.set(self.wraps.get() + 1);
+        }
+        self.last.set(raw);
+
+        (self.wraps.get() as u64) << 32 | raw as u64
+    }
+
+    /// Current time
+    pub fn now(&self) -> Instant {
+        let ticks = self.ticks();
+        let freq = self.freq as u64;
+        // Split to avoid overflowing the multiplication
+        Instant::from_micros(ticks / freq * 1_000_000 + ticks % freq * 1_000_000 / freq)
+    }
+
+    /// Wait for a number of ticks
+    fn wait_ticks(&mut self, mut ticks: u64) {
+        while ticks > 0 {
+            let chunk = ticks.min(CHUNK as u64) as u32;
+            let start = self.raw();
+            while self.raw().wrapping_sub(start) < chunk {}
+            self.ticks();
+            ticks -= chunk as u64;
+        }
+    }
+
+    /// Wait for at least `duration`
+    pub fn delay(&mut self, duration: Duration) {
+        let micros = duration.as_micros();
+        let freq = self.freq as u64;
+        self.wait_ticks(
+            micros / 1_000_000 * freq + (micros % 1_000_000 * freq).div_ceil(1_000_000),
+        );
+    }
+}
+
+impl DelayNs for Systick {
+    fn delay_ns(&mut self, ns: u32) {
+        let ticks = (ns as u64 * self.freq as u64).div_ceil(1_000_000_000);
+        self.wait_ticks(ticks);
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        self.delay(Duration::from_micros(us as u64));
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        self.delay(Duration::from_millis(ms as u64));
+    }
+}
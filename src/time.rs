@@ -0,0 +1,124 @@
+//! Time units
+
+use core::ops::{Add, AddAssign, Sub, SubAssign};
+
+/// A span of time with microsecond resolution
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Duration {
+    micros: u64,
+}
+
+impl Duration {
+    pub const ZERO: Duration = Duration { micros: 0 };
+
+    pub const fn from_micros(micros: u64) -> Self {
+        Self { micros }
+    }
+
+    pub const fn from_millis(millis: u64) -> Self {
+        Self::from_micros(millis * 1_000)
+    }
+
+    pub const fn from_secs(secs: u64) -> Self {
+        Self::from_micros(secs * 1_000_000)
+    }
+
+    pub const fn as_micros(&self) -> u64 {
+        self.micros
+    }
+
+    pub const fn as_millis(&self) -> u64 {
+        self.micros / 1_000
+    }
+
+    pub const fn as_secs(&self) -> u64 {
+        self.micros / 1_000_000
+    }
+
+    pub const fn checked_sub(self, rhs: Duration) -> Option<Duration> {
+        match self.micros.checked_sub(rhs.micros) {
+            Some(micros) => Some(Duration { micros }),
+            None => None,
+        }
+    }
+}
+
+impl Add for Duration {
+    type Output = Duration;
+
+    fn add(self, rhs: Duration) -> Duration {
+        Duration::from_micros(self.micros + rhs.micros)
+    }
+}
+
+impl AddAssign for Duration {
+    fn add_assign(&mut self, rhs: Duration) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Duration {
+    type Output = Duration;
+
+    fn sub(self, rhs: Duration) -> Duration {
+        Duration::from_micros(self.micros - rhs.micros)
+    }
+}
+
+impl SubAssign for Duration {
+    fn sub_assign(&mut self, rhs: Duration) {
+        *self = *self - rhs;
+    }
+}
+
+/// A point in time, measured from when the monotonic clock was started
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant {
+    micros: u64,
+}
+
+impl Instant {
+    pub const fn from_micros(micros: u64) -> Self {
+        Self { micros }
+    }
+
+    /// Time since the clock was started
+    pub const fn as_micros(&self) -> u64 {
+        self.micros
+    }
+
+    /// Time elapsed since `earlier`, saturating to zero
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        Duration::from_micros(self.micros.saturating_sub(earlier.micros))
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, rhs: Duration) -> Instant {
+        Instant::from_micros(self.micros + rhs.as_micros())
+    }
+}
+
+impl AddAssign<Duration> for Instant {
+    fn add_assign(&mut self, rhs: Duration) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub<Duration> for Instant {
+    type Output = Instant;
+
+    fn sub(self, rhs: Duration) -> Instant {
+        Instant::from_micros(self.micros - rhs.as_micros())
+    }
+}
+
+impl Sub for Instant {
+    type Output = Duration;
+
+    fn sub(self, rhs: Instant) -> Duration {
+        self.duration_since(rhs)
+    }
+}
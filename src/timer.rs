@@ -0,0 +1,172 @@
+//! TIM1 and TIM2 timers
+//!
+//! Each timer counts PCLK through a 16-bit prescaler and is put to one use
+//! at a time: PWM outputs, input capture or quadrature encoder counting. The
+//! pin set is chosen when the timer is set up, and channel pins have to be
+//! part of it:
+//!
+//! ```ignore
+//! let mut pwm = Pwm::new(p.TIM2, tim2::Remap1, 1_000, Counting::Up, &clocks);
+//! let mut led = pwm.channel(gpioc.pc1.into_alternate_push_pull());
+//! led.set_duty_cycle_percent(25).ok();
+//! ```
+//!
+//! TIM1 additionally drives complementary output pairs with dead-time, stops
+//! them from its break input and can skip updates with a repetition counter.
+//! Either timer can start ADC conversions through its [`TriggerOutput`].
+
+use crate::pac::{self, tim2::RegisterBlock};
+use crate::rcc::Enable;
+
+pub mod capture;
+pub mod pwm;
+pub mod qei;
+
+pub use capture::{Capture, CaptureChannel, CaptureConfig};
+pub use pwm::{Pwm, PwmChannel, PwmMode};
+pub use qei::{EncoderMode, Qei};
+
+/// A timer the drivers can run
+pub trait Instance {
+    #[doc(hidden)]
+    fn regs() -> &'static RegisterBlock;
+
+    #[doc(hidden)]
+    fn enable_and_reset();
+
+    /// Connect the channel outputs to the pins, TIM1 gates them with MOE
+    #[doc(hidden)]
+    fn enable_outputs() {}
+}
+
+impl Instance for pac::TIM1 {
+    fn regs() -> &'static RegisterBlock {
+        // TIM2 has the same layout, without the registers only TIM1 has
+        unsafe { &*(pac::TIM1::PTR as *const RegisterBlock) }
+    }
+
+    fn enable_and_reset() {
+        <pac::TIM1 as Enable>::enable_and_reset();
+    }
+
+    fn enable_outputs() {
+        let tim = unsafe { &*pac::TIM1::PTR };
+        tim.bdtr.modify(|_, w| w.moe().set_bit());
+    }
+}
+
+impl Instance for pac::TIM2 {
+    fn regs() -> &'static RegisterBlock {
+        unsafe { &*pac::TIM2::PTR }
+    }
+
+    fn enable_and_reset() {
+        <pac::TIM2 as Enable>::enable_and_reset();
+    }
+}
+
+/// Direction and alignment of the counter
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Counting {
+    /// From 0 up to the reload value, edge aligned
+    Up,
+    /// From the reload value down to 0, edge aligned
+    Down,
+    /// Up and down, compare flags set while counting down
+    CenterAligned1,
+    /// Up and down, compare flags set while counting up
+    CenterAligned2,
+    /// Up and down, compare flags set both ways
+    CenterAligned3,
+}
+
+impl Counting {
+    fn is_center_aligned(self) -> bool {
+        !matches!(self, Counting::Up | Counting::Down)
+    }
+
+    /// Set the counting mode, the counter has to be stopped
+    fn apply(self, regs: &RegisterBlock) {
+        let cms = match self {
+            Counting::Up | Counting::Down => 0b00,
+            Counting::CenterAligned1 => 0b01,
+            Counting::CenterAligned2 => 0b10,
+            Counting::CenterAligned3 => 0b11,
+        };
+        regs.ctlr1
+            .modify(|_, w| w.dir().bit(self == Counting::Down).cms().bits(cms));
+    }
+}
+
+/// Signal sent to the ADC and the other timer on TRGO
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TriggerOutput {
+    /// The UG bit
+    Reset = 0b000,
+    /// Starting the counter
+    Enable = 0b001,
+    /// Every update event
+    Update = 0b010,
+    /// Every channel 1 capture or compare match
+    ComparePulse = 0b011,
+    /// Output reference of channel 1
+    Compare1 = 0b100,
+    /// Output reference of channel 2
+    Compare2 = 0b101,
+    /// Output reference of channel 3
+    Compare3 = 0b110,
+    /// Output reference of channel 4
+    Compare4 = 0b111,
+}
+
+/// Which level of an output is the active one
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Polarity {
+    ActiveHigh,
+    ActiveLow,
+}
+
+/// Input capture errors
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// A capture happened before the previous one was read
+    Overcapture,
+}
+
+/// Prescaler and reload value for a period of `ticks` timer clocks
+///
+/// The reload value stays below 0xFFFF, so a compare value one past it still
+/// fits and gives a 100% duty cycle.
+fn prescale(ticks: u32) -> (u32, u32) {
+    let ticks = ticks.max(2);
+    let psc = ((ticks - 1) / 0xFFFF).min(0xFFFF);
+    let arr = (ticks / (psc + 1) - 1).min(0xFFFE);
+    (psc, arr)
+}
+
+/// Replace the CHCTLR byte of `channel`, counting from 1
+fn set_channel_mode<TIM: Instance>(channel: u8, bits: u32) {
+    let regs = TIM::regs();
+    let shift = 8 * ((channel as u32 - 1) % 2);
+    let update = |r: u32| r & !(0xFF << shift) | bits << shift;
+    critical_section::with(|_| {
+        if channel <= 2 {
+            regs.chctlr1
+                .modify(|r, w| unsafe { w.bits(update(r.bits())) });
+        } else {
+            regs.chctlr2
+                .modify(|r, w| unsafe { w.bits(update(r.bits())) });
+        }
+    });
+}
+
+/// Update the CCER bits of `channel` selected by the low nibble of `mask`
+fn set_channel_enable<TIM: Instance>(channel: u8, mask: u32, bits: u32) {
+    let regs = TIM::regs();
+    let shift = 4 * (channel as u32 - 1);
+    critical_section::with(|_| {
+        regs.ccer
+            .modify(|r, w| unsafe { w.bits(r.bits() & !(mask << shift) | (bits & mask) << shift) })
+    });
+}
@@ -0,0 +1,184 @@
+//! Input capture
+//!
+//! The counter free-runs at a chosen tick rate, and every channel latches
+//! it on the edges of its pin. The difference of two captures is the time
+//! between the edges, in ticks:
+//!
+//! ```ignore
+//! let mut capture = Capture::new(p.TIM2, tim2::Remap0, 1_000_000, &clocks);
+//! let mut input = capture.channel(gpiod.pd4.into_floating_input(), CaptureConfig::default());
+//! let first = nb::block!(input.read())?;
+//! let second = nb::block!(input.read())?;
+//! let period_us = second.wrapping_sub(first);
+//! ```
+
+use core::marker::PhantomData;
+
+use super::{set_channel_enable, set_channel_mode, Error, Instance};
+use crate::afio::{self, Remap, TimChannel};
+use crate::gpio::{Input, Pin};
+use crate::rcc::Clocks;
+
+/// Edge of the input that is captured
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edge {
+    Rising,
+    Falling,
+}
+
+/// Number of edges per capture
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Prescaler {
+    Div1 = 0b00,
+    Div2 = 0b01,
+    Div4 = 0b10,
+    Div8 = 0b11,
+}
+
+/// Capture channel configuration
+#[derive(Clone, Copy, Debug)]
+pub struct CaptureConfig {
+    pub edge: Edge,
+    pub prescaler: Prescaler,
+    /// Input filter, from 0 for none up to 15 for 8 samples at PCLK / 32
+    pub filter: u8,
+}
+
+impl Default for CaptureConfig {
+    /// Every rising edge, unfiltered
+    fn default() -> Self {
+        Self {
+            edge: Edge::Rising,
+            prescaler: Prescaler::Div1,
+            filter: 0,
+        }
+    }
+}
+
+/// A timer capturing edges on the pins of pin set `R`
+pub struct Capture<TIM, R> {
+    tim: TIM,
+    freq: u32,
+    _remap: PhantomData<R>,
+}
+
+impl<TIM: Instance, R: Remap<Peripheral = TIM>> Capture<TIM, R> {
+    /// Start counting at `freq` Hz, wrapping around after 65536 ticks
+    ///
+    /// Rates out of range, including 0, give the closest one the timer can
+    /// reach.
+    pub fn new(tim: TIM, _remap: R, freq: u32, clocks: &Clocks) -> Self {
+        TIM::enable_and_reset();
+        afio::remap::<R>();
+
+        let regs = TIM::regs();
+        let psc = (clocks.pclk() / freq.max(1)).clamp(1, 0x1_0000) - 1;
+        regs.psc.write(|w| w.psc().bits(psc));
+        regs.swevgr.write(|w| w.ug().set_bit());
+        regs.intfr.write(|w| unsafe { w.bits(0) });
+        regs.ctlr1.modify(|_, w| w.cen().set_bit());
+
+        Self {
+            tim,
+            freq: clocks.pclk() / (psc + 1),
+            _remap: PhantomData,
+        }
+    }
+
+    /// The actual tick rate in Hz, after rounding to the timer clock
+    pub fn tick_frequency(&self) -> u32 {
+        self.freq
+    }
+
+    /// The current count
+    pub fn counter(&self) -> u16 {
+        TIM::regs().cnt.read().cnt().bits() as u16
+    }
+
+    /// Start capturing on a channel pin
+    pub fn channel<const C: u8, const P: char, const N: u8, MODE>(
+        &mut self,
+        pin: Pin<P, N, Input<MODE>>,
+        config: CaptureConfig,
+    ) -> CaptureChannel<TIM, C, Pin<P, N, Input<MODE>>>
+    where
+        Pin<P, N, Input<MODE>>: TimChannel<R, C>,
+    {
+        // CCxS = 01 maps the channel to its own pin
+        let filter = config.filter.min(15) as u32;
+        set_channel_mode::<TIM>(C, filter << 4 | (config.prescaler as u32) << 2 | 0b01);
+        let polarity = match config.edge {
+            Edge::Rising => 0b00,
+            Edge::Falling => 0b10,
+        };
+        set_channel_enable::<TIM>(C, 0b11, polarity | 0b01);
+        CaptureChannel {
+            pin,
+            _tim: PhantomData,
+        }
+    }
+
+    /// Stop the timer and give it back
+    pub fn release(self) -> TIM {
+        TIM::regs().ctlr1.reset();
+        self.tim
+    }
+}
+
+/// Captures of one timer channel
+pub struct CaptureChannel<TIM, const C: u8, PIN> {
+    pin: PIN,
+    _tim: PhantomData<TIM>,
+}
+
+impl<TIM: Instance, const C: u8, PIN> CaptureChannel<TIM, C, PIN> {
+    /// Take the latest capture if there is a new one
+    ///
+    /// When an earlier capture was overwritten the error is returned once,
+    /// and the latest capture can be read after it.
+    pub fn read(&mut self) -> nb::Result<u16, Error> {
+        let regs = TIM::regs();
+        let intfr = regs.intfr.read().bits();
+        let overcapture = 1 << (C + 8);
+        if intfr & overcapture != 0 {
+            // Flags are cleared by writing 0, ones leave them alone
+            regs.intfr.write(|w| unsafe { w.bits(!overcapture) });
+            Err(nb::Error::Other(Error::Overcapture))
+        } else if intfr & 1 << C != 0 {
+            // Reading the value clears CCxIF
+            Ok(regs.chcvr[C as usize - 1].read().chcvr().bits() as u16)
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// Whether a capture is waiting to be read
+    pub fn is_ready(&self) -> bool {
+        TIM::regs().intfr.read().bits() & 1 << C != 0
+    }
+
+    /// Raise the timer interrupt on every capture
+    pub fn enable_interrupt(&mut self) {
+        let regs = TIM::regs();
+        critical_section::with(|_| {
+            regs.dmaintenr
+                .modify(|r, w| unsafe { w.bits(r.bits() | 1 << C) })
+        });
+    }
+
+    pub fn disable_interrupt(&mut self) {
+        let regs = TIM::regs();
+        critical_section::with(|_| {
+            regs.dmaintenr
+                .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << C)) })
+        });
+    }
+
+    /// Stop capturing and give back the pin
+    pub fn release(mut self) -> PIN {
+        self.disable_interrupt();
+        set_channel_enable::<TIM>(C, 0b11, 0b00);
+        set_channel_mode::<TIM>(C, 0);
+        self.pin
+    }
+}
@@ -0,0 +1,334 @@
+//! PWM outputs
+//!
+//! All channels share the frequency of the timer and each has its own duty
+//! cycle, set through embedded-hal's [`SetDutyCycle`]. Compare values are
+//! preloaded, so a new duty cycle starts with the next period.
+//!
+//! On TIM1 a channel can drive its complementary pin as well, with dead-time
+//! inserted between the two:
+//!
+//! ```ignore
+//! let mut pwm = Pwm::new(p.TIM1, tim1::Remap0, 20_000, Counting::CenterAligned1, &clocks);
+//! pwm.set_dead_time(500);
+//! let high = gpiod.pd2.into_alternate_push_pull();
+//! let low = gpiod.pd0.into_alternate_push_pull();
+//! let mut phase = pwm.complementary_channel(high, low);
+//! phase.set_duty_cycle_fraction(1, 3).ok();
+//! ```
+
+use core::convert::Infallible;
+use core::marker::PhantomData;
+
+use embedded_hal::pwm::{ErrorType, SetDutyCycle};
+
+use super::{
+    prescale, set_channel_enable, set_channel_mode, Counting, Instance, Polarity, TriggerOutput,
+};
+use crate::afio::{self, tim1, Remap, TimChannel, TimChannelN};
+use crate::gpio::{Alternate, Pin};
+use crate::pac;
+use crate::rcc::Clocks;
+
+/// A pin driven by a timer channel
+type Output<const P: char, const N: u8, MODE> = Pin<P, N, Alternate<MODE>>;
+
+/// How the compare value shapes the output
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PwmMode {
+    /// Active from the start of the period until the compare value
+    Mode1 = 0b110,
+    /// Inactive until the compare value, then active until the end
+    Mode2 = 0b111,
+}
+
+/// A timer generating PWM on the pins of pin set `R`
+pub struct Pwm<TIM, R> {
+    tim: TIM,
+    clk: u32,
+    counting: Counting,
+    _remap: PhantomData<R>,
+}
+
+impl<TIM: Instance, R: Remap<Peripheral = TIM>> Pwm<TIM, R> {
+    /// Start the timer with a period of `freq` Hz
+    pub fn new(tim: TIM, _remap: R, freq: u32, counting: Counting, clocks: &Clocks) -> Self {
+        TIM::enable_and_reset();
+        afio::remap::<R>();
+
+        counting.apply(TIM::regs());
+        let mut pwm = Self {
+            tim,
+            clk: clocks.pclk(),
+            counting,
+            _remap: PhantomData,
+        };
+        pwm.set_frequency(freq);
+        TIM::regs()
+            .ctlr1
+            .modify(|_, w| w.arpe().set_bit().cen().set_bit());
+        TIM::enable_outputs();
+        pwm
+    }
+
+    /// Change the period to `freq` Hz
+    ///
+    /// The counter restarts, and duty cycles have to be set again as the
+    /// maximum duty cycle changes with the frequency. Frequencies out of
+    /// range, including 0, give the closest one the timer can reach.
+    pub fn set_frequency(&mut self, freq: u32) {
+        let regs = TIM::regs();
+        let ticks = self.clk / freq.max(1);
+        // Center aligned counting goes up and down once per period, taking
+        // twice the reload value instead of one more than it
+        let (psc, arr) = if self.counting.is_center_aligned() {
+            prescale(ticks / 2 + 1)
+        } else {
+            prescale(ticks)
+        };
+        regs.psc.write(|w| w.psc().bits(psc));
+        regs.atrlr.write(|w| w.atrlr().bits(arr));
+        // Load the prescaler and preloaded values now
+        regs.swevgr.write(|w| w.ug().set_bit());
+    }
+
+    /// The actual period in Hz, after rounding to the timer clock
+    pub fn frequency(&self) -> u32 {
+        let regs = TIM::regs();
+        let psc = regs.psc.read().psc().bits() + 1;
+        let arr = regs.atrlr.read().atrlr().bits();
+        let ticks = if self.counting.is_center_aligned() {
+            2 * arr
+        } else {
+            arr + 1
+        };
+        self.clk / psc / ticks
+    }
+
+    /// Start PWM mode 1 on a channel pin, with a 0% duty cycle
+    pub fn channel<const C: u8, const P: char, const N: u8, MODE>(
+        &mut self,
+        pin: Pin<P, N, Alternate<MODE>>,
+    ) -> PwmChannel<TIM, C, Pin<P, N, Alternate<MODE>>>
+    where
+        Pin<P, N, Alternate<MODE>>: TimChannel<R, C>,
+    {
+        PwmChannel::start(pin, 0b0001)
+    }
+
+    /// Stop the counter at the end of every period
+    ///
+    /// Each [`Pwm::trigger`] then runs a single period. With
+    /// [`PwmMode::Mode2`] a channel outputs one pulse, delayed by its duty
+    /// cycle.
+    pub fn set_one_pulse(&mut self, enabled: bool) {
+        TIM::regs()
+            .ctlr1
+            .modify(|_, w| w.opm().bit(enabled).cen().bit(!enabled));
+    }
+
+    /// Start the counter, for the next pulse in one-pulse mode
+    pub fn trigger(&mut self) {
+        TIM::regs().ctlr1.modify(|_, w| w.cen().set_bit());
+    }
+
+    /// Choose what is signalled to the ADC on TRGO
+    pub fn set_trigger_output(&mut self, trigger: TriggerOutput) {
+        TIM::regs()
+            .ctlr2
+            .modify(|_, w| w.mms().bits(trigger as u32));
+    }
+
+    /// Stop the timer and give it back
+    pub fn release(self) -> TIM {
+        TIM::regs().ctlr1.reset();
+        self.tim
+    }
+}
+
+impl<R: Remap<Peripheral = pac::TIM1>> Pwm<pac::TIM1, R> {
+    /// Start PWM mode 1 on a channel pin and its complementary pin, with a
+    /// 0% duty cycle
+    ///
+    /// The complementary pin is active whenever the channel pin is not,
+    /// apart from the dead-time.
+    pub fn complementary_channel<
+        const C: u8,
+        const P: char,
+        const N: u8,
+        M,
+        const PN: char,
+        const NN: u8,
+        MN,
+    >(
+        &mut self,
+        pin: Output<P, N, M>,
+        npin: Output<PN, NN, MN>,
+    ) -> PwmChannel<pac::TIM1, C, (Output<P, N, M>, Output<PN, NN, MN>)>
+    where
+        Output<P, N, M>: TimChannel<R, C>,
+        Output<PN, NN, MN>: TimChannelN<R, C>,
+    {
+        PwmChannel::start((pin, npin), 0b0101)
+    }
+
+    /// Delay turning on either pin of a complementary pair by `ns`
+    ///
+    /// Rounded down to the timer clock, up to 1008 clocks.
+    pub fn set_dead_time(&mut self, ns: u32) {
+        let ticks = (ns as u64 * self.clk as u64 / 1_000_000_000) as u32;
+        // Four ranges with increasing steps
+        let dtg = match ticks {
+            0..=127 => ticks,
+            128..=255 => 0b1000_0000 | (ticks / 2 - 64),
+            256..=511 => 0b1100_0000 | (ticks / 8 - 32),
+            512..=1023 => 0b1110_0000 | (ticks / 16 - 32),
+            _ => 0xFF,
+        };
+        tim1_regs().bdtr.modify(|_, w| w.dtg().bits(dtg));
+    }
+
+    /// Turn off all outputs while the break input is active
+    ///
+    /// With `auto_restart` the outputs come back at the first update after
+    /// the break ends, otherwise they stay off until
+    /// [`Pwm::resume_outputs`].
+    pub fn enable_break<PIN: tim1::Bkin<R>>(
+        &mut self,
+        pin: PIN,
+        polarity: Polarity,
+        auto_restart: bool,
+    ) -> BreakInput<PIN> {
+        let tim = tim1_regs();
+        tim.bdtr.modify(|_, w| {
+            w.bkp()
+                .bit(polarity == Polarity::ActiveHigh)
+                .aoe()
+                .bit(auto_restart)
+                .bke()
+                .set_bit()
+        });
+        // Break events while it was disabled do not count
+        tim.intfr.write(|w| unsafe { w.bits(!(1 << 7)) });
+        BreakInput { pin }
+    }
+
+    /// Whether the outputs were turned off by a break
+    pub fn is_broken(&self) -> bool {
+        tim1_regs().bdtr.read().moe().bit_is_clear()
+    }
+
+    /// Turn the outputs back on after a break
+    pub fn resume_outputs(&mut self) {
+        let tim = tim1_regs();
+        tim.intfr.write(|w| unsafe { w.bits(!(1 << 7)) });
+        tim.bdtr.modify(|_, w| w.moe().set_bit());
+    }
+
+    /// Only update the preloaded values every `count + 1` periods
+    ///
+    /// In center aligned counting both ends of the count are a period.
+    pub fn set_repetition(&mut self, count: u8) {
+        tim1_regs().rptcr.write(|w| w.rptcr().bits(count as u32));
+    }
+}
+
+fn tim1_regs() -> &'static pac::tim1::RegisterBlock {
+    unsafe { &*pac::TIM1::PTR }
+}
+
+/// The break input of TIM1, disabled when released
+pub struct BreakInput<PIN> {
+    pin: PIN,
+}
+
+impl<PIN> BreakInput<PIN> {
+    pub fn release(self) -> PIN {
+        tim1_regs().bdtr.modify(|_, w| w.bke().clear_bit());
+        self.pin
+    }
+}
+
+/// Output of one timer channel on its pin, or its pair of pins
+pub struct PwmChannel<TIM, const C: u8, PIN> {
+    pin: PIN,
+    /// CCER bits of the pins being driven
+    outputs: u32,
+    _tim: PhantomData<TIM>,
+}
+
+impl<TIM: Instance, const C: u8, PIN> PwmChannel<TIM, C, PIN> {
+    fn start(pin: PIN, outputs: u32) -> Self {
+        TIM::regs().chcvr[C as usize - 1].write(|w| w.chcvr().bits(0));
+        // OCxPE
+        set_channel_mode::<TIM>(C, (PwmMode::Mode1 as u32) << 4 | 1 << 3);
+        set_channel_enable::<TIM>(C, 0b1111, outputs);
+        Self {
+            pin,
+            outputs,
+            _tim: PhantomData,
+        }
+    }
+
+    /// Drive the pins from the timer
+    pub fn enable(&mut self) {
+        set_channel_enable::<TIM>(C, self.outputs, self.outputs);
+    }
+
+    /// Stop driving the pins, leaving them floating
+    pub fn disable(&mut self) {
+        set_channel_enable::<TIM>(C, self.outputs, 0);
+    }
+
+    pub fn set_mode(&mut self, mode: PwmMode) {
+        set_channel_mode::<TIM>(C, (mode as u32) << 4 | 1 << 3);
+    }
+
+    /// Choose the level driven during the duty cycle
+    pub fn set_polarity(&mut self, polarity: Polarity) {
+        let bits = match polarity {
+            Polarity::ActiveHigh => 0b00,
+            Polarity::ActiveLow => 0b10,
+        };
+        set_channel_enable::<TIM>(C, 0b0010, bits);
+    }
+
+    /// The current compare value
+    pub fn duty_cycle(&self) -> u16 {
+        TIM::regs().chcvr[C as usize - 1].read().chcvr().bits() as u16
+    }
+
+    /// Turn off the channel and give back the pin
+    pub fn release(self) -> PIN {
+        set_channel_enable::<TIM>(C, 0b1111, 0);
+        set_channel_mode::<TIM>(C, 0);
+        self.pin
+    }
+}
+
+impl<const C: u8, PIN> PwmChannel<pac::TIM1, C, PIN> {
+    /// Choose the level the complementary pin drives outside the duty cycle
+    pub fn set_complementary_polarity(&mut self, polarity: Polarity) {
+        let bits = match polarity {
+            Polarity::ActiveHigh => 0b0000,
+            Polarity::ActiveLow => 0b1000,
+        };
+        set_channel_enable::<pac::TIM1>(C, 0b1000, bits);
+    }
+}
+
+impl<TIM, const C: u8, PIN> ErrorType for PwmChannel<TIM, C, PIN> {
+    type Error = Infallible;
+}
+
+impl<TIM: Instance, const C: u8, PIN> SetDutyCycle for PwmChannel<TIM, C, PIN> {
+    /// One past the reload value, which keeps the output active throughout
+    fn max_duty_cycle(&self) -> u16 {
+        (TIM::regs().atrlr.read().atrlr().bits() as u16).saturating_add(1)
+    }
+
+    fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Self::Error> {
+        let duty = duty.min(self.max_duty_cycle());
+        TIM::regs().chcvr[C as usize - 1].write(|w| w.chcvr().bits(duty as u32));
+        Ok(())
+    }
+}
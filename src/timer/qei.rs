@@ -0,0 +1,109 @@
+//! Quadrature encoder interface
+//!
+//! Channels 1 and 2 take the A and B signals of an encoder, and the counter
+//! follows its position, wrapping around at both ends:
+//!
+//! ```ignore
+//! let a = gpiod.pd4.into_pull_up_input();
+//! let b = gpiod.pd3.into_pull_up_input();
+//! let qei = Qei::new(p.TIM2, (a, b), EncoderMode::BothEdges, 4);
+//! let position = qei.count();
+//! ```
+
+use super::Instance;
+use crate::afio::{self, Remap, TimChannel};
+use crate::gpio::{Input, Pin};
+
+/// Pins that can be used for the encoder, as `(A, B)` on channels 1 and 2 of
+/// the same pin set `R`
+pub trait Pins<R> {}
+
+impl<R, const PA: char, const NA: u8, MA, const PB: char, const NB: u8, MB> Pins<R>
+    for (Pin<PA, NA, Input<MA>>, Pin<PB, NB, Input<MB>>)
+where
+    Pin<PA, NA, Input<MA>>: TimChannel<R, 1>,
+    Pin<PB, NB, Input<MB>>: TimChannel<R, 2>,
+{
+}
+
+/// Edges that move the counter
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EncoderMode {
+    /// Edges of A on TI1, two counts per cycle
+    AEdges = 0b001,
+    /// Edges of B on TI2, two counts per cycle
+    BEdges = 0b010,
+    /// Edges of both, four counts per cycle
+    BothEdges = 0b011,
+}
+
+/// Direction the counter last moved in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Upcounting,
+    Downcounting,
+}
+
+/// A timer counting the steps of a quadrature encoder
+pub struct Qei<TIM, PINS> {
+    tim: TIM,
+    pins: PINS,
+}
+
+impl<TIM: Instance, PINS> Qei<TIM, PINS> {
+    /// Start counting from 0 on the pin set the pins belong to
+    ///
+    /// `filter` debounces both inputs, from 0 for none up to 15 for 8
+    /// samples at PCLK / 32.
+    pub fn new<R: Remap<Peripheral = TIM>>(
+        tim: TIM,
+        pins: PINS,
+        mode: EncoderMode,
+        filter: u8,
+    ) -> Self
+    where
+        PINS: Pins<R>,
+    {
+        TIM::enable_and_reset();
+        afio::remap::<R>();
+
+        let regs = TIM::regs();
+        // CC1S = 01 and CC2S = 01, both inputs on their own pins
+        let filter = filter.min(15) as u32;
+        regs.chctlr1
+            .write(|w| unsafe { w.bits((filter << 4 | 0b01) * 0x0101) });
+        regs.ccer.write(|w| w.cc1e().set_bit().cc2e().set_bit());
+        regs.smcfgr.write(|w| w.sms().bits(mode as u32));
+        regs.ctlr1.modify(|_, w| w.cen().set_bit());
+
+        Self { tim, pins }
+    }
+
+    /// The current position
+    pub fn count(&self) -> u16 {
+        TIM::regs().cnt.read().cnt().bits() as u16
+    }
+
+    /// Move the position to `count`
+    pub fn set_count(&mut self, count: u16) {
+        TIM::regs().cnt.write(|w| w.cnt().bits(count as u32));
+    }
+
+    pub fn direction(&self) -> Direction {
+        if TIM::regs().ctlr1.read().dir().bit_is_set() {
+            Direction::Downcounting
+        } else {
+            Direction::Upcounting
+        }
+    }
+
+    /// Stop counting and give back the timer and pins
+    pub fn release(self) -> (TIM, PINS) {
+        let regs = TIM::regs();
+        regs.ctlr1.reset();
+        regs.smcfgr.reset();
+        regs.ccer.reset();
+        regs.chctlr1.reset();
+        (self.tim, self.pins)
+    }
+}
@@ -0,0 +1,148 @@
+//! Independent and window watchdogs
+//!
+//! Both reset the chip unless they are fed in time, and can not be stopped
+//! once started. The independent watchdog runs from the 128 kHz LSI
+//! oscillator, so it keeps going even if the system clock fails:
+//!
+//! ```ignore
+//! if rcc::reset_cause() == ResetCause::IndependentWatchdog {
+//!     writeln!(serial, "recovered from a lockup").ok();
+//! }
+//! let mut watchdog = IndependentWatchdog::new(p.IWDG);
+//! watchdog.start(2_000);
+//! loop {
+//!     watchdog.feed();
+//!     // ...
+//! }
+//! ```
+//!
+//! The window watchdog counts PCLK and also resets the chip when it is fed
+//! too early, catching code that runs off into a tighter loop than expected.
+
+use crate::pac::{self, IWDG, WWDG};
+use crate::rcc::{self, Clocks, Enable};
+
+/// Frequency of the internal low speed RC oscillator
+pub const LSI_FREQ: u32 = 128_000;
+
+/// Keys written to the independent watchdog's CTLR
+const KEY_ACCESS: u32 = 0x5555;
+const KEY_RELOAD: u32 = 0xAAAA;
+const KEY_START: u32 = 0xCCCC;
+
+/// Independent watchdog, clocked by LSI
+pub struct IndependentWatchdog {
+    iwdg: IWDG,
+}
+
+impl IndependentWatchdog {
+    pub fn new(iwdg: IWDG) -> Self {
+        Self { iwdg }
+    }
+
+    /// Longest timeout that can be set, in milliseconds
+    pub const MAX_TIMEOUT_MS: u32 = (4 << 6) * 0x1000 / (LSI_FREQ / 1_000);
+
+    /// Start the watchdog, resetting the chip unless it is fed at least
+    /// every `ms` milliseconds
+    ///
+    /// Starting it again only changes the timeout.
+    pub fn start(&mut self, ms: u32) {
+        rcc::enable_lsi();
+
+        // Clock divided by 4 << PR, counting down from RL
+        let ticks = ms.clamp(1, Self::MAX_TIMEOUT_MS) * (LSI_FREQ / 1_000) / 4;
+        let pr = (0..6).find(|pr| ticks >> pr <= 0x1000).unwrap_or(6);
+        let rl = ((ticks >> pr).max(1) - 1).min(0xFFF);
+
+        self.iwdg.ctlr.write(|w| w.key().bits(KEY_START));
+        self.iwdg.ctlr.write(|w| w.key().bits(KEY_ACCESS));
+        // Writes only take effect once the previous update reached the
+        // LSI domain
+        while self.iwdg.statr.read().pvu().bit_is_set() {}
+        self.iwdg.pscr.write(|w| w.pr().bits(pr));
+        while self.iwdg.statr.read().rvu().bit_is_set() {}
+        self.iwdg.rldr.write(|w| w.rl().bits(rl));
+        self.feed();
+    }
+
+    /// Restart the countdown
+    pub fn feed(&mut self) {
+        self.iwdg.ctlr.write(|w| w.key().bits(KEY_RELOAD));
+    }
+}
+
+/// Window watchdog, clocked by PCLK / 4096
+pub struct WindowWatchdog {
+    wwdg: WWDG,
+    pclk: u32,
+    /// Counter value written on every feed
+    reload: u32,
+}
+
+impl WindowWatchdog {
+    pub fn new(wwdg: WWDG, clocks: &Clocks) -> Self {
+        WWDG::enable();
+        Self {
+            wwdg,
+            pclk: clocks.pclk(),
+            reload: 0x7F,
+        }
+    }
+
+    /// Longest timeout that can be set, in microseconds
+    pub fn max_timeout_us(&self) -> u32 {
+        ((4096u64 << 3) * 64 * 1_000_000 / self.pclk as u64) as u32
+    }
+
+    /// Start the watchdog, resetting the chip unless it is fed at least
+    /// every `timeout_us`, but no earlier than `window_us` before that
+    ///
+    /// Passing the timeout as window allows feeding at any time. Starting it
+    /// again changes both.
+    pub fn start(&mut self, timeout_us: u32, window_us: u32) {
+        // Counts down from the reload value and resets below 0x40
+        let ticks = |us: u32, tb: u32| {
+            ((us as u64 * self.pclk as u64).div_ceil((4096u64 << tb) * 1_000_000)) as u32
+        };
+        let tb = (0..3).find(|&tb| ticks(timeout_us, tb) <= 64).unwrap_or(3);
+        let timeout = ticks(timeout_us, tb).clamp(1, 64);
+        let window = ticks(window_us, tb).clamp(1, timeout);
+
+        self.reload = 0x3F + timeout;
+        self.wwdg
+            .cfgr
+            .modify(|_, w| w.w().bits(0x3F + window).wdgtb().bits(tb));
+        self.wwdg
+            .ctlr
+            .write(|w| w.t().bits(self.reload).wdga().set_bit());
+    }
+
+    /// Restart the countdown, which resets the chip if done before the
+    /// window opened
+    pub fn feed(&mut self) {
+        self.wwdg
+            .ctlr
+            .write(|w| w.t().bits(self.reload).wdga().set_bit());
+    }
+
+    /// Raise the WWDG interrupt one tick before the reset
+    ///
+    /// It stays enabled until the next reset. The handler can feed the
+    /// watchdog, or save state before the reset.
+    pub fn enable_early_wakeup(&mut self) {
+        self.wwdg.cfgr.modify(|_, w| w.ewi().set_bit());
+    }
+
+    /// Whether the early wakeup interrupt fired, for use in the handler
+    pub fn is_pending() -> bool {
+        let wwdg = unsafe { &*pac::WWDG::PTR };
+        wwdg.statr.read().ewif().bit_is_set()
+    }
+
+    /// Clear the early wakeup flag, for use in the handler
+    pub fn unpend() {
+        let wwdg = unsafe { &*pac::WWDG::PTR };
+        wwdg.statr.write(|w| w.ewif().clear_bit());
+    }
+}